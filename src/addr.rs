@@ -11,13 +11,17 @@
 
 //! SocketCAN address type.
 
-use libc::{sa_family_t, sockaddr, sockaddr_can, sockaddr_storage, socklen_t};
+use libc::{canid_t, sa_family_t, sockaddr, sockaddr_can, sockaddr_storage, socklen_t};
 use nix::net::if_::if_nametoindex;
 use socket2::SockAddr;
 use std::{fmt, io, mem, os::raw::c_int};
 
 pub use libc::{AF_CAN, CAN_RAW, PF_CAN};
 
+// The offset of the protocol-specific `can_addr` union in `sockaddr_can`,
+// which is 8-byte aligned.
+const TP_OFFSET: usize = 8;
+
 /// CAN socket address.
 ///
 /// This is the address for use with CAN sockets. It is simply an addres to
@@ -47,6 +51,44 @@ impl CanAddr {
         Ok(Self::new(ifindex))
     }
 
+    /// Creates a new address for a transport protocol socket, such as
+    /// ISO-TP, on the specified interface.
+    ///
+    /// The transport protocols use the receive and transmit CAN IDs as part
+    /// of the address. These are the raw `canid_t` values, with the
+    /// `CAN_EFF_FLAG` bit set for extended IDs.
+    pub fn new_tp(ifindex: u32, rx_id: canid_t, tx_id: canid_t) -> Self {
+        let mut addr = Self::new(ifindex);
+        let tp = &mut crate::as_bytes_mut(&mut addr.0)[TP_OFFSET..TP_OFFSET + 8];
+        tp[..4].copy_from_slice(&rx_id.to_ne_bytes());
+        tp[4..].copy_from_slice(&tx_id.to_ne_bytes());
+        addr
+    }
+
+    /// Creates a new address for a J1939 socket on the specified interface.
+    ///
+    /// The J1939 address is made up of the 64-bit NAME of the ECU, a
+    /// parameter group number (PGN), and an 8-bit address.
+    pub fn new_j1939(ifindex: u32, name: u64, pgn: u32, addr: u8) -> Self {
+        let mut caddr = Self::new(ifindex);
+        let j1939 = &mut crate::as_bytes_mut(&mut caddr.0)[TP_OFFSET..TP_OFFSET + 13];
+        j1939[..8].copy_from_slice(&name.to_ne_bytes());
+        j1939[8..12].copy_from_slice(&pgn.to_ne_bytes());
+        j1939[12] = addr;
+        caddr
+    }
+
+    /// Gets the J1939 parts of the address, as the NAME, PGN, and 8-bit
+    /// address.
+    ///
+    /// This is only meaningful for an address used with a J1939 socket.
+    pub fn j1939(&self) -> (u64, u32, u8) {
+        let j1939 = &self.as_bytes()[TP_OFFSET..TP_OFFSET + 13];
+        let name = u64::from_ne_bytes(j1939[..8].try_into().unwrap());
+        let pgn = u32::from_ne_bytes(j1939[8..12].try_into().unwrap());
+        (name, pgn, j1939[12])
+    }
+
     /// Gets the address of the structure as a `sockaddr_can` pointer.
     pub fn as_ptr(&self) -> *const sockaddr_can {
         &self.0
@@ -149,4 +191,25 @@ mod tests {
         assert_eq!(CanAddr::len() as socklen_t, len);
         assert_eq!(as_bytes(&addr), &as_bytes(&sock_addr)[0..len as usize]);
     }
+
+    #[test]
+    fn test_tp_addr() {
+        let addr = CanAddr::new_tp(IDX, 0x7E8, 0x7E0);
+        let bytes = addr.as_bytes();
+
+        assert_eq!(&0x7E8u32.to_ne_bytes(), &bytes[8..12]);
+        assert_eq!(&0x7E0u32.to_ne_bytes(), &bytes[12..16]);
+        assert_eq!(as_bytes(&CanAddr::new(IDX))[..8], bytes[..8]);
+    }
+
+    #[test]
+    fn test_j1939_addr() {
+        let addr = CanAddr::new_j1939(IDX, 0x1122_3344_5566_7788, 0xFEF1, 0x80);
+        let bytes = addr.as_bytes();
+
+        assert_eq!(&0x1122_3344_5566_7788u64.to_ne_bytes(), &bytes[8..16]);
+        assert_eq!(&0xFEF1u32.to_ne_bytes(), &bytes[16..20]);
+        assert_eq!(0x80, bytes[20]);
+        assert_eq!((0x1122_3344_5566_7788, 0xFEF1, 0x80), addr.j1939());
+    }
 }
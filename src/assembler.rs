@@ -0,0 +1,395 @@
+// socketcan/src/assembler.rs
+//
+// Reassembly of multi-frame messages with sequence numbers.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Reassembly of multi-frame messages with sequence numbers.
+//!
+//! Many application protocols send a message that is too large for a
+//! single frame by splitting it across several consecutive frames with
+//! the same ID, numbering each with a sequence counter in one of the data
+//! bytes. The first frame of a message also carries the total length of
+//! the message.
+//!
+//! The [`SeqAssembler`] is configured with the layout of the frames, and
+//! is fed the received frames, returning the complete message once all of
+//! its frames arrive.
+//!
+//! ```
+//! use socketcan::{assembler::LengthField, CanFrame, EmbeddedFrame, SeqAssembler, StandardId};
+//!
+//! let id = StandardId::new(0x100).unwrap();
+//!
+//! // Sequence number in byte 0, with the length in byte 1 of the first frame.
+//! let mut asm = SeqAssembler::new(id, LengthField::U8(1));
+//!
+//! let frame = CanFrame::new(id, &[0, 10, 1, 2, 3, 4, 5, 6]).unwrap();
+//! assert!(asm.push(&frame).is_none());
+//!
+//! let frame = CanFrame::new(id, &[1, 7, 8, 9, 10]).unwrap();
+//! let msg = asm.push(&frame).unwrap().unwrap();
+//! assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10], msg);
+//! ```
+
+use crate::{Frame, Id};
+use std::{error, fmt};
+
+/// The default limit on the length of a message.
+pub const DEFAULT_MAX_LEN: usize = 4096;
+
+// ===== LengthField =====
+
+/// How the total length of a message is given.
+///
+/// The position of a length field is the index of its first byte in the
+/// data of the first frame of the message. The bytes of the length field
+/// are not part of the message data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthField {
+    /// A single byte in the first frame
+    U8(usize),
+    /// A big-endian 16-bit length in the first frame
+    U16Be(usize),
+    /// A little-endian 16-bit length in the first frame
+    U16Le(usize),
+    /// All messages have the same, fixed, length
+    Fixed(usize),
+}
+
+impl LengthField {
+    // Gets the byte range of the field in the first frame, if any.
+    fn range(&self) -> Option<(usize, usize)> {
+        match *self {
+            LengthField::U8(pos) => Some((pos, pos + 1)),
+            LengthField::U16Be(pos) | LengthField::U16Le(pos) => Some((pos, pos + 2)),
+            LengthField::Fixed(_) => None,
+        }
+    }
+
+    // Reads the length from the data of the first frame.
+    fn read(&self, data: &[u8]) -> Option<usize> {
+        match *self {
+            LengthField::U8(pos) => data.get(pos).map(|&n| n as usize),
+            LengthField::U16Be(pos) => data
+                .get(pos..pos + 2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize),
+            LengthField::U16Le(pos) => data
+                .get(pos..pos + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize),
+            LengthField::Fixed(n) => Some(n),
+        }
+    }
+}
+
+// ===== AssemblyError =====
+
+/// An error reassembling a message.
+///
+/// Any error discards the message that was in progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssemblyError {
+    /// A frame arrived with a sequence number other than the next one
+    /// expected, meaning that frames were lost or reordered.
+    Gap {
+        /// The sequence number that was expected
+        expected: u8,
+        /// The sequence number that was received
+        received: u8,
+    },
+    /// A frame was too short to hold the sequence number, or the first
+    /// frame was too short to hold the length.
+    Malformed,
+    /// The length of the message is more than the limit.
+    TooLong(usize),
+}
+
+impl error::Error for AssemblyError {}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use AssemblyError::*;
+        match *self {
+            Gap { expected, received } => write!(
+                f,
+                "sequence gap: expected {}, received {}",
+                expected, received
+            ),
+            Malformed => write!(f, "malformed frame"),
+            TooLong(n) => write!(f, "message too long ({} bytes)", n),
+        }
+    }
+}
+
+// ===== SeqAssembler =====
+
+/// Reassembles messages split across frames with sequence numbers.
+///
+/// The assembler only looks at frames with its ID; any others are
+/// ignored. Each frame has a sequence number in the byte at the
+/// configured position (byte 0 by default), and the message data is the
+/// rest of the frame's data. The first frame of a message has the start
+/// sequence number (0 by default), and carries the total length of the
+/// message in its [`LengthField`]. Each following frame has the next
+/// sequence number, which wraps around within the sequence mask (0xFF by
+/// default, for a full byte). A message is complete once the data of all
+/// its frames reaches the total length, and any extra bytes in the last
+/// frame are discarded as padding.
+///
+/// While waiting for the first frame of a message, frames with any other
+/// sequence number are silently ignored. Once a message is in progress,
+/// a frame with any sequence number but the next one is a gap. The message
+/// is discarded, and an [`AssemblyError::Gap`] is returned. If the frame
+/// that broke the sequence is itself the first frame of a message, a new
+/// message is started with it, although a message that fits entirely in
+/// that one frame is lost. Otherwise the assembler waits for the next
+/// first frame.
+#[derive(Debug, Clone)]
+pub struct SeqAssembler {
+    id: Id,
+    length: LengthField,
+    seq_pos: usize,
+    start_seq: u8,
+    seq_mask: u8,
+    max_len: usize,
+    // The message in progress: its length, next sequence number, and data
+    msg: Option<(usize, u8, Vec<u8>)>,
+}
+
+impl SeqAssembler {
+    /// Creates an assembler for the messages sent with the ID, with the
+    /// total length given in the specified way.
+    pub fn new(id: impl Into<Id>, length: LengthField) -> Self {
+        Self {
+            id: id.into(),
+            length,
+            seq_pos: 0,
+            start_seq: 0,
+            seq_mask: 0xFF,
+            max_len: DEFAULT_MAX_LEN,
+            msg: None,
+        }
+    }
+
+    /// Sets the position of the sequence number in the frame data.
+    pub fn seq_pos(mut self, pos: usize) -> Self {
+        self.seq_pos = pos;
+        self
+    }
+
+    /// Sets the sequence number of the first frame of a message.
+    pub fn start_seq(mut self, seq: u8) -> Self {
+        self.start_seq = seq;
+        self
+    }
+
+    /// Sets the mask for the bits of the sequence byte that hold the
+    /// sequence number, like 0x0F for a 4-bit counter in the low bits.
+    ///
+    /// The other bits of the byte are ignored.
+    pub fn seq_mask(mut self, mask: u8) -> Self {
+        self.seq_mask = mask;
+        self
+    }
+
+    /// Sets the limit on the length of a message.
+    ///
+    /// A first frame with a longer length is rejected with
+    /// [`AssemblyError::TooLong`]. The default is [`DEFAULT_MAX_LEN`].
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Gets the ID of the frames that the assembler accepts.
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    /// Determines if a message is partly assembled.
+    pub fn in_progress(&self) -> bool {
+        self.msg.is_some()
+    }
+
+    /// Reports a received frame.
+    ///
+    /// This returns the message once it is complete, or an error if the
+    /// frame breaks the message in progress. It returns `None` if the
+    /// frame was ignored, or if more frames are needed.
+    pub fn push<F: Frame>(&mut self, frame: &F) -> Option<Result<Vec<u8>, AssemblyError>> {
+        if frame.id() != self.id || frame.is_remote_frame() || frame.is_error_frame() {
+            return None;
+        }
+
+        let data = frame.data();
+        let seq = match data.get(self.seq_pos) {
+            Some(&seq) => seq & self.seq_mask,
+            None => {
+                return self.msg.take().map(|_| Err(AssemblyError::Malformed));
+            }
+        };
+        let start = self.start_seq & self.seq_mask;
+
+        match self.msg.take() {
+            Some((len, expected, mut buf)) if seq == expected => {
+                buf.extend(
+                    data.iter()
+                        .enumerate()
+                        .filter(|(i, _)| *i != self.seq_pos)
+                        .map(|(_, b)| *b),
+                );
+                self.complete(len, seq, buf)
+            }
+            Some((_, expected, _)) => {
+                if seq == start {
+                    // The new message can only fail or finish in this
+                    // frame, in which case the gap is the more important
+                    // thing to report.
+                    let _ = self.first_frame(data, seq);
+                }
+                Some(Err(AssemblyError::Gap {
+                    expected,
+                    received: seq,
+                }))
+            }
+            None if seq == start => self.first_frame(data, seq),
+            None => None,
+        }
+    }
+
+    // Starts a new message from its first frame.
+    fn first_frame(&mut self, data: &[u8], seq: u8) -> Option<Result<Vec<u8>, AssemblyError>> {
+        let len = match self.length.read(data) {
+            Some(len) => len,
+            None => return Some(Err(AssemblyError::Malformed)),
+        };
+        if len > self.max_len {
+            return Some(Err(AssemblyError::TooLong(len)));
+        }
+
+        let field = self.length.range();
+        let buf = data
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                *i != self.seq_pos && !field.map_or(false, |(a, b)| (a..b).contains(i))
+            })
+            .map(|(_, b)| *b)
+            .collect();
+        self.complete(len, seq, buf)
+    }
+
+    // Finishes the message if all the data is in, otherwise saves it and
+    // waits for the next frame.
+    fn complete(
+        &mut self,
+        len: usize,
+        seq: u8,
+        mut buf: Vec<u8>,
+    ) -> Option<Result<Vec<u8>, AssemblyError>> {
+        if buf.len() >= len {
+            buf.truncate(len);
+            Some(Ok(buf))
+        } else {
+            let next = seq.wrapping_add(1) & self.seq_mask;
+            self.msg = Some((len, next, buf));
+            None
+        }
+    }
+
+    /// Discards any message in progress.
+    pub fn reset(&mut self) {
+        self.msg = None;
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CanFrame, EmbeddedFrame, StandardId};
+
+    fn frame(id: u16, data: &[u8]) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), data).unwrap()
+    }
+
+    #[test]
+    fn test_assemble() {
+        let id = StandardId::new(0x100).unwrap();
+        let mut asm = SeqAssembler::new(id, LengthField::U16Be(1));
+
+        // Ignored until the first frame
+        assert!(asm.push(&frame(0x100, &[1, 1, 2])).is_none());
+        assert!(asm.push(&frame(0x100, &[0, 0, 9, 1, 2, 3, 4, 5])).is_none());
+        assert!(asm.in_progress());
+
+        // Other IDs are ignored
+        assert!(asm.push(&frame(0x101, &[1, 0xFF])).is_none());
+
+        let msg = asm.push(&frame(0x100, &[1, 6, 7, 8, 9, 0, 0])).unwrap();
+        assert_eq!(Ok(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]), msg);
+        assert!(!asm.in_progress());
+
+        // A single-frame message
+        let msg = asm.push(&frame(0x100, &[0, 0, 2, 0xA, 0xB])).unwrap();
+        assert_eq!(Ok(vec![0xA, 0xB]), msg);
+    }
+
+    #[test]
+    fn test_gaps() {
+        let id = StandardId::new(0x100).unwrap();
+        let mut asm = SeqAssembler::new(id, LengthField::Fixed(14))
+            .seq_pos(7)
+            .start_seq(1)
+            .seq_mask(0x0F);
+
+        assert!(asm
+            .push(&frame(0x100, &[1, 2, 3, 4, 5, 6, 7, 0x01]))
+            .is_none());
+        let res = asm.push(&frame(0x100, &[0, 0, 0, 0, 0, 0, 0, 0x13]));
+        assert_eq!(
+            Some(Err(AssemblyError::Gap {
+                expected: 2,
+                received: 3
+            })),
+            res
+        );
+        assert!(!asm.in_progress());
+
+        // A new first frame in the middle restarts the message
+        assert!(asm
+            .push(&frame(0x100, &[1, 2, 3, 4, 5, 6, 7, 0x01]))
+            .is_none());
+        let res = asm.push(&frame(0x100, &[1, 2, 3, 4, 5, 6, 7, 0xF1]));
+        assert!(matches!(res, Some(Err(AssemblyError::Gap { .. }))));
+        assert!(asm.in_progress());
+
+        let res = asm.push(&frame(0x100, &[8, 9, 10, 11, 12, 13, 14, 0x02]));
+        assert_eq!(
+            Some(Ok(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14])),
+            res
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let id = StandardId::new(0x100).unwrap();
+        let mut asm = SeqAssembler::new(id, LengthField::U16Le(1)).max_len(100);
+
+        assert_eq!(
+            Some(Err(AssemblyError::Malformed)),
+            asm.push(&frame(0x100, &[0, 1]))
+        );
+        assert_eq!(
+            Some(Err(AssemblyError::TooLong(0x200))),
+            asm.push(&frame(0x100, &[0, 0, 2]))
+        );
+        assert!(!asm.in_progress());
+    }
+}
@@ -11,7 +11,7 @@
 
 //! Bindings to async-io for CANbus 2.0 and FD sockets using SocketCAN on Linux.
 
-use crate::{frame::AsPtr, CanAnyFrame, CanFrame, Socket, SocketOptions};
+use crate::{frame::AsPtr, CanAnyFrame, CanFrame, CanMsg, Socket, SocketOptions};
 use std::{
     io,
     os::unix::io::{AsRawFd, RawFd},
@@ -53,6 +53,16 @@ impl CanSocket {
     pub async fn read_frame(&self) -> io::Result<CanFrame> {
         self.0.read_with(|fd| fd.read_frame()).await
     }
+
+    /// Reads a frame from the socket asynchronously, along with all the
+    /// metadata that the kernel reports with it.
+    ///
+    /// This is the same as the blocking [`crate::CanSocket::read_msg`], and
+    /// the timestamp and dropped count are enabled on the socket in the
+    /// same way.
+    pub async fn read_msg(&self) -> io::Result<CanMsg> {
+        self.0.read_with(|fd| fd.read_msg()).await
+    }
 }
 
 impl SocketOptions for CanSocket {}
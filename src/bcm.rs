@@ -0,0 +1,359 @@
+// socketcan/src/bcm.rs
+//
+// The CAN Broadcast Manager (BCM) socket.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! The CAN Broadcast Manager (BCM) socket.
+//!
+//! The Broadcast Manager is a kernel protocol that can take over two
+//! common, timing-sensitive, jobs from an application:
+//!
+//! - Periodic transmission of a frame, such as a 100ms heartbeat or status
+//!   message, driven by a kernel timer rather than a user-space loop.
+//! - Receive filtering on the content of frames, so that the application
+//!   is only woken up when the data of a frame changes.
+//!
+//! Each job is set up by writing a message to the socket, consisting of a
+//! `bcm_msg_head` header followed by zero or more CAN frames. A job keeps
+//! running until it is deleted, or until the socket is closed.
+//!
+//! ```no_run
+//! use socketcan::{CanBcmSocket, CanDataFrame, EmbeddedFrame, StandardId};
+//! use std::time::Duration;
+//!
+//! let sock = CanBcmSocket::open("vcan0").unwrap();
+//!
+//! let id = StandardId::new(0x100).unwrap();
+//! let frame = CanDataFrame::new(id, &[0x01, 0x02]).unwrap();
+//! sock.send_periodic(id, &frame, Duration::from_millis(100)).unwrap();
+//! ```
+
+use crate::{
+    as_bytes, as_bytes_mut,
+    frame::{can_frame_default, id_to_canid_t, AsPtr},
+    CanAddr, CanDataFrame, CanFrame, EmbeddedFrame, Id, IoErrorKind, IoResult,
+};
+use libc::{c_int, c_long, can_frame, canid_t, AF_CAN};
+use socket2::SockAddr;
+use std::{
+    io::{Read, Write},
+    mem,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, RawFd},
+    time::Duration,
+};
+
+/// The protocol number for a Broadcast Manager socket.
+pub const CAN_BCM: c_int = 2;
+
+// Operation codes for the message header, from linux/can/bcm.h
+
+/// Create or update a transmission task
+pub const TX_SETUP: u32 = 1;
+/// Remove a transmission task
+pub const TX_DELETE: u32 = 2;
+/// Create or update a receive filter
+pub const RX_SETUP: u32 = 5;
+/// Remove a receive filter
+pub const RX_DELETE: u32 = 6;
+/// A received frame whose content changed (sent by the kernel)
+pub const RX_CHANGED: u32 = 12;
+
+// Flags for the message header, from linux/can/bcm.h
+
+/// Set the timer intervals from the message
+pub const SETTIMER: u32 = 0x0001;
+/// Start the timer with the intervals from the message
+pub const STARTTIMER: u32 = 0x0002;
+/// Send the frame immediately when the task is set up
+pub const TX_ANNOUNCE: u32 = 0x0008;
+/// Copy the ID from the header into the frames
+pub const TX_CP_CAN_ID: u32 = 0x0010;
+/// Filter by ID only, without a content mask
+pub const RX_FILTER_ID: u32 = 0x0020;
+
+/// The `struct bcm_timeval` from linux/can/bcm.h
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct BcmTimeval {
+    tv_sec: c_long,
+    tv_usec: c_long,
+}
+
+impl From<Duration> for BcmTimeval {
+    fn from(dur: Duration) -> Self {
+        Self {
+            tv_sec: dur.as_secs() as c_long,
+            tv_usec: dur.subsec_micros() as c_long,
+        }
+    }
+}
+
+/// The `struct bcm_msg_head` from linux/can/bcm.h
+///
+/// In the kernel header this ends with a flexible array of frames. Here it
+/// is a zero-length array, which gives the header the same alignment, so
+/// that the frames start at the same offset as in the kernel.
+///
+/// On 64-bit platforms the kernel struct has 4 bytes of implicit padding
+/// before the first interval. That is made explicit here so that every
+/// byte of the header is initialized when it is sent as a byte slice.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct BcmMsgHead {
+    opcode: u32,
+    flags: u32,
+    count: u32,
+    #[cfg(target_pointer_width = "64")]
+    _pad: u32,
+    ival1: BcmTimeval,
+    ival2: BcmTimeval,
+    can_id: canid_t,
+    nframes: u32,
+    frames: [can_frame; 0],
+}
+
+impl BcmMsgHead {
+    fn new(opcode: u32, flags: u32, can_id: canid_t) -> Self {
+        Self {
+            opcode,
+            flags,
+            count: 0,
+            #[cfg(target_pointer_width = "64")]
+            _pad: 0,
+            ival1: BcmTimeval::default(),
+            ival2: BcmTimeval::default(),
+            can_id,
+            nframes: 0,
+            frames: [],
+        }
+    }
+}
+
+/// A BCM message with a single frame.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct BcmMsg {
+    head: BcmMsgHead,
+    frame: can_frame,
+}
+
+impl BcmMsg {
+    fn new(head: BcmMsgHead, frame: can_frame) -> Self {
+        Self {
+            head: BcmMsgHead { nframes: 1, ..head },
+            frame,
+        }
+    }
+}
+
+// ===== CanBcmSocket =====
+
+/// A socket for the CAN Broadcast Manager.
+///
+/// See the [module documentation](crate::bcm) for details.
+#[derive(Debug)]
+pub struct CanBcmSocket(socket2::Socket);
+
+impl CanBcmSocket {
+    /// Opens a BCM socket on the named CAN device, such as "can0".
+    pub fn open(ifname: &str) -> IoResult<Self> {
+        let addr = CanAddr::from_iface(ifname)?;
+        Self::open_addr(&addr)
+    }
+
+    /// Opens a BCM socket by kernel interface number.
+    pub fn open_iface(ifindex: u32) -> IoResult<Self> {
+        let addr = CanAddr::new(ifindex);
+        Self::open_addr(&addr)
+    }
+
+    /// Opens a BCM socket by address.
+    ///
+    /// Unlike a RAW socket, a BCM socket is connected to the interface,
+    /// rather than bound to it.
+    pub fn open_addr(addr: &CanAddr) -> IoResult<Self> {
+        let af_can = socket2::Domain::from(AF_CAN);
+        let can_bcm = socket2::Protocol::from(CAN_BCM);
+
+        let sock = socket2::Socket::new(af_can, socket2::Type::DGRAM, Some(can_bcm))?;
+        sock.connect(&SockAddr::from(*addr))?;
+        Ok(Self(sock))
+    }
+
+    /// Change socket to non-blocking mode or back to blocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> IoResult<()> {
+        self.0.set_nonblocking(nonblocking)
+    }
+
+    /// Starts sending the frame periodically, with the specified ID.
+    ///
+    /// This sets up a kernel transmission task (`TX_SETUP`) that sends the
+    /// frame immediately, and then once every `interval`, until the task
+    /// is removed with [`remove_periodic`](Self::remove_periodic), or the
+    /// socket is closed. The ID replaces the one in the frame.
+    ///
+    /// There is one task per ID, so calling this again for the same ID
+    /// updates the data and interval of the running task.
+    ///
+    /// The interval must not be zero, or this returns an `InvalidInput`
+    /// error.
+    pub fn send_periodic(
+        &self,
+        id: impl Into<Id>,
+        frame: &CanDataFrame,
+        interval: Duration,
+    ) -> IoResult<()> {
+        if interval.is_zero() {
+            return Err(IoErrorKind::InvalidInput.into());
+        }
+
+        let mut head = BcmMsgHead::new(
+            TX_SETUP,
+            SETTIMER | STARTTIMER | TX_ANNOUNCE | TX_CP_CAN_ID,
+            id_to_canid_t(id),
+        );
+        head.ival2 = interval.into();
+
+        // SAFETY: The pointer is to the frame's own C struct.
+        let frame = unsafe { *frame.as_ptr() };
+        self.write_msg(&BcmMsg::new(head, frame))
+    }
+
+    /// Stops the periodic transmission of frames with the specified ID.
+    ///
+    /// This removes the kernel transmission task (`TX_DELETE`) that was
+    /// set up with [`send_periodic`](Self::send_periodic).
+    pub fn remove_periodic(&self, id: impl Into<Id>) -> IoResult<()> {
+        let head = BcmMsgHead::new(TX_DELETE, 0, id_to_canid_t(id));
+        self.write_all(as_bytes(&head))
+    }
+
+    /// Sets up a receive filter that reports changes to frames with the
+    /// specified ID.
+    ///
+    /// This sets up a kernel receive filter (`RX_SETUP`). The `mask` holds
+    /// the bits of the data bytes that are of interest. A received frame is
+    /// only passed up to the application, through
+    /// [`read_frame`](Self::read_frame), when any of those bits differ from
+    /// the last frame that was passed up. The first frame with the ID is
+    /// always passed up.
+    ///
+    /// With an empty mask, the content is not checked, and every frame with
+    /// the ID is passed up. The mask can not be longer than eight bytes,
+    /// or this returns an `InvalidInput` error.
+    pub fn filter_rx_changes(&self, id: impl Into<Id>, mask: &[u8]) -> IoResult<()> {
+        let id = id.into();
+        if mask.is_empty() {
+            let head = BcmMsgHead::new(RX_SETUP, RX_FILTER_ID, id_to_canid_t(id));
+            return self.write_all(as_bytes(&head));
+        }
+
+        let frame = CanDataFrame::new(id, mask).ok_or(IoErrorKind::InvalidInput)?;
+        let head = BcmMsgHead::new(RX_SETUP, 0, id_to_canid_t(id));
+
+        // SAFETY: The pointer is to the frame's own C struct.
+        let frame = unsafe { *frame.as_ptr() };
+        self.write_msg(&BcmMsg::new(head, frame))
+    }
+
+    /// Removes the receive filter for the specified ID.
+    pub fn remove_rx_filter(&self, id: impl Into<Id>) -> IoResult<()> {
+        let head = BcmMsgHead::new(RX_DELETE, 0, id_to_canid_t(id));
+        self.write_all(as_bytes(&head))
+    }
+
+    /// Reads the next frame passed up by one of the receive filters.
+    ///
+    /// Messages from the kernel that don't carry a frame, such as timeout
+    /// notifications, are skipped.
+    pub fn read_frame(&self) -> IoResult<CanFrame> {
+        let head_len = mem::size_of::<BcmMsgHead>();
+        loop {
+            let mut msg = BcmMsg::new(BcmMsgHead::new(0, 0, 0), can_frame_default());
+            let n = (&self.0).read(as_bytes_mut(&mut msg))?;
+
+            if n < head_len {
+                return Err(IoErrorKind::InvalidData.into());
+            }
+            if msg.head.opcode == RX_CHANGED
+                && msg.head.nframes > 0
+                && n >= mem::size_of::<BcmMsg>()
+            {
+                return Ok(msg.frame.into());
+            }
+        }
+    }
+
+    /// Writes a message with a single frame to the socket.
+    fn write_msg(&self, msg: &BcmMsg) -> IoResult<()> {
+        self.write_all(as_bytes(msg))
+    }
+
+    /// Writes a whole message to the socket.
+    ///
+    /// The kernel takes each message in a single write, so a short write
+    /// is an error.
+    fn write_all(&self, buf: &[u8]) -> IoResult<()> {
+        match (&self.0).write(buf)? {
+            n if n == buf.len() => Ok(()),
+            _ => Err(IoErrorKind::WriteZero.into()),
+        }
+    }
+}
+
+impl AsRawFd for CanBcmSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for CanBcmSocket {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+
+impl AsFd for CanBcmSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeval() {
+        let tv = BcmTimeval::from(Duration::from_millis(1500));
+        assert_eq!(1, tv.tv_sec);
+        assert_eq!(500_000, tv.tv_usec);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_msg_layout() {
+        // As laid out by the kernel on 64-bit platforms
+        assert_eq!(56, mem::size_of::<BcmMsgHead>());
+        assert_eq!(72, mem::size_of::<BcmMsg>());
+
+        let head = BcmMsgHead::new(TX_SETUP, 0, 0x123);
+        let base = &head as *const BcmMsgHead as usize;
+        assert_eq!(16, &head.ival1 as *const BcmTimeval as usize - base);
+        assert_eq!(48, &head.can_id as *const canid_t as usize - base);
+
+        let msg = BcmMsg::new(BcmMsgHead::new(TX_SETUP, 0, 0x123), can_frame_default());
+        let bytes = as_bytes(&msg);
+        assert_eq!(&TX_SETUP.to_ne_bytes(), &bytes[0..4]);
+        assert_eq!(&0x123u32.to_ne_bytes(), &bytes[48..52]);
+        assert_eq!(&1u32.to_ne_bytes(), &bytes[52..56]);
+    }
+}
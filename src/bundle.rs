@@ -0,0 +1,232 @@
+// socketcan/src/bundle.rs
+//
+// A bundle of CAN sockets used as a group.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! A bundle of CAN sockets used as a group.
+//!
+//! This is useful for applications, like gateways, that have redundant CAN
+//! links, and want to spread the load across them, or send the same
+//! traffic over all of them.
+
+use crate::{frame::AsPtr, IoError, IoErrorKind, IoResult, Socket};
+use nix::poll::{poll, PollFd, PollFlags};
+use std::{
+    os::raw::c_int,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// A group of CAN sockets that can be written and read together.
+///
+/// The sockets are identified by their index in the bundle, which is the
+/// order in which they were added.
+#[derive(Debug)]
+pub struct CanBundle<S: Socket> {
+    socks: Vec<S>,
+    next: AtomicUsize,
+    next_read: AtomicUsize,
+}
+
+impl<S: Socket> CanBundle<S> {
+    /// Creates a bundle from a collection of open sockets.
+    pub fn new(socks: Vec<S>) -> Self {
+        Self {
+            socks,
+            next: AtomicUsize::new(0),
+            next_read: AtomicUsize::new(0),
+        }
+    }
+
+    /// Opens a bundle of sockets to the named CAN interfaces.
+    pub fn open(ifnames: &[&str]) -> IoResult<Self>
+    where
+        S: Sized,
+    {
+        let socks = ifnames
+            .iter()
+            .map(|ifname| S::open(ifname))
+            .collect::<IoResult<Vec<_>>>()?;
+        Ok(Self::new(socks))
+    }
+
+    /// Adds a socket to the bundle, returning its index.
+    pub fn push(&mut self, sock: S) -> usize {
+        self.socks.push(sock);
+        self.socks.len() - 1
+    }
+
+    /// Gets the number of sockets in the bundle.
+    pub fn len(&self) -> usize {
+        self.socks.len()
+    }
+
+    /// Determines if the bundle has no sockets.
+    pub fn is_empty(&self) -> bool {
+        self.socks.is_empty()
+    }
+
+    /// Gets the sockets in the bundle.
+    pub fn sockets(&self) -> &[S] {
+        &self.socks
+    }
+
+    /// Writes a frame to the next socket in the bundle, in turn.
+    ///
+    /// This spreads the load across the sockets. If the write to a socket
+    /// fails, the frame is tried on the following sockets, in order, until
+    /// one succeeds. On success, this returns the index of the socket that
+    /// sent the frame, otherwise the error from the last one tried.
+    pub fn write_frame_round_robin<F>(&self, frame: &F) -> IoResult<usize>
+    where
+        F: Into<S::FrameType> + AsPtr,
+    {
+        let n = self.socks.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut res = Err(Self::empty_err());
+
+        for i in 0..n {
+            let idx = (start + i) % n;
+            res = self.socks[idx].write_frame(frame).map(|_| idx);
+            if res.is_ok() {
+                break;
+            }
+        }
+        res
+    }
+
+    /// Writes a frame to all the sockets in the bundle.
+    ///
+    /// This sends the same frame over every link, for redundancy. The write
+    /// is attempted on all the sockets, even if some fail, and the first
+    /// error, if any, is returned.
+    pub fn write_frame_all<F>(&self, frame: &F) -> IoResult<()>
+    where
+        F: Into<S::FrameType> + AsPtr,
+    {
+        if self.socks.is_empty() {
+            return Err(Self::empty_err());
+        }
+
+        let mut res = Ok(());
+        for sock in &self.socks {
+            if let Err(err) = sock.write_frame(frame) {
+                if res.is_ok() {
+                    res = Err(err);
+                }
+            }
+        }
+        res
+    }
+
+    /// Blocking read of the first frame available from any of the sockets.
+    ///
+    /// This returns the index of the socket that received the frame, along
+    /// with the frame.
+    pub fn read_frame_any(&self) -> IoResult<(usize, S::FrameType)> {
+        self.poll_read(-1)
+    }
+
+    /// Blocking read of the first frame available from any of the sockets,
+    /// with a timeout.
+    ///
+    /// This returns a `TimedOut` error if no frame is received in time.
+    pub fn read_frame_any_timeout(&self, timeout: Duration) -> IoResult<(usize, S::FrameType)> {
+        let timeout = timeout.as_millis().min(c_int::MAX as u128) as c_int;
+        self.poll_read(timeout)
+    }
+
+    // Polls all the sockets and reads from the first one that's ready.
+    // When several are ready, the search starts from the socket after the
+    // one last read, so that a busy one can't starve the others.
+    fn poll_read(&self, timeout: c_int) -> IoResult<(usize, S::FrameType)> {
+        if self.socks.is_empty() {
+            return Err(Self::empty_err());
+        }
+
+        let mut fds: Vec<_> = self
+            .socks
+            .iter()
+            .map(|sock| PollFd::new(sock.as_raw_fd(), PollFlags::POLLIN))
+            .collect();
+
+        if poll(&mut fds, timeout)? == 0 {
+            return Err(IoErrorKind::TimedOut.into());
+        }
+
+        let n = fds.len();
+        let start = self.next_read.load(Ordering::Relaxed);
+
+        for i in 0..n {
+            let idx = (start + i) % n;
+            if let Some(ev) = fds[idx].revents() {
+                if !ev.is_empty() {
+                    self.next_read.store(idx + 1, Ordering::Relaxed);
+                    return self.socks[idx].read_frame().map(|frame| (idx, frame));
+                }
+            }
+        }
+        Err(IoErrorKind::WouldBlock.into())
+    }
+
+    fn empty_err() -> IoError {
+        IoError::new(IoErrorKind::InvalidInput, "the bundle has no sockets")
+    }
+}
+
+impl<S: Socket> From<Vec<S>> for CanBundle<S> {
+    fn from(socks: Vec<S>) -> Self {
+        Self::new(socks)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{as_bytes, frame::can_frame_default, CanFrame, CanSocket, Frame};
+    use std::os::unix::io::OwnedFd;
+
+    #[test]
+    fn test_empty_bundle() {
+        let bundle = CanBundle::<CanSocket>::new(Vec::new());
+        assert!(bundle.is_empty());
+
+        let frame = CanFrame::from_raw_id(0x123, &[1, 2, 3]).unwrap();
+        assert!(bundle.write_frame_round_robin(&frame).is_err());
+        assert!(bundle.write_frame_all(&frame).is_err());
+        assert!(bundle.read_frame_any().is_err());
+    }
+
+    #[test]
+    fn test_read_any_is_fair() {
+        let mut socks = Vec::new();
+        let mut peers = Vec::new();
+        for _ in 0..2 {
+            let (a, b) =
+                socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::DGRAM, None).unwrap();
+            socks.push(CanSocket::from(OwnedFd::from(a)));
+            peers.push(b);
+        }
+
+        let frame = can_frame_default();
+        for peer in &peers {
+            for _ in 0..2 {
+                peer.send(as_bytes(&frame)).unwrap();
+            }
+        }
+
+        let bundle = CanBundle::new(socks);
+        let order: Vec<_> = (0..4)
+            .map(|_| bundle.read_frame_any_timeout(Duration::MAX).unwrap().0)
+            .collect();
+        assert_eq!(vec![0, 1, 0, 1], order);
+    }
+}
@@ -22,15 +22,18 @@
 //!
 //! Can be parsed by a `Reader` object. The API is inspired by the
 //! [csv](https://crates.io/crates/csv) crate.
+//!
+//! The frame arguments to the `cansend` utility, like `can0 123#11223344`,
+//! can also be parsed with [`parse_cansend_arg`].
+//!
+//! Frames can be formatted like the default, live output of `candump` with
+//! [`format_frame_compact`], and written to a log file, which can be read
+//! back, with a [`Writer`]. Single log lines can be handled with
+//! [`parse_line`] and [`format_line`].
 
-use crate::{
-    frame::{FdFlags, IdFlags},
-    CanDataFrame, CanFdFrame,
-};
-use embedded_can::StandardId;
-use hex::FromHex;
-use libc::canid_t;
-use std::{fs, io, path};
+use crate::{CanAnyFrame, CanError, CanErrorFrame, ConstructionError};
+use libc::{CAN_EFF_FLAG, CAN_EFF_MASK, CAN_ERR_FLAG, CAN_SFF_MASK};
+use std::{fs, io, path, time::Duration};
 
 // cannot be generic, because from_str_radix is not part of any Trait
 fn parse_raw(bytes: &[u8], radix: u32) -> Option<u64> {
@@ -80,7 +83,7 @@ pub struct CanDumpRecord<'a> {
     /// The name of the device
     pub device: &'a str,
     /// The parsed frame
-    pub frame: super::CanAnyFrame,
+    pub frame: CanAnyFrame,
 }
 
 #[derive(Debug)]
@@ -97,7 +100,7 @@ pub enum ParseError {
     /// Invalid CAN frame
     InvalidCanFrame,
     /// Error creating the frame
-    ConstructionError(super::ConstructionError),
+    ConstructionError(ConstructionError),
 }
 
 impl From<io::Error> for ParseError {
@@ -106,8 +109,8 @@ impl From<io::Error> for ParseError {
     }
 }
 
-impl From<super::ConstructionError> for ParseError {
-    fn from(e: super::ConstructionError) -> ParseError {
+impl From<ConstructionError> for ParseError {
+    fn from(e: ConstructionError) -> ParseError {
         ParseError::ConstructionError(e)
     }
 }
@@ -159,60 +162,16 @@ impl<R: io::BufRead> Reader<R> {
 
         // parse packet
         let can_raw = field_iter.next().ok_or(ParseError::UnexpectedEndOfLine)?;
+        let can_raw = ::std::str::from_utf8(can_raw).map_err(|_| ParseError::InvalidCanFrame)?;
+        let (can_id, can_data) = can_raw.split_once('#').ok_or(ParseError::InvalidCanFrame)?;
 
-        let sep_idx = can_raw
-            .iter()
-            .position(|&c| c == b'#')
-            .ok_or(ParseError::InvalidCanFrame)?;
-        let (can_id, mut can_data) = can_raw.split_at(sep_idx);
-
-        // determine frame type (FD or classical) and skip separator(s)
-        let mut fd_flags = FdFlags::empty();
-        let is_fd_frame = if let Some(&b'#') = can_data.get(1) {
-            fd_flags = FdFlags::from_bits_truncate(can_data[2]);
-            can_data = &can_data[3..];
-            true
-        } else {
-            can_data = &can_data[1..];
-            false
-        };
-
-        // cut of linefeed
-        if let Some(&b'\n') = can_data.last() {
-            can_data = &can_data[..can_data.len() - 1];
-        };
-        // cut off \r
-        if let Some(&b'\r') = can_data.last() {
-            can_data = &can_data[..can_data.len() - 1];
-        };
-
-        let mut flags = IdFlags::empty();
-        flags.set(IdFlags::RTR, b"R" == can_data);
-        // TODO: How are error frames saved?
-
-        let data = if flags.contains(IdFlags::RTR) {
-            Vec::new()
-        } else {
-            Vec::from_hex(can_data).map_err(|_| ParseError::InvalidCanFrame)?
-        };
-        let frame: super::CanAnyFrame = if is_fd_frame {
-            CanFdFrame::init(
-                parse_raw(can_id, 16).ok_or(ParseError::InvalidCanFrame)? as canid_t | flags.bits(),
-                &data,
-                fd_flags,
-            )
-            .map(super::CanAnyFrame::Fd)
-        } else {
-            // TODO: Check for other frame types?
-            // is extended?
-            let can_id = parse_raw(can_id, 16).ok_or(ParseError::InvalidCanFrame)?;
-            if can_id >= StandardId::MAX.as_raw() as u64 {
-                flags.set(IdFlags::EFF, true);
-            }
-            CanDataFrame::init(can_id as canid_t | flags.bits(), &data)
-                .map(super::CanFrame::Data)
-                .map(|f| f.into())
-        }?;
+        // The ID isn't always zero-padded in the logs, so pad it out to the
+        // width that `cansend` expects: 8 digits for anything that doesn't
+        // look like a standard ID.
+        let is_extended = can_id.len() > 3
+            || u32::from_str_radix(can_id, 16).map_or(false, |id| id > CAN_SFF_MASK);
+        let width = if is_extended { 8 } else { 3 };
+        let frame = parse_cansend_frame(&format!("{:0>width$}#{}", can_id, can_data))?;
 
         Ok(Some(CanDumpRecord {
             t_us,
@@ -222,8 +181,26 @@ impl<R: io::BufRead> Reader<R> {
     }
 }
 
+/// Parses a single line of a candump log, like
+/// `(1469439874.299654) can1 701#7F`.
+///
+/// This returns the timestamp, as the time since the Unix epoch, the name
+/// of the device, and the frame. Standard, extended, remote, error, and
+/// FD frames are all supported, as written by `candump -l`.
+pub fn parse_line(line: &str) -> Result<(Duration, String, CanAnyFrame), ParseError> {
+    let mut reader = Reader::from_reader(line.as_bytes());
+    let rec = reader
+        .next_record()?
+        .ok_or(ParseError::UnexpectedEndOfLine)?;
+    Ok((
+        Duration::from_micros(rec.t_us),
+        rec.device.to_string(),
+        rec.frame,
+    ))
+}
+
 impl<'a, R: io::Read> Iterator for CanDumpRecords<'a, io::BufReader<R>> {
-    type Item = Result<(u64, super::CanAnyFrame), ParseError>;
+    type Item = Result<(u64, CanAnyFrame), ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         // lift Option:
@@ -235,10 +212,208 @@ impl<'a, R: io::Read> Iterator for CanDumpRecords<'a, io::BufReader<R>> {
     }
 }
 
+// ===== candump log writer =====
+
+/// A CAN log writer.
+///
+/// This writes frames in the log file format of `candump -l`, which can
+/// be read back by a [`Reader`], or replayed by the `canplayer` utility
+/// from [can-utils](https://github.com/linux-can/can-utils):
+///
+/// ```text
+/// (1469439874.299654) can1 701#7F
+/// ```
+///
+/// Error frames are written with their 8-digit ID, including the error
+/// flag, and the full data, which holds the details of the error. So a
+/// [`CanError`] written with [`write_error`](Writer::write_error) is read
+/// back as the same error.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    wtr: W,
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Creates an I/O buffered CAN log writer from a writer.
+    pub fn from_writer(wtr: W) -> Writer<io::BufWriter<W>> {
+        Writer {
+            wtr: io::BufWriter::new(wtr),
+        }
+    }
+
+    /// Writes a record for a frame.
+    ///
+    /// The timestamp, `t_us`, is in microseconds, like in a
+    /// [`CanDumpRecord`].
+    pub fn write_record<F>(&mut self, t_us: u64, device: &str, frame: &F) -> io::Result<()>
+    where
+        F: Clone + Into<CanAnyFrame>,
+    {
+        let line = format_line(Duration::from_micros(t_us), device, frame);
+        writeln!(self.wtr, "{}", line)
+    }
+
+    /// Writes a record for an error, as an error frame.
+    pub fn write_error(&mut self, t_us: u64, device: &str, err: CanError) -> io::Result<()> {
+        self.write_record(t_us, device, &CanErrorFrame::from(err))
+    }
+
+    /// Flushes any buffered records to the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+
+    /// Gets the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.wtr
+    }
+}
+
+impl Writer<fs::File> {
+    /// Creates an I/O buffered CAN log writer to a new file.
+    ///
+    /// If the file already exists, it is truncated.
+    pub fn from_file<P>(path: P) -> io::Result<Writer<io::BufWriter<fs::File>>>
+    where
+        P: AsRef<path::Path>,
+    {
+        Ok(Writer::from_writer(fs::File::create(path)?))
+    }
+}
+
+/// Formats a frame as a line of a candump log, without the line ending.
+///
+/// This is the reverse of [`parse_line`], and is the format written by a
+/// [`Writer`]. The timestamp is the time since the Unix epoch, and is
+/// written to the microsecond.
+pub fn format_line<F>(timestamp: Duration, device: &str, frame: &F) -> String
+where
+    F: Clone + Into<CanAnyFrame>,
+{
+    let frame: CanAnyFrame = frame.clone().into();
+    format!(
+        "({}.{:06}) {} {}",
+        timestamp.as_secs(),
+        timestamp.subsec_micros(),
+        device,
+        frame
+    )
+}
+
+// ===== cansend =====
+
+/// Parses a frame in the format used by the `cansend` utility.
+///
+/// This is the same syntax as the frame argument to `cansend` from
+/// [can-utils](https://github.com/linux-can/can-utils):
+///
+/// ```text
+/// <can_id>#{data}          Classic data frame
+/// <can_id>#R{len}          Classic remote frame, with an optional length
+/// <can_id>##<flags>{data}  FD frame, with a single hex digit of flags
+/// ```
+///
+/// The `<can_id>` is 3 hex digits for a standard ID, or 8 for an extended
+/// ID. An 8-digit ID with the error flag (0x20000000) set is an error
+/// frame. The data bytes are pairs of hex digits, optionally separated
+/// by dots, like "11.2233.44".
+///
+/// The classic frame "len8_dlc" suffix (`_<dlc>`) is not supported.
+pub fn parse_cansend_frame(s: &str) -> Result<CanAnyFrame, ParseError> {
+    s.parse().map_err(|err| match err {
+        ConstructionError::InvalidFormat | ConstructionError::IDTooLarge => {
+            ParseError::InvalidCanFrame
+        }
+        err => err.into(),
+    })
+}
+
+/// Parses the arguments to the `cansend` utility, like "can0 123#11223344".
+///
+/// This returns the name of the interface and the frame. See
+/// [`parse_cansend_frame`] for the format of the frame.
+pub fn parse_cansend_arg(s: &str) -> Result<(String, CanAnyFrame), ParseError> {
+    let mut args = s.split_whitespace();
+    let iface = args.next().ok_or(ParseError::UnexpectedEndOfLine)?;
+    let frame = args.next().ok_or(ParseError::UnexpectedEndOfLine)?;
+
+    if args.next().is_some() {
+        return Err(ParseError::InvalidCanFrame);
+    }
+    Ok((iface.to_string(), parse_cansend_frame(frame)?))
+}
+
+// ===== candump compact format =====
+
+/// Formats a frame like the default (non-log) output of `candump`.
+///
+/// This is the one-line-per-frame format that `candump` prints when run
+/// without any options, like:
+///
+/// ```text
+///  can0  123   [4]  11 22 33 44
+///  can0  12345678   [8]  11 22 33 44 55 66 77 88
+///  can0  123   [4]  remote request
+///  can0  123  [12]  11 22 33 44 55 66 77 88 99 AA BB CC
+/// ```
+///
+/// Classic frames show the length as a single digit, while FD frames use
+/// two, and remote frames show their requested length. Error frames are
+/// shown with their raw ID, including the error flag, and data.
+///
+/// When dumping several interfaces, `candump` right-aligns their names to
+/// the longest one, which the caller can do by padding `iface`. It also
+/// indents the standard IDs once it has seen an extended one; this is
+/// stateless, so it doesn't.
+pub fn format_frame_compact<F>(iface: &str, frame: &F) -> String
+where
+    F: Clone + Into<CanAnyFrame>,
+{
+    use crate::{frame::CAN_ERR_MASK, Frame};
+    use embedded_can::Frame as EmbeddedFrame;
+    use CanAnyFrame::*;
+
+    let frame: CanAnyFrame = frame.clone().into();
+
+    let (id_word, len, data, is_fd) = match &frame {
+        Normal(frame) => (frame.id_word(), frame.len(), frame.data(), false),
+        Remote(frame) => (frame.id_word(), frame.dlc(), &[][..], false),
+        Error(frame) => (frame.id_word(), frame.len(), frame.data(), false),
+        Fd(frame) => (frame.id_word(), frame.len(), frame.data(), true),
+    };
+
+    let id = if id_word & CAN_ERR_FLAG != 0 {
+        format!("{:08X}", id_word & (CAN_ERR_MASK | CAN_ERR_FLAG))
+    } else if id_word & CAN_EFF_FLAG != 0 {
+        format!("{:08X}", id_word & CAN_EFF_MASK)
+    } else {
+        format!("{:03X}", id_word & CAN_SFF_MASK)
+    };
+
+    let len = if is_fd {
+        format!("[{:02}]", len)
+    } else {
+        format!(" [{}]", len)
+    };
+
+    let data = if matches!(frame, Remote(_)) {
+        "remote request".to_string()
+    } else {
+        data.iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    format!(" {}  {}  {}  {}", iface, id, len, data)
+        .trim_end()
+        .to_string()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{CanAnyFrame, Frame};
+    use crate::{frame::FdFlags, CanAnyFrame, CanFdFrame, CanFrame, Frame};
     use embedded_can::Frame as EmbeddedFrame;
 
     #[test]
@@ -259,7 +434,7 @@ mod test {
                 assert!(!frame.is_remote_frame());
                 assert!(!frame.is_error_frame());
                 assert!(!frame.is_extended());
-                assert_eq!(frame.data(), &[]);
+                assert!(frame.data().is_empty());
             } else {
                 panic!("Expected Normal frame, got FD");
             }
@@ -302,7 +477,7 @@ mod test {
                 assert_eq!(frame.is_remote_frame(), false);
                 assert_eq!(frame.is_error_frame(), false);
                 assert_eq!(frame.is_extended(), true);
-                assert_eq!(frame.data(), &[]);
+                assert!(frame.data().is_empty());
             } else {
                 panic!("Expected Normal frame, got FD");
             }
@@ -346,7 +521,7 @@ mod test {
                 assert!(!frame.is_extended());
                 assert!(!frame.is_brs());
                 assert!(!frame.is_esi());
-                assert_eq!(frame.data(), &[]);
+                assert!(frame.data().is_empty());
             } else {
                 panic!("Expected FD frame, got Normal");
             }
@@ -371,4 +546,248 @@ mod test {
 
         assert!(reader.next_record().unwrap().is_none());
     }
+
+    #[test]
+    fn test_cansend() {
+        let (iface, frame) = parse_cansend_arg("can0 123#11223344").unwrap();
+        assert_eq!("can0", iface);
+        match frame {
+            CanAnyFrame::Normal(frame) => {
+                assert_eq!(0x123, frame.raw_id());
+                assert!(!frame.is_extended());
+                assert_eq!(&[0x11, 0x22, 0x33, 0x44], frame.data());
+            }
+            _ => panic!("Expected Normal frame"),
+        }
+
+        match parse_cansend_frame("5A1#11.2233.44556677.88").unwrap() {
+            CanAnyFrame::Normal(frame) => {
+                assert_eq!(
+                    &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88],
+                    frame.data()
+                )
+            }
+            _ => panic!("Expected Normal frame"),
+        }
+
+        match parse_cansend_frame("5AA#").unwrap() {
+            CanAnyFrame::Normal(frame) => assert!(frame.data().is_empty()),
+            _ => panic!("Expected Normal frame"),
+        }
+
+        // Extended ID by length, not value
+        match parse_cansend_frame("00000123#DEADBEEF").unwrap() {
+            CanAnyFrame::Normal(frame) => {
+                assert!(frame.is_extended());
+                assert_eq!(0x123, frame.raw_id());
+            }
+            _ => panic!("Expected Normal frame"),
+        }
+
+        // Remote frames
+        match parse_cansend_frame("123#R").unwrap() {
+            CanAnyFrame::Remote(frame) => assert_eq!(0, frame.dlc()),
+            _ => panic!("Expected Remote frame"),
+        }
+        match parse_cansend_frame("00000123#R3").unwrap() {
+            CanAnyFrame::Remote(frame) => {
+                assert!(frame.is_extended());
+                assert_eq!(3, frame.dlc());
+            }
+            _ => panic!("Expected Remote frame"),
+        }
+
+        // FD frames
+        match parse_cansend_frame("213##311223344").unwrap() {
+            CanAnyFrame::Fd(frame) => {
+                assert_eq!(0x213, frame.raw_id());
+                assert!(frame.is_brs());
+                assert!(frame.is_esi());
+                assert_eq!(&[0x11, 0x22, 0x33, 0x44], frame.data());
+            }
+            _ => panic!("Expected FD frame"),
+        }
+        match parse_cansend_frame("123##1").unwrap() {
+            CanAnyFrame::Fd(frame) => assert!(frame.data().is_empty()),
+            _ => panic!("Expected FD frame"),
+        }
+
+        // Error frame
+        let frame = parse_cansend_frame("20000040#").unwrap();
+        match frame {
+            CanAnyFrame::Error(frame) => assert_eq!(0x0040, frame.error_bits()),
+            _ => panic!("Expected Error frame"),
+        }
+    }
+
+    #[test]
+    fn test_cansend_invalid() {
+        assert!(parse_cansend_frame("123").is_err());
+        assert!(parse_cansend_frame("12#11").is_err());
+        assert!(parse_cansend_frame("800#11").is_err());
+        assert!(parse_cansend_frame("123#112").is_err());
+        assert!(parse_cansend_frame("123#R9").is_err());
+        assert!(parse_cansend_frame("123#112233445566778899").is_err());
+        assert!(parse_cansend_frame("1F334455#1122334455667788_B").is_err());
+        assert!(parse_cansend_arg("can0").is_err());
+        assert!(parse_cansend_arg("can0 123#11 extra").is_err());
+    }
+
+    #[test]
+    fn test_format_compact() {
+        let frame = CanFrame::from_raw_id(0x123, &[0x11, 0x22, 0x33, 0x44]).unwrap();
+        assert_eq!(
+            " can0  123   [4]  11 22 33 44",
+            format_frame_compact("can0", &frame)
+        );
+
+        let frame = CanFrame::from_raw_id(0x12345678, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        assert_eq!(
+            " can0  12345678   [8]  01 02 03 04 05 06 07 08",
+            format_frame_compact("can0", &frame)
+        );
+
+        let frame = CanFrame::remote_from_raw_id(0x123, 4).unwrap();
+        assert_eq!(
+            " can0  123   [4]  remote request",
+            format_frame_compact("can0", &frame)
+        );
+
+        let frame = CanFrame::from_raw_id(0x7FF, &[]).unwrap();
+        assert_eq!(" vcan1  7FF   [0]", format_frame_compact("vcan1", &frame));
+
+        let frame = CanFdFrame::from_raw_id(0x123, &[0xAA; 12]).unwrap();
+        assert_eq!(
+            " can0  123  [12]  AA AA AA AA AA AA AA AA AA AA AA AA",
+            format_frame_compact("can0", &frame)
+        );
+
+        let frame = CanErrorFrame::new_error(0x04, &[0, 0x10, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(
+            " can0  20000004   [8]  00 10 00 00 00 00 00 00",
+            format_frame_compact("can0", &frame)
+        );
+    }
+
+    #[test]
+    fn test_write_error_round_trip() {
+        use crate::errors::{ControllerProblem, Location, ViolationType};
+
+        let errors = [
+            CanError::TransmitTimeout,
+            CanError::LostArbitration(7),
+            CanError::ControllerProblem(ControllerProblem::TransmitErrorPassive),
+            CanError::ProtocolViolation {
+                vtype: ViolationType::BitStuffingError,
+                location: Location::Id2821,
+            },
+            CanError::NoAck,
+            CanError::BusOff,
+            CanError::Restarted,
+        ];
+
+        let mut writer = Writer::from_writer(Vec::new());
+        for (i, err) in errors.iter().enumerate() {
+            writer
+                .write_error(i as u64 * 1_500_000, "can0", *err)
+                .unwrap();
+        }
+        let log = writer.into_inner().into_inner().unwrap();
+        assert!(log.starts_with(b"(0.000000) can0 20000001#0000000000000000\n"));
+
+        let mut reader = Reader::from_reader(&log[..]);
+        for (i, err) in errors.iter().enumerate() {
+            let rec = reader.next_record().unwrap().unwrap();
+            assert_eq!(i as u64 * 1_500_000, rec.t_us);
+            assert_eq!("can0", rec.device);
+            match rec.frame {
+                CanAnyFrame::Error(frame) => {
+                    let err2 = frame.into_error();
+                    assert_eq!(err.error_class(), err2.error_class());
+                    assert_eq!(err.to_string(), err2.to_string());
+                    assert_eq!(CanErrorFrame::from(*err).data(), frame.data());
+                }
+                _ => panic!("Expected Error frame"),
+            }
+        }
+        assert!(reader.next_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let frames = [
+            parse_cansend_frame("123#11223344").unwrap(),
+            parse_cansend_frame("12345678#AABB").unwrap(),
+            parse_cansend_frame("123#R3").unwrap(),
+            parse_cansend_frame("213##311223344").unwrap(),
+        ];
+
+        let mut writer = Writer::from_writer(Vec::new());
+        for frame in &frames {
+            writer.write_record(1_000_001, "vcan0", frame).unwrap();
+        }
+        let log = writer.into_inner().into_inner().unwrap();
+        let log = String::from_utf8(log).unwrap();
+        assert_eq!(
+            "(1.000001) vcan0 123#11223344\n\
+             (1.000001) vcan0 12345678#AABB\n\
+             (1.000001) vcan0 123#R3\n\
+             (1.000001) vcan0 213##311223344\n",
+            log
+        );
+
+        let mut reader = Reader::from_reader(log.as_bytes());
+        for frame in &frames {
+            let rec = reader.next_record().unwrap().unwrap();
+            assert_eq!(frame.to_string(), rec.frame.to_string());
+        }
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let (ts, dev, frame) = parse_line("(1648000000.123456) can0 123#DEADBEEF").unwrap();
+        assert_eq!(Duration::new(1648000000, 123456000), ts);
+        assert_eq!("can0", dev);
+        assert_eq!("123#DEADBEEF", frame.to_string());
+
+        // Extended IDs are 8 digits, even when they'd fit in 11 bits
+        let (_, _, frame) = parse_line("(0.000000) can0 00000123#01\n").unwrap();
+        assert_eq!("00000123#01", frame.to_string());
+
+        let (_, _, frame) = parse_line("(0.000000) can0 7FF#").unwrap();
+        assert_eq!("7FF#", frame.to_string());
+
+        let (_, _, frame) = parse_line("(0.000000) can0 1ABCDEF0#R").unwrap();
+        assert!(matches!(frame, CanAnyFrame::Remote(_)));
+        assert_eq!("1ABCDEF0#R", frame.to_string());
+
+        let (_, _, frame) = parse_line("(0.000000) can0 20000004#0004000000000000").unwrap();
+        assert!(matches!(frame, CanAnyFrame::Error(_)));
+
+        let (_, _, frame) = parse_line("(0.000000) can0 12345678##3AABB").unwrap();
+        if let CanAnyFrame::Fd(frame) = frame {
+            assert!(frame.is_extended());
+            assert!(frame.is_brs());
+            assert!(frame.is_esi());
+            assert_eq!(&[0xAA, 0xBB], frame.data());
+        } else {
+            panic!("Expected FD frame");
+        }
+
+        assert!(parse_line("").is_err());
+        assert!(parse_line("(0.000000) can0 123##").is_err());
+        assert!(parse_line("(0.000000) can0 123#ABC").is_err());
+    }
+
+    #[test]
+    fn test_format_line() {
+        let frame = CanFdFrame::init(0x12345678 | CAN_EFF_FLAG, &[0xAA], FdFlags::BRS).unwrap();
+        let line = format_line(Duration::from_micros(1_500_000), "can1", &frame);
+        assert_eq!("(1.500000) can1 12345678##1AA", line);
+
+        let (ts, dev, parsed) = parse_line(&line).unwrap();
+        assert_eq!(Duration::from_millis(1500), ts);
+        assert_eq!("can1", dev);
+        assert_eq!(line, format_line(ts, &dev, &parsed));
+    }
 }
@@ -38,7 +38,10 @@
 //! [linux/can/error.h](https://raw.githubusercontent.com/torvalds/linux/master/include/uapi/linux/can/error.h)
 //!
 
-use crate::{CanErrorFrame, EmbeddedFrame, Frame};
+use crate::{
+    frame::{CAN_ERR_FLAG, CAN_ERR_MASK, CAN_MAX_DLEN},
+    CanErrorFrame, CanFrame, Frame,
+};
 use std::{convert::TryFrom, error, fmt, io};
 use thiserror::Error;
 
@@ -49,14 +52,43 @@ use thiserror::Error;
 /// This can be any of the underlying errors from this library. The two main
 /// error sources are either CAN errors coming in through received error
 /// frames or from typical system I/O errors.
+///
+/// When converted from an I/O error, the conditions that mean that a socket
+/// can no longer be used are reported as [`Error::Closed`] or
+/// [`Error::InterfaceNotFound`]. An application reading in a loop should
+/// stop when it gets one of these, which can be checked with
+/// [`Error::is_terminal`]. Any other error may be transient.
 #[derive(Error, Debug)]
 pub enum Error {
     /// A CANbus error, usually from an error frmae
     #[error(transparent)]
     Can(#[from] CanError),
+    /// The socket was closed, or its read side was shut down.
+    /// The original I/O error is included.
+    #[error("socket closed")]
+    Closed(#[source] io::Error),
+    /// The CAN interface does not exist, or it has been removed.
+    /// The original I/O error, with its `errno`, is included.
+    #[error("CAN interface not found")]
+    InterfaceNotFound(#[source] io::Error),
+    /// A read or write did not complete within its timeout.
+    #[error("operation timed out")]
+    TimedOut,
+    /// A frame was received with a different type of ID (standard or
+    /// extended) than expected. The frame is included.
+    #[error("unexpected frame type")]
+    UnexpectedFrameType(CanFrame),
     /// An I/O Error
     #[error(transparent)]
-    Io(#[from] io::Error),
+    Io(io::Error),
+}
+
+impl Error {
+    /// Determines if this error means that the socket can no longer be
+    /// used, and an application should stop reading from it.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Error::Closed(_) | Error::InterfaceNotFound(_))
+    }
 }
 
 impl embedded_can::Error for Error {
@@ -74,6 +106,46 @@ impl From<CanErrorFrame> for Error {
     }
 }
 
+/// Determines if an I/O error means that a socket can no longer be used.
+///
+/// These are the errors that convert to [`Error::Closed`] or
+/// [`Error::InterfaceNotFound`], along with `EBADF`, which is a bad file
+/// descriptor that will never become usable.
+pub(crate) fn is_terminal_io_error(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::UnexpectedEof
+        || matches!(
+            err.raw_os_error(),
+            Some(libc::ENODEV) | Some(libc::ENXIO) | Some(libc::EBADF)
+        )
+}
+
+impl From<io::Error> for Error {
+    /// Creates an error from an I/O error, picking out the terminal
+    /// conditions for a socket.
+    ///
+    /// A read that hits the end of the stream means the socket was closed
+    /// or shut down. The kernel reports `ENODEV` or `ENXIO` when the
+    /// interface to which a socket is bound has been removed. A `TimedOut`
+    /// error, such as from a read with a timeout, is reported as
+    /// [`Error::TimedOut`].
+    ///
+    /// The original error is kept inside the `Closed` and
+    /// `InterfaceNotFound` variants. Anything else, including `EBADF`,
+    /// which is a programming error rather than a closed socket, is
+    /// returned as [`Error::Io`].
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => return Error::Closed(err),
+            io::ErrorKind::TimedOut => return Error::TimedOut,
+            _ => (),
+        }
+        match err.raw_os_error() {
+            Some(libc::ENODEV) | Some(libc::ENXIO) => Error::InterfaceNotFound(err),
+            _ => Error::Io(err),
+        }
+    }
+}
+
 impl From<io::ErrorKind> for Error {
     /// Creates an Io error straight from an io::ErrorKind
     fn from(ek: io::ErrorKind) -> Self {
@@ -122,6 +194,7 @@ pub type IoResult<T> = io::Result<T>;
 /// (`CAN_ERR_FLAG`) is set. But there are additional types to handle any
 /// problems decoding the error frame.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CanError {
     /// TX timeout (by netdevice driver)
     TransmitTimeout,
@@ -137,8 +210,8 @@ pub enum CanError {
         /// The location (field or bit) of the violation
         location: Location,
     },
-    /// Transceiver Error.
-    TransceiverError,
+    /// Transceiver error, with the status of the transceiver.
+    TransceiverError(TransceiverError),
     /// No ACK received for current CAN frame.
     NoAck,
     /// Bus off (due to too many detected errors)
@@ -147,13 +220,111 @@ pub enum CanError {
     BusError,
     /// The bus has been restarted
     Restarted,
+    /// The controller's transmit and receive error counters.
+    ///
+    /// These can be watched to see the controller drift toward the error
+    /// passive (128) or bus-off (256) levels.
+    ErrorCounters {
+        /// The transmit error counter
+        tx: u8,
+        /// The receive error counter
+        rx: u8,
+    },
     /// There was an error decoding the error frame
     DecodingFailure(CanErrorDecodingFailure),
     /// Unknown, possibly invalid, error
     Unknown(u32),
 }
 
-impl error::Error for CanError {}
+impl error::Error for CanError {
+    /// Gets the more specific error that caused this one, if any.
+    ///
+    /// This is the inner controller problem, violation type, transceiver
+    /// error, or decoding failure, for the errors that carry one.
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        use CanError::*;
+        match self {
+            ControllerProblem(err) => Some(err),
+            ProtocolViolation { vtype, .. } => Some(vtype),
+            TransceiverError(err) => Some(err),
+            DecodingFailure(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl CanError {
+    /// Gets the error class bit of the error.
+    ///
+    /// This is the bit in the CAN ID word of an error frame, and in the
+    /// socket's error mask, for the class of errors to which this one
+    /// belongs. For example, any `ControllerProblem` is in the
+    /// `CAN_ERR_CRTL` (0x0004) class.
+    ///
+    /// An `Unknown` error returns the bits that it was created from, and a
+    /// `DecodingFailure` returns zero, as it doesn't have a class.
+    pub fn error_class(&self) -> u32 {
+        use CanError::*;
+        match *self {
+            TransmitTimeout => err_mask::TX_TIMEOUT,
+            LostArbitration(_) => err_mask::LOST_ARBITRATION,
+            ControllerProblem(_) => err_mask::CONTROLLER_PROBLEM,
+            ProtocolViolation { .. } => err_mask::PROTOCOL_VIOLATION,
+            TransceiverError(_) => err_mask::TRANSCEIVER,
+            NoAck => err_mask::NO_ACK,
+            BusOff => err_mask::BUS_OFF,
+            BusError => err_mask::BUS_ERROR,
+            Restarted => err_mask::RESTARTED,
+            ErrorCounters { .. } => err_mask::ERROR_COUNTERS,
+            DecodingFailure(_) => 0,
+            Unknown(bits) => bits & CAN_ERR_MASK,
+        }
+    }
+
+    /// Gets the severity of the error.
+    ///
+    /// This is useful to choose a logging level, or to decide whether to
+    /// keep using a socket after the error. The classification is:
+    ///
+    /// | Error | Severity |
+    /// |-------|----------|
+    /// | `LostArbitration` | `Info` |
+    /// | `ControllerProblem(Active)` | `Info` |
+    /// | `Restarted` | `Info` |
+    /// | `ErrorCounters` | `Info` |
+    /// | `ControllerProblem(ReceiveBufferOverflow)` | `Fatal` |
+    /// | `ControllerProblem(TransmitBufferOverflow)` | `Fatal` |
+    /// | `BusOff` | `Fatal` |
+    /// | anything else | `Warning` |
+    ///
+    /// Transceiver (wiring) faults are a `Warning`, since closing and
+    /// reopening the socket won't fix them.
+    pub fn severity(&self) -> ErrorSeverity {
+        use self::ControllerProblem::*;
+        use CanError::*;
+        match *self {
+            LostArbitration(_) | Restarted | ErrorCounters { .. } => ErrorSeverity::Info,
+            ControllerProblem(Active) => ErrorSeverity::Info,
+            ControllerProblem(ReceiveBufferOverflow | TransmitBufferOverflow) => {
+                ErrorSeverity::Fatal
+            }
+            BusOff => ErrorSeverity::Fatal,
+            _ => ErrorSeverity::Warning,
+        }
+    }
+
+    /// Determines if the application can keep using the socket after
+    /// the error.
+    ///
+    /// This is `false` for the errors with a [`ErrorSeverity::Fatal`]
+    /// severity: a bus-off, or a controller buffer overflow. These
+    /// usually require the socket to be closed, and maybe the interface
+    /// restarted. All other errors are transient conditions, and are
+    /// recoverable. See [`CanError::severity`] for the full table.
+    pub fn is_recoverable(&self) -> bool {
+        self.severity() != ErrorSeverity::Fatal
+    }
+}
 
 impl fmt::Display for CanError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -165,11 +336,12 @@ impl fmt::Display for CanError {
             ProtocolViolation { vtype, location } => {
                 write!(f, "protocol violation at {}: {}", location, vtype)
             }
-            TransceiverError => write!(f, "transceiver error"),
+            TransceiverError(e) => write!(f, "transceiver error: {}", e),
             NoAck => write!(f, "no ack"),
             BusOff => write!(f, "bus off"),
             BusError => write!(f, "bus error"),
             Restarted => write!(f, "restarted"),
+            ErrorCounters { tx, rx } => write!(f, "error counters: tx {}, rx {}", tx, rx),
             DecodingFailure(err) => write!(f, "decoding failure: {}", err),
             Unknown(err) => write!(f, "unknown error ({})", err),
         }
@@ -194,43 +366,382 @@ impl embedded_can::Error for CanError {
     }
 }
 
-impl From<CanErrorFrame> for CanError {
-    /// Constructs a CAN error from an error frame.
-    fn from(frame: CanErrorFrame) -> Self {
-        // Note that the CanErrorFrame is guaranteed to have the full 8-byte
-        // data payload.
-        match frame.error_bits() {
-            0x0001 => CanError::TransmitTimeout,
-            0x0002 => CanError::LostArbitration(frame.data()[0]),
-            0x0004 => match ControllerProblem::try_from(frame.data()[1]) {
+impl CanError {
+    /// Decodes the error for the error bits and the data of an error frame.
+    ///
+    /// The bits should normally be for a single error class. Anything
+    /// else is reported as `Unknown`.
+    pub(crate) fn decode(bits: u32, data: &[u8; 8]) -> Self {
+        match bits {
+            err_mask::TX_TIMEOUT => CanError::TransmitTimeout,
+            err_mask::LOST_ARBITRATION => CanError::LostArbitration(data[0]),
+            err_mask::CONTROLLER_PROBLEM => match ControllerProblem::try_from(data[1]) {
                 Ok(err) => CanError::ControllerProblem(err),
                 Err(err) => CanError::DecodingFailure(err),
             },
-            0x0008 => {
-                match (
-                    ViolationType::try_from(frame.data()[2]),
-                    Location::try_from(frame.data()[3]),
-                ) {
-                    (Ok(vtype), Ok(location)) => CanError::ProtocolViolation { vtype, location },
-                    (Err(err), _) | (_, Err(err)) => CanError::DecodingFailure(err),
-                }
-            }
-            0x0010 => CanError::TransceiverError,
-            0x0020 => CanError::NoAck,
-            0x0040 => CanError::BusOff,
-            0x0080 => CanError::BusError,
-            0x0100 => CanError::Restarted,
+            err_mask::PROTOCOL_VIOLATION => match (
+                ViolationType::try_from(data[2]),
+                Location::try_from(data[3]),
+            ) {
+                (Ok(vtype), Ok(location)) => CanError::ProtocolViolation { vtype, location },
+                (Err(err), _) | (_, Err(err)) => CanError::DecodingFailure(err),
+            },
+            err_mask::TRANSCEIVER => match TransceiverError::try_from(data[4]) {
+                Ok(err) => CanError::TransceiverError(err),
+                Err(err) => CanError::DecodingFailure(err),
+            },
+            err_mask::NO_ACK => CanError::NoAck,
+            err_mask::BUS_OFF => CanError::BusOff,
+            err_mask::BUS_ERROR => CanError::BusError,
+            err_mask::RESTARTED => CanError::Restarted,
+            err_mask::ERROR_COUNTERS => CanError::ErrorCounters {
+                tx: data[6],
+                rx: data[7],
+            },
             err => CanError::Unknown(err),
         }
     }
 }
 
+impl From<CanErrorFrame> for CanError {
+    /// Constructs a CAN error from an error frame.
+    ///
+    /// Note that this is lossy. An error frame can report several errors
+    /// at once, but this only decodes a single one. Drivers commonly set
+    /// the error counter bit (0x0200) along with another error, such as a
+    /// controller problem. In that case the other error is decoded, and
+    /// the counters can still be read with
+    /// [`CanErrorFrame::error_counters`]. If any other combination of
+    /// bits is set, this returns an `Unknown` error with the bits. Use
+    /// [`CanErrorFrame::errors`] to get all of the errors in the frame.
+    fn from(frame: CanErrorFrame) -> Self {
+        frame.as_can_error()
+    }
+}
+
+impl TryFrom<&CanFrame> for CanError {
+    type Error = CanErrorDecodingFailure;
+
+    /// Decodes the CAN error from any type of frame.
+    ///
+    /// This fails with `NotAnError` if the frame doesn't have the error
+    /// flag (`CAN_ERR_FLAG`) set in its ID word, and with `NotEnoughData`
+    /// if it has less than the full 8 bytes of error data.
+    fn try_from(frame: &CanFrame) -> std::result::Result<Self, CanErrorDecodingFailure> {
+        if frame.id_word() & CAN_ERR_FLAG == 0 {
+            return Err(CanErrorDecodingFailure::NotAnError);
+        }
+        if frame.len() < CAN_MAX_DLEN {
+            return Err(CanErrorDecodingFailure::NotEnoughData(frame.len() as u8));
+        }
+        match frame {
+            CanFrame::Error(frame) => Ok(CanError::from(*frame)),
+            _ => Err(CanErrorDecodingFailure::NotAnError),
+        }
+    }
+}
+
+// ===== ErrorSeverity =====
+
+/// How serious a [`CanError`] is.
+///
+/// The levels are ordered, from the least to the most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorSeverity {
+    /// Normal bus activity or a status report
+    Info,
+    /// A transient error that the bus can recover from
+    Warning,
+    /// The socket or interface can't continue without intervention
+    Fatal,
+}
+
+impl fmt::Display for ErrorSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ErrorSeverity::*;
+        let msg = match *self {
+            Info => "info",
+            Warning => "warning",
+            Fatal => "fatal",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+// ===== err_mask =====
+
+/// The error class bits, as used in the ID word of an error frame, and in
+/// a socket's error filter (`CAN_RAW_ERR_FILTER`).
+///
+/// These match the `CAN_ERR_*` values in the kernel's
+/// `<linux/can/error.h>`, and can be OR'ed together to make an error
+/// mask:
+///
+/// ```no_run
+/// use socketcan::{errors::err_mask, CanSocket, Socket, SocketOptions};
+///
+/// let sock = CanSocket::open("can0").unwrap();
+/// sock.set_error_filter(err_mask::BUS_OFF | err_mask::NO_ACK).unwrap();
+/// ```
+///
+/// To get the bit for a specific error, use [`CanError::error_class`], or
+/// build a filter from a list of errors with
+/// [`CanErrorFilter::for_errors`].
+pub mod err_mask {
+    /// TX timeout (by netdevice driver)
+    pub const TX_TIMEOUT: u32 = 0x0001;
+    /// Lost arbitration
+    pub const LOST_ARBITRATION: u32 = 0x0002;
+    /// Controller problems
+    pub const CONTROLLER_PROBLEM: u32 = 0x0004;
+    /// Protocol violations
+    pub const PROTOCOL_VIOLATION: u32 = 0x0008;
+    /// Transceiver status
+    pub const TRANSCEIVER: u32 = 0x0010;
+    /// Received no ACK on transmission
+    pub const NO_ACK: u32 = 0x0020;
+    /// Bus off
+    pub const BUS_OFF: u32 = 0x0040;
+    /// Bus error (may flood!)
+    pub const BUS_ERROR: u32 = 0x0080;
+    /// Controller restarted
+    pub const RESTARTED: u32 = 0x0100;
+    /// TX/RX error counters are in the data
+    pub const ERROR_COUNTERS: u32 = 0x0200;
+}
+
+// ===== CanErrorClass =====
+
+/// The class of an error, from the error bits in the ID word of an error
+/// frame.
+///
+/// An error frame can have several classes set at once. This is a lighter
+/// weight inspection of the frame than a full decode into a [`CanError`],
+/// for when an application only needs to know which classes fired.
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum CanErrorClass {
+    /// TX timeout (by netdevice driver)
+    TransmitTimeout = err_mask::TX_TIMEOUT,
+    /// Lost arbitration
+    LostArbitration = err_mask::LOST_ARBITRATION,
+    /// Controller problems
+    Controller = err_mask::CONTROLLER_PROBLEM,
+    /// Protocol violations
+    ProtocolViolation = err_mask::PROTOCOL_VIOLATION,
+    /// Transceiver status
+    Transceiver = err_mask::TRANSCEIVER,
+    /// Received no ACK on transmission
+    NoAck = err_mask::NO_ACK,
+    /// Bus off
+    BusOff = err_mask::BUS_OFF,
+    /// Bus error (may flood!)
+    BusError = err_mask::BUS_ERROR,
+    /// Controller restarted
+    Restarted = err_mask::RESTARTED,
+    /// TX/RX error counters are in the data
+    ErrorCounters = err_mask::ERROR_COUNTERS,
+}
+
+impl CanErrorClass {
+    /// All of the error classes, in order of their bits.
+    pub const ALL: [CanErrorClass; 10] = [
+        CanErrorClass::TransmitTimeout,
+        CanErrorClass::LostArbitration,
+        CanErrorClass::Controller,
+        CanErrorClass::ProtocolViolation,
+        CanErrorClass::Transceiver,
+        CanErrorClass::NoAck,
+        CanErrorClass::BusOff,
+        CanErrorClass::BusError,
+        CanErrorClass::Restarted,
+        CanErrorClass::ErrorCounters,
+    ];
+
+    /// Gets the bit for the class in the error mask.
+    pub fn bit(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Gets an iterator over the classes set in the error bits.
+    ///
+    /// Any bits that don't correspond to a known class are ignored.
+    pub fn iter_bits(bits: u32) -> impl Iterator<Item = CanErrorClass> {
+        Self::ALL
+            .into_iter()
+            .filter(move |class| bits & class.bit() != 0)
+    }
+}
+
+impl fmt::Display for CanErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use CanErrorClass::*;
+        let msg = match *self {
+            TransmitTimeout => "transmission timeout",
+            LostArbitration => "lost arbitration",
+            Controller => "controller problem",
+            ProtocolViolation => "protocol violation",
+            Transceiver => "transceiver status",
+            NoAck => "no ack",
+            BusOff => "bus off",
+            BusError => "bus error",
+            Restarted => "restarted",
+            ErrorCounters => "error counters",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl TryFrom<u32> for CanErrorClass {
+    type Error = CanErrorDecodingFailure;
+
+    /// Gets the class from a single error bit.
+    fn try_from(bit: u32) -> std::result::Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|class| class.bit() == bit)
+            .ok_or(CanErrorDecodingFailure::UnknownErrorType(bit))
+    }
+}
+
+// ===== CanErrorFilter =====
+
+/// An error mask to select which errors a socket should receive.
+///
+/// This is a friendlier way to build the mask for
+/// [`SocketOptions::set_error_filter`](crate::SocketOptions::set_error_filter)
+/// from the errors of interest, rather than from the error class bits.
+///
+/// The filter can be built from a list of errors, or by chaining the
+/// named classes:
+///
+/// ```
+/// use socketcan::{CanError, CanErrorFilter};
+///
+/// let filter = CanErrorFilter::for_errors(&[CanError::BusOff, CanError::NoAck]);
+/// assert_eq!(0x0060, filter.bits());
+///
+/// let filter = CanErrorFilter::none().bus_off().no_ack();
+/// assert_eq!(0x0060, filter.bits());
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CanErrorFilter(u32);
+
+impl CanErrorFilter {
+    /// Creates a filter from the raw error class bits.
+    pub fn new(bits: u32) -> Self {
+        Self(bits & CAN_ERR_MASK)
+    }
+
+    /// Creates a filter that accepts all errors.
+    pub fn all() -> Self {
+        Self(CAN_ERR_MASK)
+    }
+
+    /// Creates a filter that doesn't accept any errors.
+    ///
+    /// This is the default for a socket, and a starting point to add the
+    /// classes of interest.
+    pub fn none() -> Self {
+        Self(0)
+    }
+
+    /// Creates a filter that receives the classes of the specified errors.
+    ///
+    /// Any data in the errors is ignored. For example, any
+    /// `CanError::LostArbitration(_)` selects the lost arbitration class.
+    pub fn for_errors(errors: &[CanError]) -> Self {
+        errors
+            .iter()
+            .fold(Self::default(), |filter, err| filter.with(err))
+    }
+
+    /// Adds the class of the specified error to the filter.
+    pub fn with(self, err: &CanError) -> Self {
+        Self(self.0 | err.error_class())
+    }
+
+    /// Adds an error class to the filter.
+    pub fn with_class(self, class: CanErrorClass) -> Self {
+        Self(self.0 | class.bit())
+    }
+
+    /// Adds TX timeouts to the filter.
+    pub fn tx_timeout(self) -> Self {
+        Self(self.0 | err_mask::TX_TIMEOUT)
+    }
+
+    /// Adds lost arbitration to the filter.
+    pub fn lost_arbitration(self) -> Self {
+        Self(self.0 | err_mask::LOST_ARBITRATION)
+    }
+
+    /// Adds controller problems to the filter.
+    pub fn controller_problem(self) -> Self {
+        Self(self.0 | err_mask::CONTROLLER_PROBLEM)
+    }
+
+    /// Adds protocol violations to the filter.
+    pub fn protocol_violation(self) -> Self {
+        Self(self.0 | err_mask::PROTOCOL_VIOLATION)
+    }
+
+    /// Adds transceiver status errors to the filter.
+    pub fn transceiver(self) -> Self {
+        Self(self.0 | err_mask::TRANSCEIVER)
+    }
+
+    /// Adds missing ACKs to the filter.
+    pub fn no_ack(self) -> Self {
+        Self(self.0 | err_mask::NO_ACK)
+    }
+
+    /// Adds bus-off to the filter.
+    pub fn bus_off(self) -> Self {
+        Self(self.0 | err_mask::BUS_OFF)
+    }
+
+    /// Adds bus errors to the filter.
+    ///
+    /// Note that these can flood the socket on a bus with problems.
+    pub fn bus_error(self) -> Self {
+        Self(self.0 | err_mask::BUS_ERROR)
+    }
+
+    /// Adds controller restarts to the filter.
+    pub fn restarted(self) -> Self {
+        Self(self.0 | err_mask::RESTARTED)
+    }
+
+    /// Adds the error counters to the filter.
+    pub fn error_counters(self) -> Self {
+        Self(self.0 | err_mask::ERROR_COUNTERS)
+    }
+
+    /// Determines if the filter accepts the class of the specified error.
+    pub fn accepts(&self, err: &CanError) -> bool {
+        self.0 & err.error_class() != 0
+    }
+
+    /// Gets the error mask bits.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<CanErrorFilter> for u32 {
+    fn from(filter: CanErrorFilter) -> Self {
+        filter.0
+    }
+}
+
 // ===== ControllerProblem =====
 
 /// Error status of the CAN conroller.
 ///
 /// This is derived from `data[1]` of an error frame
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ControllerProblem {
     /// unspecified
@@ -270,6 +781,39 @@ impl fmt::Display for ControllerProblem {
     }
 }
 
+impl ControllerProblem {
+    /// Decodes all the controller problems present in the byte.
+    ///
+    /// The kernel reports the controller status in `data[1]` of an error
+    /// frame as a bitmask, and a controller can report several conditions
+    /// at once, like an RX buffer overflow along with the RX error warning
+    /// (`0x05`). The `TryFrom<u8>` conversion only handles a single bit;
+    /// this returns every condition that is set, in order of the bits.
+    ///
+    /// A zero byte is returned as `[Unspecified]`. Any bits that are not
+    /// defined by the kernel are ignored.
+    pub fn flags_from(byte: u8) -> Vec<ControllerProblem> {
+        use ControllerProblem::*;
+
+        if byte == 0 {
+            return vec![Unspecified];
+        }
+
+        [
+            ReceiveBufferOverflow,
+            TransmitBufferOverflow,
+            ReceiveErrorWarning,
+            TransmitErrorWarning,
+            ReceiveErrorPassive,
+            TransmitErrorPassive,
+            Active,
+        ]
+        .into_iter()
+        .filter(|prob| byte & (*prob as u8) != 0)
+        .collect()
+    }
+}
+
 impl TryFrom<u8> for ControllerProblem {
     type Error = CanErrorDecodingFailure;
 
@@ -295,6 +839,7 @@ impl TryFrom<u8> for ControllerProblem {
 ///
 /// This is derived from `data[2]` of an error frame.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ViolationType {
     /// Unspecified Violation
@@ -364,6 +909,7 @@ impl TryFrom<u8> for ViolationType {
 ///
 /// This is derived from `data[3]` of an error frame.
 #[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Location {
     /// Unspecified
@@ -473,6 +1019,7 @@ impl TryFrom<u8> for Location {
 ///
 /// This is derived from `data[4]` of an error frame.
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum TransceiverError {
     /// Unsecified
@@ -497,6 +1044,27 @@ pub enum TransceiverError {
     CanLowShortToCanHigh = 0x80,
 }
 
+impl error::Error for TransceiverError {}
+
+impl fmt::Display for TransceiverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TransceiverError::*;
+        let msg = match *self {
+            Unspecified => "unspecified",
+            CanHighNoWire => "CAN_H no wire",
+            CanHighShortToBat => "CAN_H short to BAT",
+            CanHighShortToVcc => "CAN_H short to VCC",
+            CanHighShortToGnd => "CAN_H short to GND",
+            CanLowNoWire => "CAN_L no wire",
+            CanLowShortToBat => "CAN_L short to BAT",
+            CanLowShortToVcc => "CAN_L short to VCC",
+            CanLowShortToGnd => "CAN_L short to GND",
+            CanLowShortToCanHigh => "CAN_L short to CAN_H",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
 impl TryFrom<u8> for TransceiverError {
     type Error = CanErrorDecodingFailure;
 
@@ -519,21 +1087,27 @@ impl TryFrom<u8> for TransceiverError {
 }
 
 /// Get the controller specific error information.
+///
+/// In an error frame, the controller specific information starts at
+/// `data[5]` and runs to the end of the data. For a classic error frame
+/// that's the last three bytes, but some controllers put more in the
+/// tail of a longer, FD, error frame.
 pub trait ControllerSpecificErrorInformation {
     /// Get the controller specific error information.
+    ///
+    /// This is `None` if the frame's data doesn't reach `data[5]`.
     fn get_ctrl_err(&self) -> Option<&[u8]>;
+
+    /// Gets the number of bytes of controller specific error information.
+    fn ctrl_err_len(&self) -> usize {
+        self.get_ctrl_err().map_or(0, <[u8]>::len)
+    }
 }
 
 impl<T: Frame> ControllerSpecificErrorInformation for T {
     /// Get the controller specific error information.
     fn get_ctrl_err(&self) -> Option<&[u8]> {
-        let data = self.data();
-
-        if data.len() == 8 {
-            Some(&data[5..])
-        } else {
-            None
-        }
+        self.data().get(5..).filter(|info| !info.is_empty())
     }
 }
 
@@ -541,6 +1115,7 @@ impl<T: Frame> ControllerSpecificErrorInformation for T {
 
 /// Error decoding a CanError from a CanErrorFrame.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CanErrorDecodingFailure {
     /// The supplied CANFrame did not have the error bit set.
     NotAnError,
@@ -590,6 +1165,8 @@ pub enum ConstructionError {
     IDTooLarge,
     /// Larger payload reported than can be held in the frame.
     TooMuchData,
+    /// The text of a frame could not be parsed
+    InvalidFormat,
 }
 
 impl error::Error for ConstructionError {}
@@ -601,6 +1178,7 @@ impl fmt::Display for ConstructionError {
             WrongFrameType => "Incompatible frame type",
             IDTooLarge => "CAN ID too large",
             TooMuchData => "Payload is too large",
+            InvalidFormat => "Invalid frame format",
         };
         write!(f, "{}", msg)
     }
@@ -610,12 +1188,16 @@ impl fmt::Display for ConstructionError {
 
 #[cfg(test)]
 mod tests {
+    use super::{
+        CanError, CanErrorDecodingFailure, CanErrorFilter, ControllerProblem,
+        ControllerSpecificErrorInformation, ErrorSeverity,
+    };
     use crate::Error;
     use std::io;
 
     #[test]
     fn test_errors() {
-        const KIND: io::ErrorKind = io::ErrorKind::TimedOut;
+        const KIND: io::ErrorKind = io::ErrorKind::PermissionDenied;
 
         // From an IO error.
         let err = Error::from(io::Error::from(KIND));
@@ -633,4 +1215,208 @@ mod tests {
             panic!("Wrong error conversion");
         }
     }
+
+    #[test]
+    fn test_ctrl_err() {
+        use crate::{CanFdFrame, CanFrame, EmbeddedFrame, StandardId};
+
+        let id = StandardId::new(0x100).unwrap();
+
+        let frame = CanFrame::new(id, &[0, 1, 2, 3, 4, 5, 6, 7]).unwrap();
+        assert_eq!(Some(&[5, 6, 7][..]), frame.get_ctrl_err());
+        assert_eq!(3, frame.ctrl_err_len());
+
+        let frame = CanFdFrame::new(id, &[0xAA; 12]).unwrap();
+        assert_eq!(Some(&[0xAA; 7][..]), frame.get_ctrl_err());
+        assert_eq!(7, frame.ctrl_err_len());
+
+        for n in 0..=5 {
+            let frame = CanFrame::new(id, &[0; 8][..n]).unwrap();
+            assert!(frame.get_ctrl_err().is_none());
+            assert_eq!(0, frame.ctrl_err_len());
+        }
+    }
+
+    #[test]
+    fn test_source() {
+        use std::error::Error as _;
+
+        let err = CanError::ControllerProblem(ControllerProblem::ReceiveErrorPassive);
+        let source = err.source().unwrap();
+        assert_eq!(
+            ControllerProblem::ReceiveErrorPassive.to_string(),
+            source.to_string()
+        );
+
+        // The transparent wrapper forwards to the inner error's source
+        let err = Error::from(err);
+        assert_eq!(source.to_string(), err.source().unwrap().to_string());
+        assert!(CanError::NoAck.source().is_none());
+    }
+
+    #[test]
+    fn test_try_from_frame() {
+        use crate::{CanErrorFrame, CanFrame, EmbeddedFrame, StandardId};
+        use std::convert::TryFrom;
+
+        let frame = CanFrame::from(CanErrorFrame::new_error(0x0020, &[]).unwrap());
+        assert!(matches!(CanError::try_from(&frame), Ok(CanError::NoAck)));
+
+        let id = StandardId::new(0x0020).unwrap();
+        let frame = CanFrame::new(id, &[0; 8]).unwrap();
+        assert!(matches!(
+            CanError::try_from(&frame),
+            Err(CanErrorDecodingFailure::NotAnError)
+        ));
+
+        let mut cframe = crate::frame::can_frame_default();
+        cframe.can_id = crate::frame::CAN_ERR_FLAG | 0x0020;
+        cframe.can_dlc = 4;
+        let frame = CanFrame::from(cframe);
+        assert!(matches!(
+            CanError::try_from(&frame),
+            Err(CanErrorDecodingFailure::NotEnoughData(4))
+        ));
+    }
+
+    #[test]
+    fn test_error_filter_builder() {
+        use super::CanErrorClass;
+
+        assert_eq!(0, CanErrorFilter::none().bits());
+
+        let filter = CanErrorFilter::none()
+            .bus_off()
+            .controller_problem()
+            .with_class(CanErrorClass::Restarted);
+        assert_eq!(0x0144, filter.bits());
+        assert!(filter.accepts(&CanError::BusOff));
+        assert!(!filter.accepts(&CanError::NoAck));
+
+        let filter = CanErrorFilter::none()
+            .tx_timeout()
+            .lost_arbitration()
+            .protocol_violation()
+            .transceiver()
+            .no_ack()
+            .bus_error()
+            .error_counters();
+        assert_eq!(0x02BB, filter.bits());
+    }
+
+    #[test]
+    fn test_err_mask() {
+        use super::{err_mask, CanErrorClass};
+
+        let mask = err_mask::BUS_OFF | err_mask::NO_ACK;
+        assert_eq!(0x0060, mask);
+        assert_eq!(err_mask::BUS_OFF, CanError::BusOff.error_class());
+        assert_eq!(err_mask::ERROR_COUNTERS, CanErrorClass::ErrorCounters.bit());
+
+        let filter = CanErrorFilter::new(mask);
+        assert!(filter.accepts(&CanError::NoAck));
+        assert!(!filter.accepts(&CanError::Restarted));
+    }
+
+    #[test]
+    fn test_severity() {
+        assert!(!CanError::BusOff.is_recoverable());
+        assert_eq!(ErrorSeverity::Fatal, CanError::BusOff.severity());
+
+        let err = CanError::ControllerProblem(ControllerProblem::ReceiveBufferOverflow);
+        assert!(!err.is_recoverable());
+
+        let err = CanError::ControllerProblem(ControllerProblem::ReceiveErrorPassive);
+        assert!(err.is_recoverable());
+        assert_eq!(ErrorSeverity::Warning, err.severity());
+
+        assert!(CanError::NoAck.is_recoverable());
+        assert!(CanError::Restarted.is_recoverable());
+        assert_eq!(ErrorSeverity::Info, CanError::LostArbitration(3).severity());
+        assert!(ErrorSeverity::Info < ErrorSeverity::Fatal);
+    }
+
+    #[test]
+    fn test_error_filter() {
+        use crate::{errors::ControllerProblem, CanError, CanErrorFrame};
+
+        let filter = CanErrorFilter::for_errors(&[
+            CanError::BusOff,
+            CanError::NoAck,
+            CanError::LostArbitration(0),
+        ]);
+        assert_eq!(0x0062, filter.bits());
+        assert!(filter.accepts(&CanError::LostArbitration(12)));
+        assert!(!filter.accepts(&CanError::Restarted));
+
+        let err = CanError::ControllerProblem(ControllerProblem::ReceiveErrorPassive);
+        let frame = CanErrorFrame::from(err);
+        assert_eq!(frame.error_bits(), err.error_class());
+
+        assert_eq!(0, CanErrorFilter::for_errors(&[]).bits());
+        assert_eq!(crate::frame::ERR_MASK_ALL, CanErrorFilter::all().bits());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_json() {
+        use super::{Location, ViolationType};
+
+        let errs = [
+            CanError::BusOff,
+            CanError::LostArbitration(12),
+            CanError::ControllerProblem(ControllerProblem::ReceiveErrorPassive),
+            CanError::ProtocolViolation {
+                vtype: ViolationType::BitStuffingError,
+                location: Location::Id0400,
+            },
+        ];
+        for err in errs {
+            let json = serde_json::to_string(&err).unwrap();
+            let rt: CanError = serde_json::from_str(&json).unwrap();
+            assert_eq!(format!("{:?}", err), format!("{:?}", rt));
+        }
+    }
+
+    #[test]
+    fn test_terminal_errors() {
+        let err = Error::from(io::ErrorKind::UnexpectedEof);
+        assert!(matches!(err, Error::Closed(_)));
+        assert!(err.is_terminal());
+
+        let err = Error::from(io::Error::from_raw_os_error(libc::ENODEV));
+        match &err {
+            Error::InterfaceNotFound(err) => assert_eq!(Some(libc::ENODEV), err.raw_os_error()),
+            _ => panic!("Expected InterfaceNotFound, got {:?}", err),
+        }
+        assert!(err.is_terminal());
+
+        let err = Error::from(io::Error::from_raw_os_error(libc::EBADF));
+        assert!(matches!(err, Error::Io(_)));
+        assert!(!err.is_terminal());
+
+        let err = Error::from(io::Error::from_raw_os_error(libc::ENETDOWN));
+        assert!(matches!(err, Error::Io(_)));
+        assert!(!err.is_terminal());
+
+        assert!(!Error::from(io::ErrorKind::WouldBlock).is_terminal());
+
+        let err = Error::from(io::ErrorKind::TimedOut);
+        assert!(matches!(err, Error::TimedOut));
+        assert!(!err.is_terminal());
+    }
+
+    #[test]
+    fn test_controller_problem_flags() {
+        use super::ControllerProblem::{self, *};
+        use std::convert::TryFrom;
+
+        assert!(ControllerProblem::try_from(0x05).is_err());
+        assert_eq!(
+            vec![ReceiveBufferOverflow, ReceiveErrorWarning],
+            ControllerProblem::flags_from(0x05)
+        );
+        assert_eq!(vec![Unspecified], ControllerProblem::flags_from(0));
+        assert_eq!(vec![Active], ControllerProblem::flags_from(0xC0));
+    }
 }
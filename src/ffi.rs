@@ -0,0 +1,136 @@
+// socketcan/src/ffi.rs
+//
+// A C-compatible CAN frame for passing across an FFI boundary.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! A C-compatible CAN frame for passing across an FFI boundary.
+//!
+//! This is for applications that embed this crate into a larger C or C++
+//! program, and need to pass frames back and forth by value. The
+//! [`CCanFrame`] has the same memory layout as the kernel's
+//! `struct can_frame` from `<linux/can.h>`, so C code can use it directly
+//! as that struct.
+//!
+//! This module is only available with the "ffi" build feature.
+
+use crate::{frame::can_frame_default, CanFrame, ConstructionError};
+use libc::{can_frame, canid_t, CAN_MAX_DLEN};
+use std::convert::TryFrom;
+
+/// A classic CAN 2.0 frame with a stable, C-compatible memory layout.
+///
+/// The layout matches the Linux `struct can_frame`: 16 bytes, aligned to 8
+/// bytes, with the fields at the same offsets. This layout is part of the
+/// Linux user space ABI, and won't change in a compatible release of this
+/// crate.
+///
+/// The `can_id` is the composite SocketCAN ID word: the 11 or 29-bit ID in
+/// the low bits, along with the EFF/RTR/ERR flags in the high bits. Like
+/// in the kernel, it is in the native byte order of the host, _not_ the
+/// network byte order, so it can be used directly as an integer on either
+/// side of the boundary.
+#[repr(C, align(8))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CCanFrame {
+    /// The ID word, with the EFF/RTR/ERR flags, in host byte order
+    pub can_id: u32,
+    /// The number of data bytes (0-8)
+    pub len: u8,
+    /// Padding
+    pub pad: u8,
+    /// Reserved
+    pub res0: u8,
+    /// The optional DLC for a data length of 8 (9-15), or zero
+    pub len8_dlc: u8,
+    /// The data bytes. Only the first `len` are valid.
+    pub data: [u8; CAN_MAX_DLEN],
+}
+
+impl From<can_frame> for CCanFrame {
+    fn from(frame: can_frame) -> Self {
+        Self {
+            can_id: frame.can_id,
+            len: frame.can_dlc,
+            pad: 0,
+            res0: 0,
+            len8_dlc: frame.len8_dlc,
+            data: frame.data,
+        }
+    }
+}
+
+impl From<CanFrame> for CCanFrame {
+    fn from(frame: CanFrame) -> Self {
+        Self::from(*frame.as_ref())
+    }
+}
+
+impl From<&CanFrame> for CCanFrame {
+    fn from(frame: &CanFrame) -> Self {
+        Self::from(*frame.as_ref())
+    }
+}
+
+impl TryFrom<CCanFrame> for CanFrame {
+    type Error = ConstructionError;
+
+    /// Converts a frame from C code into a `CanFrame`.
+    ///
+    /// Since the frame comes from outside of Rust, it is validated, and
+    /// this fails if the data length is more than 8 bytes.
+    fn try_from(frame: CCanFrame) -> Result<Self, ConstructionError> {
+        if frame.len as usize > CAN_MAX_DLEN {
+            return Err(ConstructionError::TooMuchData);
+        }
+
+        let mut cframe = can_frame_default();
+        cframe.can_id = frame.can_id as canid_t;
+        cframe.can_dlc = frame.len;
+        cframe.len8_dlc = frame.len8_dlc;
+        cframe.data = frame.data;
+        Ok(CanFrame::from(cframe))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EmbeddedFrame, Frame};
+    use std::mem;
+
+    #[test]
+    fn test_layout() {
+        let frame = CCanFrame::default();
+        let offset = |p: *const u8| p as usize - &frame as *const _ as usize;
+
+        assert_eq!(mem::size_of::<can_frame>(), mem::size_of::<CCanFrame>());
+        assert_eq!(8, mem::align_of::<CCanFrame>());
+        assert_eq!(4, offset(&frame.len));
+        assert_eq!(7, offset(&frame.len8_dlc));
+        assert_eq!(8, offset(frame.data.as_ptr()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let frame = CanFrame::from_raw_id(0x12345, &[1, 2, 3]).unwrap();
+
+        let cframe = CCanFrame::from(&frame);
+        assert_eq!(0x12345 | libc::CAN_EFF_FLAG, cframe.can_id);
+        assert_eq!(3, cframe.len);
+
+        let frame2 = CanFrame::try_from(cframe).unwrap();
+        assert_eq!(frame.id_word(), frame2.id_word());
+        assert_eq!(frame.data(), frame2.data());
+
+        let bad = CCanFrame { len: 9, ..cframe };
+        assert!(CanFrame::try_from(bad).is_err());
+    }
+}
@@ -30,9 +30,14 @@
 //! [Error](https://doc.rust-lang.org/std/error/trait.Error.html) types.
 //!
 
-use crate::{CanError, ConstructionError};
+use crate::{
+    as_bytes_mut,
+    errors::{err_mask, CanErrorClass},
+    CanError, ConstructionError,
+};
 use bitflags::bitflags;
 use embedded_can::{ExtendedId, Frame as EmbeddedFrame, Id, StandardId};
+use hex::FromHex;
 use itertools::Itertools;
 use libc::{can_frame, canfd_frame, canid_t};
 use std::{
@@ -99,6 +104,80 @@ pub fn id_from_raw(id: u32) -> Option<Id> {
     Some(id)
 }
 
+/// The payload lengths that can be encoded in a CAN FD frame, indexed by DLC.
+const FD_DLC_LEN: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+
+/// Gets the DLC for an FD payload of the given length.
+///
+/// FD frames can only carry 0-8, 12, 16, 20, 24, 32, 48, or 64 bytes, so
+/// other lengths map to the smallest DLC that will hold them. A length of
+/// 10 gives a DLC of 9 (12 bytes). Lengths over 64 return `None`.
+pub fn fd_len_to_dlc(len: usize) -> Option<u8> {
+    FD_DLC_LEN
+        .iter()
+        .position(|&n| len <= n as usize)
+        .map(|dlc| dlc as u8)
+}
+
+/// Gets the FD payload length for the given DLC.
+///
+/// Only the lower 4 bits of the DLC are used, as in the kernel.
+pub fn fd_dlc_to_len(dlc: u8) -> usize {
+    FD_DLC_LEN[(dlc & 0x0F) as usize] as usize
+}
+
+/// Gets a valid FD payload length that will hold `len` bytes,
+/// or `None` if it's too long.
+fn fd_valid_len(len: usize) -> Option<usize> {
+    fd_len_to_dlc(len).map(fd_dlc_to_len)
+}
+
+/// The byte order of an integer in a frame's data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Most significant byte first (network order, "Motorola")
+    BigEndian,
+    /// Least significant byte first ("Intel")
+    LittleEndian,
+}
+
+/// The number of data bytes used to hold an integer in a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameWidth {
+    /// The fewest bytes that can hold the value, with at least one byte
+    Minimal,
+    /// A fixed number of bytes, from 1 to 8, zero-padded if needed
+    Fixed(usize),
+}
+
+/// The ways to format a CAN ID as hex, with [`format_can_id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum IdStyle {
+    /// The fewest digits needed, like `7F` or `1ABCD`
+    Minimal,
+    /// Zero-padded to 3 digits for a standard ID, and 8 for an extended
+    /// one, like `07F` or `0001ABCD`. This is the form used by candump.
+    #[default]
+    Padded,
+    /// The padded form with a `0x` prefix, like `0x07F` or `0x0001ABCD`
+    WithPrefix,
+}
+
+/// Formats a CAN ID as upper-case hex, in the specified style.
+///
+/// The padded styles make it possible to tell a standard ID from an
+/// extended one with the same value, since they have a different number
+/// of digits.
+pub fn format_can_id(id: Id, style: IdStyle) -> String {
+    match (style, id) {
+        (IdStyle::Minimal, Id::Standard(id)) => format!("{:X}", id.as_raw()),
+        (IdStyle::Minimal, Id::Extended(id)) => format!("{:X}", id.as_raw()),
+        (IdStyle::Padded, Id::Standard(id)) => format!("{:03X}", id.as_raw()),
+        (IdStyle::Padded, Id::Extended(id)) => format!("{:08X}", id.as_raw()),
+        (IdStyle::WithPrefix, id) => format!("0x{}", format_can_id(id, IdStyle::Padded)),
+    }
+}
+
 // ===== can_frame =====
 
 /// Creates a default C `can_frame`.
@@ -178,9 +257,28 @@ pub trait Frame: EmbeddedFrame {
     }
 
     /// Get the composite SocketCAN ID word, with EFF/RTR/ERR flags
+    ///
+    /// This is the raw `can_id` field of the frame. For an extended frame
+    /// it has `CAN_EFF_FLAG` set, so it won't compare equal to the bare
+    /// identifier. Use [`raw_id`](Frame::raw_id) for that.
     fn id_word(&self) -> canid_t;
 
     /// Return the actual raw CAN ID (without EFF/RTR/ERR flags)
+    ///
+    /// The identifier is masked to 11 or 29 bits depending on whether the
+    /// frame is extended, so it can be compared directly to an integer ID.
+    /// Note that this can't tell a standard ID from an extended one with
+    /// the same value; compare the [`embedded_can::Id`] from
+    /// [`EmbeddedFrame::id`] when that matters.
+    ///
+    /// ```
+    /// use socketcan::{CanFrame, EmbeddedFrame, ExtendedId, Frame};
+    ///
+    /// let id = ExtendedId::new(0x123).unwrap();
+    /// let frame = CanFrame::new(id, &[]).unwrap();
+    /// assert_eq!(0x123, frame.raw_id());
+    /// assert_ne!(0x123, frame.id_word());
+    /// ```
     fn raw_id(&self) -> canid_t {
         let mask = if self.is_extended() {
             CAN_EFF_MASK
@@ -213,6 +311,17 @@ pub trait Frame: EmbeddedFrame {
         self.dlc()
     }
 
+    /// Gets the data length code (DLC) as it appears on the wire.
+    ///
+    /// For a classic data frame this is the length of the data. A remote
+    /// frame has no data but gives the requested length, and an FD frame
+    /// encodes longer payloads into a 4-bit code, so a 64-byte FD frame
+    /// has a DLC of 15. This differs from [`EmbeddedFrame::dlc`], which
+    /// gives the number of bytes.
+    fn raw_dlc(&self) -> u8 {
+        self.data().len() as u8
+    }
+
     /// Check if frame is an error message
     fn is_error_frame(&self) -> bool {
         self.id_flags().contains(IdFlags::ERR)
@@ -237,6 +346,43 @@ pub enum CanRawFrame {
     Fd(canfd_frame),
 }
 
+impl CanRawFrame {
+    /// Creates a raw frame from the bytes of a kernel frame struct.
+    ///
+    /// This takes the bytes of a C `can_frame` (16 bytes) or `canfd_frame`
+    /// (72 bytes), as would be read from a CAN socket, or received from
+    /// some other source, like a character device or shared memory. It is
+    /// the same decoding used when reading frames from a socket.
+    ///
+    /// The length of the buffer must be exactly the size of one of the
+    /// structs, otherwise this returns a `WrongFrameType` error. If the
+    /// payload length in the frame is larger than the struct can hold, it
+    /// returns a `TooMuchData` error.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, ConstructionError> {
+        use libc::{CANFD_MTU, CAN_MTU};
+
+        match buf.len() {
+            CAN_MTU => {
+                let mut frame = can_frame_default();
+                as_bytes_mut(&mut frame).copy_from_slice(buf);
+                if frame.can_dlc as usize > CAN_MAX_DLEN {
+                    return Err(ConstructionError::TooMuchData);
+                }
+                Ok(frame.into())
+            }
+            CANFD_MTU => {
+                let mut frame = canfd_frame_default();
+                as_bytes_mut(&mut frame).copy_from_slice(buf);
+                if frame.len as usize > CANFD_MAX_DLEN {
+                    return Err(ConstructionError::TooMuchData);
+                }
+                Ok(frame.into())
+            }
+            _ => Err(ConstructionError::WrongFrameType),
+        }
+    }
+}
+
 impl From<can_frame> for CanRawFrame {
     fn from(frame: can_frame) -> Self {
         Self::Classic(frame)
@@ -262,6 +408,247 @@ pub enum CanAnyFrame {
     Fd(CanFdFrame),
 }
 
+impl CanAnyFrame {
+    /// Creates a frame from the bytes of a C `can_frame` or `canfd_frame`
+    /// struct.
+    ///
+    /// The type of frame is determined by the length of the buffer, then
+    /// by its flags. See [`CanRawFrame::from_bytes`] for details.
+    pub fn from_raw_frame_bytes(buf: &[u8]) -> Result<Self, ConstructionError> {
+        CanRawFrame::from_bytes(buf).map(Self::from)
+    }
+}
+
+impl EmbeddedFrame for CanAnyFrame {
+    /// Create a new data frame.
+    ///
+    /// This is a classic CAN 2.0 frame if the data fits, with up to 8 bytes,
+    /// otherwise an FD frame.
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        match data.len() {
+            n if n <= CAN_MAX_DLEN => CanDataFrame::new(id, data).map(Self::Normal),
+            _ => CanFdFrame::new(id, data).map(Self::Fd),
+        }
+    }
+
+    /// Create a new remote transmission request frame.
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        CanRemoteFrame::new_remote(id, dlc).map(Self::Remote)
+    }
+
+    /// Check if frame uses 29-bit extended ID format.
+    fn is_extended(&self) -> bool {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.is_extended(),
+            Remote(frame) => frame.is_extended(),
+            Error(frame) => frame.is_extended(),
+            Fd(frame) => frame.is_extended(),
+        }
+    }
+
+    /// Check if frame is a remote transmission request.
+    fn is_remote_frame(&self) -> bool {
+        matches!(self, CanAnyFrame::Remote(_))
+    }
+
+    /// Return the frame identifier.
+    fn id(&self) -> Id {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.id(),
+            Remote(frame) => frame.id(),
+            Error(frame) => frame.id(),
+            Fd(frame) => frame.id(),
+        }
+    }
+
+    /// Data length
+    fn dlc(&self) -> usize {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.dlc(),
+            Remote(frame) => frame.dlc(),
+            Error(frame) => frame.dlc(),
+            Fd(frame) => frame.dlc(),
+        }
+    }
+
+    /// A slice into the actual data.
+    ///
+    /// This is up to 8 bytes for a classic frame, and up to 64 for FD.
+    fn data(&self) -> &[u8] {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.data(),
+            Remote(frame) => frame.data(),
+            Error(frame) => frame.data(),
+            Fd(frame) => frame.data(),
+        }
+    }
+}
+
+impl Frame for CanAnyFrame {
+    /// Get the composite SocketCAN ID word, with EFF/RTR/ERR flags
+    fn id_word(&self) -> canid_t {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.id_word(),
+            Remote(frame) => frame.id_word(),
+            Error(frame) => frame.id_word(),
+            Fd(frame) => frame.id_word(),
+        }
+    }
+
+    /// Sets the CAN ID for the frame
+    fn set_id(&mut self, id: impl Into<Id>) {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.set_id(id),
+            Remote(frame) => frame.set_id(id),
+            Error(frame) => frame.set_id(id),
+            Fd(frame) => frame.set_id(id),
+        }
+    }
+
+    /// Sets the data payload of the frame.
+    ///
+    /// This doesn't change the type of frame, so a classic frame can only
+    /// take up to 8 bytes.
+    fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError> {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.set_data(data),
+            Remote(frame) => frame.set_data(data),
+            Error(frame) => frame.set_data(data),
+            Fd(frame) => frame.set_data(data),
+        }
+    }
+
+    /// Gets the data length code (DLC) as it appears on the wire.
+    fn raw_dlc(&self) -> u8 {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.raw_dlc(),
+            Remote(frame) => frame.raw_dlc(),
+            Error(frame) => frame.raw_dlc(),
+            Fd(frame) => frame.raw_dlc(),
+        }
+    }
+}
+
+impl fmt::Display for CanAnyFrame {
+    /// Formats the frame in the `ID#DATA` notation of `cansend` and
+    /// `candump` logs.
+    ///
+    /// See the [`FromStr`](std::str::FromStr) implementation for the
+    /// format, which this round-trips with.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id_word = self.id_word();
+
+        if id_word & CAN_ERR_FLAG != 0 {
+            write!(f, "{:08X}#", id_word & (CAN_ERR_MASK | CAN_ERR_FLAG))?;
+        } else if id_word & CAN_EFF_FLAG != 0 {
+            write!(f, "{:08X}#", id_word & CAN_EFF_MASK)?;
+        } else {
+            write!(f, "{:03X}#", id_word & CAN_SFF_MASK)?;
+        }
+
+        let data = match self {
+            Self::Remote(frame) => {
+                f.write_str("R")?;
+                if frame.dlc() > 0 {
+                    write!(f, "{:X}", frame.dlc())?;
+                }
+                &[]
+            }
+            Self::Fd(frame) => {
+                write!(f, "#{:X}", frame.flags().bits())?;
+                frame.data()
+            }
+            _ => self.data(),
+        };
+
+        for b in data {
+            write!(f, "{:02X}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for CanAnyFrame {
+    type Err = ConstructionError;
+
+    /// Parses a frame in the `ID#DATA` notation used by `cansend`.
+    ///
+    /// ```text
+    /// <can_id>#{data}          Classic data frame
+    /// <can_id>#R{len}          Classic remote frame, with an optional length
+    /// <can_id>##<flags>{data}  FD frame, with a single hex digit of flags
+    /// ```
+    ///
+    /// The `<can_id>` is 3 hex digits for a standard ID, or 8 for an
+    /// extended ID. An 8-digit ID with the error flag (0x20000000) set is
+    /// an error frame. The data bytes are pairs of hex digits, optionally
+    /// separated by dots, like "11.2233.44".
+    ///
+    /// An ID that's out of range for its number of digits is an
+    /// `IDTooLarge` error, and too much data is `TooMuchData`. Anything
+    /// else that doesn't fit the format is an `InvalidFormat` error.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use ConstructionError::InvalidFormat;
+
+        let (id_str, rest) = s.trim().split_once('#').ok_or(InvalidFormat)?;
+
+        if !id_str.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(InvalidFormat);
+        }
+        let raw_id = u32::from_str_radix(id_str, 16).map_err(|_| InvalidFormat)?;
+
+        let (can_id, is_err) = match id_str.len() {
+            3 if raw_id <= CAN_SFF_MASK => (raw_id, false),
+            8 if raw_id & CAN_ERR_FLAG != 0 => (raw_id, true),
+            8 if raw_id <= CAN_EFF_MASK => (raw_id | CAN_EFF_FLAG, false),
+            3 | 8 => return Err(ConstructionError::IDTooLarge),
+            _ => return Err(InvalidFormat),
+        };
+
+        let parse_data = |s: &str| {
+            let hex: String = s.chars().filter(|&c| c != '.').collect();
+            Vec::<u8>::from_hex(hex).map_err(|_| InvalidFormat)
+        };
+
+        if let Some(fd) = rest.strip_prefix('#') {
+            let mut chars = fd.chars();
+            let flags = chars
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or(InvalidFormat)?;
+            let data = parse_data(chars.as_str())?;
+            let frame = CanFdFrame::init(can_id, &data, FdFlags::from_bits_truncate(flags as u8))?;
+            Ok(frame.into())
+        } else if is_err {
+            let data = parse_data(rest)?;
+            Ok(CanErrorFrame::new_error(can_id, &data)?.into())
+        } else if let Some(len) = rest.strip_prefix('R') {
+            let dlc = match len {
+                "" => 0,
+                _ => len.parse::<usize>().map_err(|_| InvalidFormat)?,
+            };
+            if dlc > CAN_MAX_DLEN {
+                return Err(ConstructionError::TooMuchData);
+            }
+            let mut frame = can_frame_default();
+            frame.can_id = can_id | CAN_RTR_FLAG;
+            frame.can_dlc = dlc as u8;
+            Ok(CanFrame::from(frame).into())
+        } else {
+            let data = parse_data(rest)?;
+            Ok(CanDataFrame::init(can_id, &data)?.into())
+        }
+    }
+}
+
 impl fmt::UpperHex for CanAnyFrame {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -284,6 +671,24 @@ impl From<CanFrame> for CanAnyFrame {
     }
 }
 
+impl From<CanDataFrame> for CanAnyFrame {
+    fn from(frame: CanDataFrame) -> Self {
+        Self::Normal(frame)
+    }
+}
+
+impl From<CanRemoteFrame> for CanAnyFrame {
+    fn from(frame: CanRemoteFrame) -> Self {
+        Self::Remote(frame)
+    }
+}
+
+impl From<CanErrorFrame> for CanAnyFrame {
+    fn from(frame: CanErrorFrame) -> Self {
+        Self::Error(frame)
+    }
+}
+
 impl From<can_frame> for CanAnyFrame {
     fn from(frame: can_frame) -> Self {
         let frame = CanFrame::from(frame);
@@ -358,6 +763,170 @@ pub enum CanFrame {
     Error(CanErrorFrame),
 }
 
+impl CanFrame {
+    /// Creates a frame from the bytes of a C `can_frame` struct.
+    ///
+    /// This decodes the frame into the proper type from its flags, like
+    /// when it is read from a socket. The buffer must be exactly the size
+    /// of a `can_frame`. To decode the bytes of either a classic or an FD
+    /// frame, use [`CanAnyFrame::from_raw_frame_bytes`].
+    pub fn from_raw_frame_bytes(buf: &[u8]) -> Result<Self, ConstructionError> {
+        match CanRawFrame::from_bytes(buf)? {
+            CanRawFrame::Classic(frame) => Ok(frame.into()),
+            CanRawFrame::Fd(_) => Err(ConstructionError::WrongFrameType),
+        }
+    }
+
+    /// Creates a remote frame, requesting the specified data length.
+    ///
+    /// The DLC is independent of any data, since a remote frame carries
+    /// none; some nodes respond differently depending on the length that
+    /// was requested. This sets the `CAN_RTR_FLAG` in the ID word.
+    ///
+    /// This is the same as [`EmbeddedFrame::new_remote`], but reports a
+    /// DLC larger than 8 as a `TooMuchData` error, rather than `None`.
+    pub fn try_new_remote(id: impl Into<Id>, dlc: u8) -> Result<Self, ConstructionError> {
+        CanRemoteFrame::new_remote(id, dlc as usize)
+            .map(CanFrame::Remote)
+            .ok_or(ConstructionError::TooMuchData)
+    }
+
+    /// Creates a frame from a raw ID, the ID flags, and data, validating
+    /// all of them up front.
+    ///
+    /// The flags select the kind of frame:
+    /// - no flags, or just `EFF`: a data frame with up to 8 bytes
+    /// - `RTR`, and optionally `EFF`: a remote frame, which can't have data
+    /// - `ERR`: an error frame, with the error class bits in `id`
+    ///
+    /// The `id` must fit in 11 bits, or 29 bits with the `EFF` flag, and is
+    /// never silently masked; anything larger is an `IDTooLarge` error.
+    /// More than 8 bytes of data is a `TooMuchData` error. Inconsistent
+    /// flags, like `RTR` with `ERR`, `ERR` with `EFF`, or `RTR` with data,
+    /// are a `WrongFrameType` error.
+    ///
+    /// To create a remote frame requesting a specific data length, use
+    /// [`try_new_remote`](Self::try_new_remote).
+    pub fn try_new(id: u32, data: &[u8], flags: IdFlags) -> Result<Self, ConstructionError> {
+        use ConstructionError::*;
+
+        let is_eff = flags.contains(IdFlags::EFF);
+        let is_rtr = flags.contains(IdFlags::RTR);
+        let is_err = flags.contains(IdFlags::ERR);
+
+        if (is_rtr && (is_err || !data.is_empty())) || (is_err && is_eff) {
+            return Err(WrongFrameType);
+        }
+
+        let mask = match (is_err, is_eff) {
+            (true, _) => CAN_ERR_MASK,
+            (_, true) => CAN_EFF_MASK,
+            _ => CAN_SFF_MASK,
+        };
+        if id > mask {
+            return Err(IDTooLarge);
+        }
+        if data.len() > CAN_MAX_DLEN {
+            return Err(TooMuchData);
+        }
+
+        let can_id = id | flags.bits();
+        if is_err {
+            CanErrorFrame::new_error(can_id, data).map(Self::Error)
+        } else if is_rtr {
+            let mut frame = can_frame_default();
+            frame.can_id = can_id;
+            Ok(Self::Remote(CanRemoteFrame(frame)))
+        } else {
+            CanDataFrame::init(can_id, data).map(Self::Data)
+        }
+    }
+
+    /// Creates a data frame holding an integer value.
+    ///
+    /// The value is written in the specified byte order, using either the
+    /// minimal number of bytes, or a fixed width. A fixed width larger than
+    /// the value needs is zero-padded, on the left for big-endian, or on
+    /// the right for little-endian, so the data still reads as the same
+    /// number. This returns `None` if the fixed width is zero, more than
+    /// 8 bytes, or too small to hold the value.
+    ///
+    /// ```
+    /// use socketcan::{
+    ///     frame::{ByteOrder, FrameWidth},
+    ///     CanFrame, EmbeddedFrame, StandardId,
+    /// };
+    ///
+    /// let id = StandardId::new(0x100).unwrap();
+    /// let frame =
+    ///     CanFrame::from_u32(id, 0x1234, ByteOrder::BigEndian, FrameWidth::Minimal).unwrap();
+    /// assert_eq!(&[0x12, 0x34], frame.data());
+    /// ```
+    pub fn from_u32(
+        id: impl Into<Id>,
+        value: u32,
+        order: ByteOrder,
+        width: FrameWidth,
+    ) -> Option<Self> {
+        let needed = (4 - value.leading_zeros() as usize / 8).max(1);
+        let n = match width {
+            FrameWidth::Minimal => needed,
+            FrameWidth::Fixed(n) if (needed..=CAN_MAX_DLEN).contains(&n) => n,
+            FrameWidth::Fixed(_) => return None,
+        };
+
+        let mut data = [0u8; CAN_MAX_DLEN];
+        match order {
+            ByteOrder::BigEndian => {
+                data[n - needed..n].copy_from_slice(&value.to_be_bytes()[4 - needed..])
+            }
+            ByteOrder::LittleEndian => {
+                data[..needed].copy_from_slice(&value.to_le_bytes()[..needed])
+            }
+        }
+        CanDataFrame::new(id, &data[..n]).map(CanFrame::Data)
+    }
+
+    /// Reads the data of the frame as an integer value.
+    ///
+    /// The whole data payload is taken as one integer in the specified
+    /// byte order, so this is the reverse of [`from_u32`](Self::from_u32).
+    /// This returns `None` for a frame with no data, for a remote or error
+    /// frame, or if the value is too large for a `u32`.
+    pub fn as_u32(&self, order: ByteOrder) -> Option<u32> {
+        let data = match self {
+            CanFrame::Data(frame) => frame.data(),
+            _ => return None,
+        };
+        if data.is_empty() {
+            return None;
+        }
+
+        let mut bytes = [0u8; CAN_MAX_DLEN];
+        let n = data.len();
+        let value = match order {
+            ByteOrder::BigEndian => {
+                bytes[CAN_MAX_DLEN - n..].copy_from_slice(data);
+                u64::from_be_bytes(bytes)
+            }
+            ByteOrder::LittleEndian => {
+                bytes[..n].copy_from_slice(data);
+                u64::from_le_bytes(bytes)
+            }
+        };
+        u32::try_from(value).ok()
+    }
+
+    /// Creates a data frame from a fixed-size payload.
+    ///
+    /// Unlike [`EmbeddedFrame::new`], this can't fail, since the length
+    /// of the payload is checked at compile time. Using a `Payload` with
+    /// more than 8 bytes is a build error.
+    pub fn from_payload<const N: usize>(id: impl Into<Id>, payload: Payload<N>) -> Self {
+        CanFrame::Data(CanDataFrame::from_payload(id, payload))
+    }
+}
+
 impl AsPtr for CanFrame {
     type Inner = can_frame;
 
@@ -471,6 +1040,16 @@ impl Frame for CanFrame {
             Error(frame) => frame.set_data(data),
         }
     }
+
+    /// Gets the data length code (DLC) as it appears on the wire.
+    fn raw_dlc(&self) -> u8 {
+        use CanFrame::*;
+        match self {
+            Data(frame) => frame.raw_dlc(),
+            Remote(frame) => frame.raw_dlc(),
+            Error(frame) => frame.raw_dlc(),
+        }
+    }
 }
 
 impl Default for CanFrame {
@@ -492,6 +1071,31 @@ impl fmt::UpperHex for CanFrame {
     }
 }
 
+impl fmt::Display for CanFrame {
+    /// Formats the frame in the `ID#DATA` notation of `cansend`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&CanAnyFrame::from(*self), f)
+    }
+}
+
+impl std::str::FromStr for CanFrame {
+    type Err = ConstructionError;
+
+    /// Parses a classic CAN 2.0 frame in the `ID#DATA` notation of
+    /// `cansend`.
+    ///
+    /// See [`CanAnyFrame`] for the format. An FD frame (`ID##...`) is a
+    /// `WrongFrameType` error.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<CanAnyFrame>()? {
+            CanAnyFrame::Normal(frame) => Ok(frame.into()),
+            CanAnyFrame::Remote(frame) => Ok(frame.into()),
+            CanAnyFrame::Error(frame) => Ok(frame.into()),
+            CanAnyFrame::Fd(_) => Err(ConstructionError::WrongFrameType),
+        }
+    }
+}
+
 impl From<can_frame> for CanFrame {
     /// Create a `CanFrame` from a C `can_frame` struct.
     fn from(frame: can_frame) -> Self {
@@ -571,6 +1175,15 @@ impl CanDataFrame {
             _ => Err(ConstructionError::TooMuchData),
         }
     }
+
+    /// Creates a data frame from a fixed-size payload.
+    ///
+    /// Using a `Payload` with more than 8 bytes is a build error.
+    pub fn from_payload<const N: usize>(id: impl Into<Id>, payload: Payload<N>) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Payload::<N>::FITS_CLASSIC;
+        Self::init(id_to_canid_t(id), &payload.0).unwrap()
+    }
 }
 
 impl AsPtr for CanDataFrame {
@@ -674,6 +1287,13 @@ impl fmt::UpperHex for CanDataFrame {
     }
 }
 
+impl fmt::Display for CanDataFrame {
+    /// Formats the frame in the `ID#DATA` notation of `cansend`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&CanAnyFrame::from(*self), f)
+    }
+}
+
 impl TryFrom<can_frame> for CanDataFrame {
     type Error = ConstructionError;
 
@@ -814,6 +1434,11 @@ impl Frame for CanRemoteFrame {
     fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError> {
         self.set_dlc(data.len())
     }
+
+    /// Gets the requested data length of the remote frame.
+    fn raw_dlc(&self) -> u8 {
+        self.0.can_dlc
+    }
 }
 
 impl Default for CanRemoteFrame {
@@ -841,6 +1466,13 @@ impl fmt::UpperHex for CanRemoteFrame {
     }
 }
 
+impl fmt::Display for CanRemoteFrame {
+    /// Formats the frame in the `ID#DATA` notation of `cansend`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&CanAnyFrame::from(*self), f)
+    }
+}
+
 impl TryFrom<can_frame> for CanRemoteFrame {
     type Error = ConstructionError;
 
@@ -904,14 +1536,88 @@ impl CanErrorFrame {
         }
     }
 
+    /// Creates an error frame reporting that the controller went bus-off.
+    ///
+    /// This has the bus-off class bit (0x0040) set, with zeroed data.
+    /// Like [`restarted`](Self::restarted), it is mainly useful for testing
+    /// an application's recovery logic.
+    pub fn bus_off() -> Self {
+        Self::from(CanError::BusOff)
+    }
+
+    /// Creates an error frame reporting that the controller was restarted.
+    ///
+    /// This has the restarted class bit (0x0100) set, with zeroed data.
+    pub fn restarted() -> Self {
+        Self::from(CanError::Restarted)
+    }
+
     /// Return the error bits from the ID word of the error frame.
     pub fn error_bits(&self) -> u32 {
         self.id_word() & CAN_ERR_MASK
     }
 
+    /// Gets an iterator over the classes of the errors set in the frame.
+    ///
+    /// This is a lighter weight inspection than decoding the frame into a
+    /// `CanError`, and reports all the classes set, not just one.
+    pub fn error_classes(&self) -> impl Iterator<Item = CanErrorClass> {
+        CanErrorClass::iter_bits(self.error_bits())
+    }
+
+    /// Gets the controller's transmit and receive error counters, as
+    /// `(tx, rx)`, if they are reported in the frame.
+    ///
+    /// The counters are in `data[6]` and `data[7]` when the error counter
+    /// bit (0x0200) is set. This can be set along with other error bits,
+    /// so the counters might be present whatever the decoded `CanError`.
+    pub fn error_counters(&self) -> Option<(u8, u8)> {
+        if self.error_bits() & err_mask::ERROR_COUNTERS != 0 {
+            Some((self.data()[6], self.data()[7]))
+        } else {
+            None
+        }
+    }
+
+    /// Gets all of the errors reported in the frame, one for each error
+    /// bit that is set.
+    ///
+    /// An error frame often reports several conditions at once, like a
+    /// bus error along with a controller problem and the error counters.
+    /// Unlike [`CanErrorFrame::into_error`], which only decodes a single
+    /// error, this decodes each of them. Any set bits that don't match a
+    /// known error class are reported together as one `Unknown` error.
+    pub fn errors(&self) -> Vec<CanError> {
+        let data = &self.0.data;
+        let mut errs: Vec<_> = self
+            .error_classes()
+            .map(|class| CanError::decode(class.bit(), data))
+            .collect();
+
+        let known = CanErrorClass::ALL.iter().fold(0, |bits, c| bits | c.bit());
+        let unknown = self.error_bits() & !known;
+        if unknown != 0 {
+            errs.push(CanError::Unknown(unknown));
+        }
+        errs
+    }
+
+    /// Decodes the error in the frame, without consuming it.
+    ///
+    /// This is the same decoding as `CanError::from`, with the same
+    /// caveat that only one error is reported for the frame. See
+    /// [`errors`](Self::errors) to get all of them.
+    pub fn as_can_error(&self) -> CanError {
+        let bits = match self.error_bits() {
+            err_mask::ERROR_COUNTERS => err_mask::ERROR_COUNTERS,
+            bits => bits & !err_mask::ERROR_COUNTERS,
+        };
+        CanError::decode(bits, &self.0.data)
+    }
+
     /// Converts this error frame into a `CanError`
     pub fn into_error(self) -> CanError {
-        CanError::from(self)
+        self.as_can_error()
     }
 }
 
@@ -1015,6 +1721,13 @@ impl fmt::UpperHex for CanErrorFrame {
     }
 }
 
+impl fmt::Display for CanErrorFrame {
+    /// Formats the frame in the `ID#DATA` notation of `cansend`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&CanAnyFrame::from(*self), f)
+    }
+}
+
 impl TryFrom<can_frame> for CanErrorFrame {
     type Error = ConstructionError;
 
@@ -1036,25 +1749,33 @@ impl From<CanError> for CanErrorFrame {
 
         let mut data = [0u8; CAN_MAX_DLEN];
         let id: canid_t = match err {
-            TransmitTimeout => 0x0001,
+            TransmitTimeout => err_mask::TX_TIMEOUT,
             LostArbitration(bit) => {
                 data[0] = bit;
-                0x0002
+                err_mask::LOST_ARBITRATION
             }
             ControllerProblem(prob) => {
                 data[1] = prob as u8;
-                0x0004
+                err_mask::CONTROLLER_PROBLEM
             }
             ProtocolViolation { vtype, location } => {
                 data[2] = vtype as u8;
                 data[3] = location as u8;
-                0x0008
+                err_mask::PROTOCOL_VIOLATION
+            }
+            TransceiverError(err) => {
+                data[4] = err as u8;
+                err_mask::TRANSCEIVER
+            }
+            NoAck => err_mask::NO_ACK,
+            BusOff => err_mask::BUS_OFF,
+            BusError => err_mask::BUS_ERROR,
+            Restarted => err_mask::RESTARTED,
+            ErrorCounters { tx, rx } => {
+                data[6] = tx;
+                data[7] = rx;
+                err_mask::ERROR_COUNTERS
             }
-            TransceiverError => 0x0010,
-            NoAck => 0x0020,
-            BusOff => 0x0040,
-            BusError => 0x0080,
-            Restarted => 0x0100,
             DecodingFailure(_failure) => 0,
             Unknown(e) => e,
         };
@@ -1084,23 +1805,51 @@ impl CanFdFrame {
         Self::init(can_id, data, flags).ok()
     }
 
+    /// Creates an FD frame from a fixed-size payload.
+    ///
+    /// Using a `Payload` with more than 64 bytes is a build error.
+    pub fn from_payload<const N: usize>(id: impl Into<Id>, payload: Payload<N>) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Payload::<N>::FITS_FD;
+        Self::init(id_to_canid_t(id), &payload.0, FdFlags::empty()).unwrap()
+    }
+
+    /// Creates an FD frame, padding the data with the `pad` byte.
+    ///
+    /// If the data isn't one of the lengths that an FD frame can carry, it
+    /// is extended to the next valid length, like 10 bytes up to 12. The
+    /// other constructors pad with zeros.
+    pub fn new_padded(id: impl Into<Id>, data: &[u8], pad: u8) -> Result<Self, ConstructionError> {
+        let mut frame = Self(canfd_frame_default());
+        frame.0.can_id = id_to_canid_t(id);
+        frame.fill_data(data, pad)?;
+        Ok(frame)
+    }
+
     /// Initialize a FD frame from the raw components.
+    ///
+    /// The data is zero-padded up to a valid FD length.
     pub(crate) fn init(
         can_id: u32,
         data: &[u8],
         fd_flags: FdFlags,
     ) -> Result<Self, ConstructionError> {
-        match data.len() {
-            n if n <= CANFD_MAX_DLEN => {
-                let mut frame = canfd_frame_default();
-                frame.can_id = can_id;
-                frame.len = n as u8;
-                frame.flags = fd_flags.bits();
-                frame.data[..n].copy_from_slice(data);
-                Ok(Self(frame))
-            }
-            _ => Err(ConstructionError::TooMuchData),
-        }
+        let mut frame = canfd_frame_default();
+        frame.can_id = can_id;
+        frame.flags = fd_flags.bits();
+        let mut frame = Self(frame);
+        frame.fill_data(data, 0)?;
+        Ok(frame)
+    }
+
+    /// Sets the data, padding it up to a valid FD length with `pad` bytes.
+    fn fill_data(&mut self, data: &[u8], pad: u8) -> Result<(), ConstructionError> {
+        let n = data.len();
+        let len = fd_valid_len(n).ok_or(ConstructionError::TooMuchData)?;
+        self.0.len = len as u8;
+        self.0.data[..n].copy_from_slice(data);
+        self.0.data[n..len].fill(pad);
+        Ok(())
     }
 
     /// Gets the flags for the FD frame.
@@ -1209,15 +1958,15 @@ impl Frame for CanFdFrame {
     }
 
     /// Sets the data payload of the frame.
+    ///
+    /// The data is zero-padded up to a valid FD length.
     fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError> {
-        match data.len() {
-            n if n <= CANFD_MAX_DLEN => {
-                self.0.len = n as u8;
-                self.0.data[..n].copy_from_slice(data);
-                Ok(())
-            }
-            _ => Err(ConstructionError::TooMuchData),
-        }
+        self.fill_data(data, 0)
+    }
+
+    /// Gets the 4-bit FD data length code for the payload length.
+    fn raw_dlc(&self) -> u8 {
+        fd_len_to_dlc(self.0.len as usize).unwrap_or(15)
     }
 }
 
@@ -1245,6 +1994,28 @@ impl fmt::UpperHex for CanFdFrame {
     }
 }
 
+impl fmt::Display for CanFdFrame {
+    /// Formats the frame in the `ID#DATA` notation of `cansend`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&CanAnyFrame::from(*self), f)
+    }
+}
+
+impl std::str::FromStr for CanFdFrame {
+    type Err = ConstructionError;
+
+    /// Parses an FD frame in the `ID##<flags>DATA` notation of `cansend`.
+    ///
+    /// See [`CanAnyFrame`] for the format. A classic frame is a
+    /// `WrongFrameType` error.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<CanAnyFrame>()? {
+            CanAnyFrame::Fd(frame) => Ok(frame),
+            _ => Err(ConstructionError::WrongFrameType),
+        }
+    }
+}
+
 impl From<CanDataFrame> for CanFdFrame {
     fn from(frame: CanDataFrame) -> Self {
         let n = frame.dlc();
@@ -1269,12 +2040,270 @@ impl AsRef<canfd_frame> for CanFdFrame {
     }
 }
 
+// ===== Payload =====
+
+/// A frame payload with a fixed length, known at compile time.
+///
+/// This is for protocols in which a message always has the same size. A
+/// frame can be created from a `Payload` without any runtime length
+/// checks, like with [`CanFrame::from_payload`], since a payload that is
+/// too large for the type of frame fails to build.
+///
+/// ```
+/// use socketcan::{CanFrame, EmbeddedFrame, Payload, StandardId};
+///
+/// let id = StandardId::new(0x123).unwrap();
+/// let frame = CanFrame::from_payload(id, Payload([1, 2, 3, 4]));
+/// assert_eq!(&[1, 2, 3, 4], frame.data());
+/// ```
+///
+/// A payload that doesn't fit the frame is caught by the compiler:
+///
+/// ```compile_fail
+/// use socketcan::{CanFrame, Payload, StandardId};
+///
+/// let id = StandardId::new(0x123).unwrap();
+/// let frame = CanFrame::from_payload(id, Payload([0; 12]));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Payload<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Payload<N> {
+    /// Evaluates to an error at build time if the payload is too large
+    /// for a classic CAN 2.0 frame.
+    const FITS_CLASSIC: () = assert!(N <= CAN_MAX_DLEN, "payload too large for a CAN frame");
+
+    /// Evaluates to an error at build time if the payload is too large
+    /// for an FD frame.
+    const FITS_FD: () = assert!(N <= CANFD_MAX_DLEN, "payload too large for a CAN FD frame");
+
+    /// Creates a new payload from an array of bytes.
+    pub const fn new(data: [u8; N]) -> Self {
+        Self(data)
+    }
+
+    /// Gets the payload as a slice of bytes.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Gets the length of the payload, in bytes.
+    pub const fn len(&self) -> usize {
+        N
+    }
+
+    /// Determines if the payload has no data.
+    pub const fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// Consumes the payload, returning the array of bytes.
+    pub const fn into_inner(self) -> [u8; N] {
+        self.0
+    }
+}
+
+impl<const N: usize> Default for Payload<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Payload<N> {
+    fn from(data: [u8; N]) -> Self {
+        Self(data)
+    }
+}
+
+impl<const N: usize> From<Payload<N>> for [u8; N] {
+    fn from(payload: Payload<N>) -> Self {
+        payload.0
+    }
+}
+
+impl<const N: usize> TryFrom<&[u8]> for Payload<N> {
+    type Error = std::array::TryFromSliceError;
+
+    /// Creates a payload from a slice, which must be exactly `N` bytes.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        <[u8; N]>::try_from(data).map(Self)
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for Payload<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+// ===== serde =====
+
+/// Serialization of the frames, with `serde`.
+///
+/// The frames are serialized through plain mirror types, with the ID split
+/// into its value and an extended flag, like `{ "id": 291, "extended":
+/// false, ... }`, so that the type of ID isn't lost. A classic frame is
+/// tagged with its type, as "data", "remote", or "error".
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// The serialized form of a classic CAN 2.0 frame.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "lowercase")]
+    pub(super) enum CanFrameRepr {
+        Data {
+            id: u32,
+            extended: bool,
+            data: Vec<u8>,
+        },
+        Remote {
+            id: u32,
+            extended: bool,
+            dlc: usize,
+        },
+        Error {
+            bits: u32,
+            data: Vec<u8>,
+        },
+    }
+
+    /// The serialized form of a CAN FD frame.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub(super) struct CanFdFrameRepr {
+        id: u32,
+        extended: bool,
+        #[serde(default)]
+        brs: bool,
+        #[serde(default)]
+        esi: bool,
+        data: Vec<u8>,
+    }
+
+    /// Splits an ID into its raw value and whether it's extended.
+    fn id_parts(id: Id) -> (u32, bool) {
+        match id {
+            Id::Standard(id) => (id.as_raw() as u32, false),
+            Id::Extended(id) => (id.as_raw(), true),
+        }
+    }
+
+    /// Puts an ID back together from its raw value and extended flag.
+    fn id_from_parts(id: u32, extended: bool) -> Result<Id, ConstructionError> {
+        let id = if extended {
+            ExtendedId::new(id).map(Id::from)
+        } else {
+            u16::try_from(id)
+                .ok()
+                .and_then(StandardId::new)
+                .map(Id::from)
+        };
+        id.ok_or(ConstructionError::IDTooLarge)
+    }
+
+    impl From<&CanFrame> for CanFrameRepr {
+        fn from(frame: &CanFrame) -> Self {
+            let (id, extended) = id_parts(frame.id());
+            match frame {
+                CanFrame::Data(frame) => Self::Data {
+                    id,
+                    extended,
+                    data: frame.data().to_vec(),
+                },
+                CanFrame::Remote(frame) => Self::Remote {
+                    id,
+                    extended,
+                    dlc: frame.dlc(),
+                },
+                CanFrame::Error(frame) => Self::Error {
+                    bits: frame.error_bits(),
+                    data: frame.data().to_vec(),
+                },
+            }
+        }
+    }
+
+    impl TryFrom<CanFrameRepr> for CanFrame {
+        type Error = ConstructionError;
+
+        fn try_from(repr: CanFrameRepr) -> Result<Self, ConstructionError> {
+            match repr {
+                CanFrameRepr::Data { id, extended, data } => {
+                    CanDataFrame::new(id_from_parts(id, extended)?, &data)
+                        .map(CanFrame::Data)
+                        .ok_or(ConstructionError::TooMuchData)
+                }
+                CanFrameRepr::Remote { id, extended, dlc } => {
+                    CanRemoteFrame::new_remote(id_from_parts(id, extended)?, dlc)
+                        .map(CanFrame::Remote)
+                        .ok_or(ConstructionError::TooMuchData)
+                }
+                CanFrameRepr::Error { bits, data } => {
+                    CanErrorFrame::new_error(bits, &data).map(CanFrame::Error)
+                }
+            }
+        }
+    }
+
+    impl From<&CanFdFrame> for CanFdFrameRepr {
+        fn from(frame: &CanFdFrame) -> Self {
+            let (id, extended) = id_parts(frame.id());
+            Self {
+                id,
+                extended,
+                brs: frame.is_brs(),
+                esi: frame.is_esi(),
+                data: frame.data().to_vec(),
+            }
+        }
+    }
+
+    impl TryFrom<CanFdFrameRepr> for CanFdFrame {
+        type Error = ConstructionError;
+
+        fn try_from(repr: CanFdFrameRepr) -> Result<Self, ConstructionError> {
+            let id = id_from_parts(repr.id, repr.extended)?;
+            let mut flags = FdFlags::empty();
+            flags.set(FdFlags::BRS, repr.brs);
+            flags.set(FdFlags::ESI, repr.esi);
+            CanFdFrame::init(id_to_canid_t(id), &repr.data, flags)
+        }
+    }
+
+    impl Serialize for CanFrame {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            CanFrameRepr::from(self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CanFrame {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = CanFrameRepr::deserialize(deserializer)?;
+            CanFrame::try_from(repr).map_err(de::Error::custom)
+        }
+    }
+
+    impl Serialize for CanFdFrame {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            CanFdFrameRepr::from(self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CanFdFrame {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = CanFdFrameRepr::deserialize(deserializer)?;
+            CanFdFrame::try_from(repr).map_err(de::Error::custom)
+        }
+    }
+}
+
 /////////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::errors;
+    use crate::errors::{self, TransceiverError};
 
     const STD_ID: Id = Id::Standard(StandardId::MAX);
     const EXT_ID: Id = Id::Extended(ExtendedId::MAX);
@@ -1447,7 +2476,10 @@ mod tests {
         frame.can_id = CAN_ERR_FLAG | 0x0010;
 
         let err = CanError::from(CanErrorFrame(frame));
-        assert!(matches!(err, CanError::TransceiverError));
+        assert!(matches!(
+            err,
+            CanError::TransceiverError(TransceiverError::Unspecified)
+        ));
 
         let id = StandardId::new(0x0010).unwrap();
         let frame = CanErrorFrame::new(id, &[]).unwrap();
@@ -1456,7 +2488,63 @@ mod tests {
         assert!(frame.is_error_frame());
 
         let err = CanError::from(frame);
-        assert!(matches!(err, CanError::TransceiverError));
+        assert!(matches!(
+            err,
+            CanError::TransceiverError(TransceiverError::Unspecified)
+        ));
+
+        // The transceiver status is in data[4]
+        let frame = CanErrorFrame::new_error(0x0010, &[0, 0, 0, 0, 0x07]).unwrap();
+        let err = CanError::from(frame);
+        assert!(matches!(
+            err,
+            CanError::TransceiverError(TransceiverError::CanHighShortToGnd)
+        ));
+        assert_eq!("transceiver error: CAN_H short to GND", err.to_string());
+        assert_eq!(frame.data(), CanErrorFrame::from(err).data());
+
+        let frame = CanErrorFrame::new_error(0x0010, &[0, 0, 0, 0, 0x03]).unwrap();
+        assert!(matches!(
+            CanError::from(frame),
+            CanError::DecodingFailure(_)
+        ));
+
+        // The error counters are in data[6..8]
+        let frame = CanErrorFrame::new_error(0x0200, &[0, 0, 0, 0, 0, 0, 97, 12]).unwrap();
+        assert_eq!(Some((97, 12)), frame.error_counters());
+        let err = CanError::from(frame);
+        assert!(matches!(err, CanError::ErrorCounters { tx: 97, rx: 12 }));
+        assert!(matches!(
+            frame.as_can_error(),
+            CanError::ErrorCounters { tx: 97, rx: 12 }
+        ));
+        assert_eq!(0x0200, err.error_class());
+        assert_eq!("error counters: tx 97, rx 12", err.to_string());
+        assert_eq!(frame.data(), CanErrorFrame::from(err).data());
+
+        // ...and can come along with another error
+        let frame = CanErrorFrame::new_error(0x0204, &[0, 0x20, 0, 0, 0, 0, 130, 4]).unwrap();
+        assert_eq!(Some((130, 4)), frame.error_counters());
+        assert!(matches!(
+            CanError::from(frame),
+            CanError::ControllerProblem(errors::ControllerProblem::TransmitErrorPassive)
+        ));
+
+        // All of the errors in the frame
+        let frame = CanErrorFrame::new_error(0x02C4, &[0, 0x20, 0, 0, 0, 0, 130, 4]).unwrap();
+        assert!(matches!(CanError::from(frame), CanError::Unknown(0xC4)));
+        let errs = frame.errors();
+        assert_eq!(4, errs.len());
+        assert!(matches!(
+            errs[0],
+            CanError::ControllerProblem(errors::ControllerProblem::TransmitErrorPassive)
+        ));
+        assert!(matches!(errs[1], CanError::BusOff));
+        assert!(matches!(errs[2], CanError::BusError));
+        assert!(matches!(
+            errs[3],
+            CanError::ErrorCounters { tx: 130, rx: 4 }
+        ));
 
         let id = ExtendedId::new(0x0020).unwrap();
         let frame = CanErrorFrame::new(id, &[]).unwrap();
@@ -1477,6 +2565,29 @@ mod tests {
         let err = frame.into_error();
         assert!(matches!(err, CanError::TransmitTimeout));
 
+        let frame = CanErrorFrame::bus_off();
+        assert!(frame.is_error_frame());
+        assert_eq!(0x0040, frame.error_bits());
+        assert_eq!(&[0u8; 8], frame.data());
+        assert!(matches!(frame.into_error(), CanError::BusOff));
+
+        let frame = CanErrorFrame::new_error(0x0244, &[]).unwrap();
+        let classes: Vec<_> = frame.error_classes().collect();
+        assert_eq!(
+            vec![
+                CanErrorClass::Controller,
+                CanErrorClass::BusOff,
+                CanErrorClass::ErrorCounters
+            ],
+            classes
+        );
+
+        let frame = CanErrorFrame::restarted();
+        assert!(frame.is_error_frame());
+        assert_eq!(0x0100, frame.error_bits());
+        assert_eq!(&[0u8; 8], frame.data());
+        assert!(matches!(frame.into_error(), CanError::Restarted));
+
         let err = CanError::ProtocolViolation {
             vtype: errors::ViolationType::BitStuffingError,
             location: errors::Location::Id0400,
@@ -1527,6 +2638,132 @@ mod tests {
         assert!(frame.is_extended());
     }
 
+    #[test]
+    fn test_fd_flags() {
+        let mut frame = CanFdFrame::new(STD_ID, DATA).unwrap();
+        assert!(!frame.is_brs());
+        assert!(!frame.is_esi());
+
+        frame.set_brs(true);
+        assert!(frame.is_brs());
+        assert!(!frame.is_esi());
+        assert_eq!(CANFD_BRS as u8, frame.as_ref().flags);
+
+        frame.set_esi(true);
+        assert_eq!(FdFlags::BRS | FdFlags::ESI, frame.flags());
+
+        frame.set_brs(false);
+        assert!(!frame.is_brs());
+        assert!(frame.is_esi());
+        assert_eq!(CANFD_ESI as u8, frame.as_ref().flags);
+
+        let frame = CanFdFrame::with_flags(STD_ID, DATA, FdFlags::BRS).unwrap();
+        assert!(frame.is_brs());
+        assert!(!frame.is_esi());
+    }
+
+    #[test]
+    fn test_try_new() {
+        use ConstructionError::*;
+
+        const IDS: [u32; 6] = [0, 0x123, 0x7FF, 0x800, 0x1FFF_FFFF, 0x2000_0000];
+
+        for bits in 0..8 {
+            let flags = IdFlags::from_bits_truncate(bits << 29);
+            let is_eff = flags.contains(IdFlags::EFF);
+            let is_rtr = flags.contains(IdFlags::RTR);
+            let is_err = flags.contains(IdFlags::ERR);
+
+            for id in IDS {
+                for n in 0..=9 {
+                    let data: Vec<u8> = (1..=n as u8).collect();
+                    let res = CanFrame::try_new(id, &data, flags);
+
+                    let max_id = if is_eff || is_err { 0x1FFF_FFFF } else { 0x7FF };
+                    let expected = if (is_rtr && (is_err || n > 0)) || (is_err && is_eff) {
+                        Some(WrongFrameType)
+                    } else if id > max_id {
+                        Some(IDTooLarge)
+                    } else if n > 8 {
+                        Some(TooMuchData)
+                    } else {
+                        None
+                    };
+                    assert_eq!(
+                        expected,
+                        res.as_ref().err().copied(),
+                        "{:?} {:X} {}",
+                        flags,
+                        id,
+                        n
+                    );
+
+                    if let Ok(frame) = res {
+                        assert_eq!(id | flags.bits(), frame.id_word());
+                        assert_eq!(flags, frame.id_flags());
+                        assert_eq!(is_rtr, frame.is_remote_frame());
+                        assert_eq!(is_err, frame.is_error_frame());
+                        assert_eq!(&data[..], &frame.data()[..n]);
+
+                        let parsed: CanFrame = frame.to_string().parse().unwrap();
+                        assert_eq!(frame.id_word(), parsed.id_word());
+                        assert_eq!(frame.data(), parsed.data());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_raw_dlc() {
+        let frame = CanDataFrame::new(STD_ID, DATA).unwrap();
+        assert_eq!(DATA.len() as u8, frame.raw_dlc());
+
+        let frame = CanRemoteFrame::new_remote(STD_ID, 6).unwrap();
+        assert_eq!(6, frame.raw_dlc());
+        assert_eq!(6, CanFrame::from(frame).raw_dlc());
+
+        let frame = CanFdFrame::new(STD_ID, &[0; 64]).unwrap();
+        assert_eq!(64, frame.dlc());
+        assert_eq!(15, frame.raw_dlc());
+
+        let frame = CanAnyFrame::from(CanFdFrame::new(STD_ID, &[0; 10]).unwrap());
+        assert_eq!(12, frame.len());
+        assert_eq!(9, frame.raw_dlc());
+    }
+
+    #[test]
+    fn test_fd_dlc() {
+        assert_eq!(Some(8), fd_len_to_dlc(8));
+        assert_eq!(Some(9), fd_len_to_dlc(9));
+        assert_eq!(Some(9), fd_len_to_dlc(12));
+        assert_eq!(Some(13), fd_len_to_dlc(25));
+        assert_eq!(Some(15), fd_len_to_dlc(64));
+        assert_eq!(None, fd_len_to_dlc(65));
+
+        assert_eq!(5, fd_dlc_to_len(5));
+        assert_eq!(12, fd_dlc_to_len(9));
+        assert_eq!(64, fd_dlc_to_len(15));
+        assert_eq!(12, fd_dlc_to_len(0x19));
+
+        let mut frame = CanFdFrame::new(STD_ID, &[0x11; 10]).unwrap();
+        assert_eq!(12, frame.len());
+        assert_eq!(&[0x11; 10], &frame.data()[..10]);
+        assert_eq!(&[0; 2], &frame.data()[10..]);
+
+        frame.set_data(&[0x22; 33]).unwrap();
+        assert_eq!(48, frame.len());
+        assert_eq!(&[0; 15], &frame.data()[33..]);
+
+        let frame = CanFdFrame::new_padded(STD_ID, &[0x11; 10], 0xCC).unwrap();
+        assert_eq!(&[0xCC; 2], &frame.data()[10..]);
+
+        assert_eq!(
+            Some(ConstructionError::TooMuchData),
+            CanFdFrame::new_padded(STD_ID, &[0; 65], 0xCC).err()
+        );
+    }
+
     #[test]
     fn test_frame_to_fd() {
         let frame = CanDataFrame::new(STD_ID, DATA).unwrap();
@@ -1539,4 +2776,322 @@ mod tests {
         assert!(!frame.is_error_frame());
         assert_eq!(DATA, frame.data());
     }
+
+    #[test]
+    fn test_from_raw_frame_bytes() {
+        use libc::{CANFD_MTU, CAN_MTU};
+
+        let frame = CanFrame::from_raw_id(0x123, &[1, 2, 3]).unwrap();
+        let decoded = CanFrame::from_raw_frame_bytes(frame.as_bytes()).unwrap();
+        assert!(matches!(decoded, CanFrame::Data(_)));
+        assert_eq!(frame.as_bytes(), decoded.as_bytes());
+
+        let frame = CanFrame::remote_from_raw_id(0x123, 2).unwrap();
+        let decoded = CanFrame::from_raw_frame_bytes(frame.as_bytes()).unwrap();
+        assert!(matches!(decoded, CanFrame::Remote(_)));
+
+        let frame = CanFrame::Error(CanErrorFrame::new_error(0x04, &[]).unwrap());
+        let decoded = CanAnyFrame::from_raw_frame_bytes(frame.as_bytes()).unwrap();
+        assert!(matches!(decoded, CanAnyFrame::Error(_)));
+
+        let frame = CanFdFrame::from_raw_id(0x123, &[0xAA; 12]).unwrap();
+        let decoded = CanAnyFrame::from_raw_frame_bytes(frame.as_bytes()).unwrap();
+        match decoded {
+            CanAnyFrame::Fd(decoded) => assert_eq!(frame.data(), decoded.data()),
+            _ => panic!("Expected an FD frame"),
+        }
+
+        // Strict lengths
+        assert!(matches!(
+            CanFrame::from_raw_frame_bytes(frame.as_bytes()),
+            Err(ConstructionError::WrongFrameType)
+        ));
+        assert!(CanAnyFrame::from_raw_frame_bytes(&[0; CAN_MTU - 1]).is_err());
+        assert!(CanAnyFrame::from_raw_frame_bytes(&[0; CANFD_MTU + 1]).is_err());
+        assert!(CanAnyFrame::from_raw_frame_bytes(&[]).is_err());
+
+        // Bad payload length
+        let mut buf = [0u8; CAN_MTU];
+        buf[4] = 9;
+        assert!(matches!(
+            CanFrame::from_raw_frame_bytes(&buf),
+            Err(ConstructionError::TooMuchData)
+        ));
+    }
+
+    #[test]
+    fn test_any_frame_size() {
+        use libc::{CANFD_MTU, CAN_MTU};
+
+        // Classic frames are written as a 16-byte can_frame, even through
+        // an FD socket, and only FD frames use the 72-byte canfd_frame.
+        let frame = CanAnyFrame::from(CanDataFrame::new(STD_ID, DATA).unwrap());
+        assert_eq!(CAN_MTU, frame.size());
+        assert_eq!(CAN_MTU, frame.as_bytes().len());
+
+        let frame = CanAnyFrame::from(CanRemoteFrame::new_remote(STD_ID, DATA_LEN).unwrap());
+        assert_eq!(CAN_MTU, frame.as_bytes().len());
+
+        let frame = CanAnyFrame::from(CanErrorFrame::new_error(0, &[]).unwrap());
+        assert_eq!(CAN_MTU, frame.as_bytes().len());
+
+        let frame = CanAnyFrame::from(CanFdFrame::new(STD_ID, DATA).unwrap());
+        assert_eq!(CANFD_MTU, frame.size());
+        assert_eq!(CANFD_MTU, frame.as_bytes().len());
+    }
+
+    #[test]
+    fn test_any_frame_accessors() {
+        let frame = CanAnyFrame::new(STD_ID, DATA).unwrap();
+        assert!(matches!(frame, CanAnyFrame::Normal(_)));
+        assert_eq!(STD_ID, frame.id());
+        assert_eq!(DATA, frame.data());
+        assert!(!frame.is_extended());
+
+        let data = [0xA5u8; 12];
+        let mut frame = CanAnyFrame::new(EXT_ID, &data).unwrap();
+        assert!(matches!(frame, CanAnyFrame::Fd(_)));
+        assert_eq!(EXT_ID, frame.id());
+        assert_eq!(&data, frame.data());
+        assert!(frame.is_extended());
+        assert!(!frame.is_remote_frame());
+
+        frame.set_id(STD_ID);
+        assert_eq!(STD_ID, frame.hal_id());
+
+        let frame = CanAnyFrame::new_remote(STD_ID, 2).unwrap();
+        assert!(frame.is_remote_frame());
+        assert_eq!(2, frame.dlc());
+
+        let frame = CanAnyFrame::from(CanErrorFrame::new_error(0, &[]).unwrap());
+        assert!(frame.is_error_frame());
+    }
+
+    #[test]
+    fn test_frame_str() {
+        for s in [
+            "123#DEADBEEF",
+            "18FEF100#0011",
+            "7FF#",
+            "123#R",
+            "00000123#R3",
+            "213##311223344",
+            "20000040#0000000000000000",
+        ] {
+            let frame: CanAnyFrame = s.parse().unwrap();
+            assert_eq!(s, frame.to_string());
+        }
+
+        let frame: CanFrame = "18FEF100#00.11".parse().unwrap();
+        assert!(frame.is_extended());
+        assert_eq!(0x18FEF100, frame.raw_id());
+        assert_eq!(&[0x00, 0x11], frame.data());
+        assert_eq!("18FEF100#0011", frame.to_string());
+
+        let frame: CanFrame = "123#R2".parse().unwrap();
+        assert!(frame.is_remote_frame());
+        assert_eq!(2, frame.dlc());
+
+        let frame: CanFdFrame = "123##1AABB".parse().unwrap();
+        assert!(frame.is_brs());
+        assert_eq!(&[0xAA, 0xBB], frame.data());
+        assert_eq!("123##1AABB", frame.to_string());
+
+        use ConstructionError::*;
+        assert_eq!(Some(IDTooLarge), "800#11".parse::<CanFrame>().err());
+        assert_eq!(Some(IDTooLarge), "40000000#11".parse::<CanFrame>().err());
+        assert_eq!(
+            Some(TooMuchData),
+            "123#112233445566778899".parse::<CanFrame>().err()
+        );
+        assert_eq!(Some(TooMuchData), "123#R9".parse::<CanFrame>().err());
+        assert_eq!(Some(InvalidFormat), "12#11".parse::<CanFrame>().err());
+        assert_eq!(Some(InvalidFormat), "123#112".parse::<CanFrame>().err());
+        assert_eq!(Some(InvalidFormat), "123".parse::<CanFrame>().err());
+        assert_eq!(Some(WrongFrameType), "123##0".parse::<CanFrame>().err());
+        assert_eq!(Some(WrongFrameType), "123#11".parse::<CanFdFrame>().err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_repr() {
+        use super::serde_impls::{CanFdFrameRepr, CanFrameRepr};
+
+        let frames: Vec<CanFrame> = vec![
+            "123#DEADBEEF".parse().unwrap(),
+            "00000123#01".parse().unwrap(),
+            "1ABCDEF0#R3".parse().unwrap(),
+            "20000040#0000000000000000".parse().unwrap(),
+        ];
+        for frame in frames {
+            let repr = CanFrameRepr::from(&frame);
+            let rt = CanFrame::try_from(repr.clone()).unwrap();
+            assert_eq!(frame.to_string(), rt.to_string());
+            assert_eq!(repr, CanFrameRepr::from(&rt));
+        }
+
+        // The type of ID survives, even for a small extended ID
+        let frame: CanFrame = "00000123#01".parse().unwrap();
+        match CanFrameRepr::from(&frame) {
+            CanFrameRepr::Data { id, extended, .. } => {
+                assert_eq!(0x123, id);
+                assert!(extended);
+            }
+            _ => panic!("Wrong frame type"),
+        }
+
+        let frame: CanFdFrame = "12345678##3AABB".parse().unwrap();
+        let rt = CanFdFrame::try_from(CanFdFrameRepr::from(&frame)).unwrap();
+        assert_eq!(frame.to_string(), rt.to_string());
+
+        let repr = CanFrameRepr::Data {
+            id: 0x800,
+            extended: false,
+            data: vec![],
+        };
+        assert!(matches!(
+            CanFrame::try_from(repr),
+            Err(ConstructionError::IDTooLarge)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_json() {
+        let frames: Vec<CanFrame> = vec![
+            "123#DEADBEEF".parse().unwrap(),
+            "00000123#01".parse().unwrap(),
+            "1ABCDEF0#R3".parse().unwrap(),
+            "20000040#0000000000000000".parse().unwrap(),
+        ];
+        for frame in frames {
+            let json = serde_json::to_string(&frame).unwrap();
+            let rt: CanFrame = serde_json::from_str(&json).unwrap();
+            assert_eq!(frame.to_string(), rt.to_string());
+            assert_eq!(frame.raw_dlc(), rt.raw_dlc());
+        }
+
+        let json = serde_json::to_string(&CanFrame::from_raw_id(0x123, &[1, 2]).unwrap()).unwrap();
+        assert_eq!(
+            r#"{"type":"data","id":291,"extended":false,"data":[1,2]}"#,
+            json
+        );
+
+        let frame: CanFdFrame = "12345678##3AABB".parse().unwrap();
+        let json = serde_json::to_string(&frame).unwrap();
+        let rt: CanFdFrame = serde_json::from_str(&json).unwrap();
+        assert_eq!(frame.to_string(), rt.to_string());
+        assert!(rt.is_brs() && rt.is_esi());
+
+        // Invalid frames are rejected when deserializing
+        let json = r#"{"type":"data","id":2048,"extended":false,"data":[]}"#;
+        assert!(serde_json::from_str::<CanFrame>(json).is_err());
+        let json = r#"{"type":"data","id":1,"extended":false,"data":[0,0,0,0,0,0,0,0,0]}"#;
+        assert!(serde_json::from_str::<CanFrame>(json).is_err());
+    }
+
+    #[test]
+    fn test_try_new_remote() {
+        for dlc in 0..=8u8 {
+            let frame = CanFrame::try_new_remote(STD_ID, dlc).unwrap();
+            assert!(frame.is_remote_frame());
+            assert_eq!(dlc as usize, frame.dlc());
+            assert!(frame.id_flags().contains(IdFlags::RTR));
+            assert!(frame.data().iter().all(|&b| b == 0));
+        }
+
+        let frame = CanFrame::try_new_remote(EXT_ID, 3).unwrap();
+        assert!(frame.is_extended());
+        assert_eq!(EXT_ID, frame.id());
+
+        assert!(matches!(
+            CanFrame::try_new_remote(STD_ID, 9),
+            Err(ConstructionError::TooMuchData)
+        ));
+    }
+
+    #[test]
+    fn test_payload() {
+        let payload = Payload([1, 2, 3, 4]);
+        assert_eq!(4, payload.len());
+        assert_eq!(DATA_LEN, payload.len());
+
+        let frame = CanFrame::from_payload(STD_ID, payload);
+        assert!(matches!(frame, CanFrame::Data(_)));
+        assert_eq!(STD_ID, frame.id());
+        assert_eq!(&[1, 2, 3, 4], frame.data());
+
+        let frame = CanFdFrame::from_payload(EXT_ID, Payload([0xA5; 64]));
+        assert_eq!(EXT_ID, frame.id());
+        assert_eq!(64, frame.len());
+
+        let payload = Payload::<4>::try_from(DATA).unwrap();
+        assert_eq!(DATA, payload.as_slice());
+        assert_eq!([0, 1, 2, 3], <[u8; 4]>::from(payload));
+        assert!(Payload::<3>::try_from(DATA).is_err());
+        assert!(Payload::<0>::default().is_empty());
+    }
+
+    #[test]
+    fn test_format_can_id() {
+        let std_id = |n| Id::Standard(StandardId::new(n).unwrap());
+        let ext_id = |n| Id::Extended(ExtendedId::new(n).unwrap());
+
+        assert_eq!("0", format_can_id(std_id(0), IdStyle::Minimal));
+        assert_eq!("000", format_can_id(std_id(0), IdStyle::Padded));
+        assert_eq!("7FF", format_can_id(STD_ID, IdStyle::Minimal));
+        assert_eq!("7FF", format_can_id(STD_ID, IdStyle::Padded));
+        assert_eq!("0x7FF", format_can_id(STD_ID, IdStyle::WithPrefix));
+
+        assert_eq!("0", format_can_id(ext_id(0), IdStyle::Minimal));
+        assert_eq!("00000000", format_can_id(ext_id(0), IdStyle::Padded));
+        assert_eq!("7FF", format_can_id(EXT_LOW_ID, IdStyle::Minimal));
+        assert_eq!("000007FF", format_can_id(EXT_LOW_ID, IdStyle::Padded));
+        assert_eq!("1FFFFFFF", format_can_id(EXT_ID, IdStyle::Padded));
+        assert_eq!("0x1FFFFFFF", format_can_id(EXT_ID, IdStyle::WithPrefix));
+    }
+
+    #[test]
+    fn test_u32_frames() {
+        use ByteOrder::*;
+        use FrameWidth::*;
+
+        let frame = CanFrame::from_u32(STD_ID, 0x1234, BigEndian, Minimal).unwrap();
+        assert_eq!(&[0x12, 0x34], frame.data());
+        assert_eq!(Some(0x1234), frame.as_u32(BigEndian));
+        assert_eq!(Some(0x3412), frame.as_u32(LittleEndian));
+
+        let frame = CanFrame::from_u32(STD_ID, 0x1234, LittleEndian, Minimal).unwrap();
+        assert_eq!(&[0x34, 0x12], frame.data());
+        assert_eq!(Some(0x1234), frame.as_u32(LittleEndian));
+
+        let frame = CanFrame::from_u32(STD_ID, 0, BigEndian, Minimal).unwrap();
+        assert_eq!(&[0], frame.data());
+
+        // Zero-padded
+        let frame = CanFrame::from_u32(EXT_ID, 0xABCD, BigEndian, Fixed(6)).unwrap();
+        assert_eq!(&[0, 0, 0, 0, 0xAB, 0xCD], frame.data());
+        assert_eq!(Some(0xABCD), frame.as_u32(BigEndian));
+
+        let frame = CanFrame::from_u32(EXT_ID, 0xABCD, LittleEndian, Fixed(6)).unwrap();
+        assert_eq!(&[0xCD, 0xAB, 0, 0, 0, 0], frame.data());
+        assert_eq!(Some(0xABCD), frame.as_u32(LittleEndian));
+
+        let frame = CanFrame::from_u32(STD_ID, u32::MAX, BigEndian, Fixed(4)).unwrap();
+        assert_eq!(Some(u32::MAX), frame.as_u32(BigEndian));
+
+        // Bad widths
+        assert!(CanFrame::from_u32(STD_ID, 0x10000, BigEndian, Fixed(2)).is_none());
+        assert!(CanFrame::from_u32(STD_ID, 1, BigEndian, Fixed(0)).is_none());
+        assert!(CanFrame::from_u32(STD_ID, 1, BigEndian, Fixed(9)).is_none());
+
+        // Frames that can't be read
+        let frame = CanFrame::new(STD_ID, &[]).unwrap();
+        assert_eq!(None, frame.as_u32(BigEndian));
+        let frame = CanFrame::new(STD_ID, &[1, 0, 0, 0, 0]).unwrap();
+        assert_eq!(None, frame.as_u32(BigEndian));
+        assert_eq!(Some(1), frame.as_u32(LittleEndian));
+        let frame = CanFrame::new_remote(STD_ID, 4).unwrap();
+        assert_eq!(None, frame.as_u32(BigEndian));
+    }
 }
@@ -0,0 +1,371 @@
+// socketcan/src/isotp.rs
+//
+// ISO-TP (ISO 15765-2) transport protocol sockets.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! ISO-TP (ISO 15765-2) transport protocol sockets.
+//!
+//! ISO-TP is the transport layer used by vehicle diagnostics, like UDS and
+//! OBD-II. It carries messages larger than a single CAN frame by splitting
+//! them into a first frame and a series of consecutive frames, paced by
+//! flow control frames from the receiver.
+//!
+//! The protocol is implemented by the kernel's `can-isotp` module, which
+//! must be loaded (and is part of mainline Linux since 5.10). A socket is
+//! bound to an interface with a pair of CAN IDs, one for transmitting and
+//! one for receiving, and then each read or write transfers a whole
+//! message. The kernel takes care of the segmentation, reassembly, and
+//! flow control.
+//!
+//! ```no_run
+//! use socketcan::{isotp::IsoTpSocketBuilder, StandardId};
+//! use std::time::Duration;
+//!
+//! let tx_id = StandardId::new(0x7E0).unwrap();
+//! let rx_id = StandardId::new(0x7E8).unwrap();
+//!
+//! let sock = IsoTpSocketBuilder::new()
+//!     .block_size(8)
+//!     .st_min(Duration::from_millis(2))
+//!     .padding(0xAA)
+//!     .open("vcan0", tx_id, rx_id)
+//!     .unwrap();
+//!
+//! // Read all the DTCs (UDS service 0x19, sub-function 0x02)
+//! sock.write(&[0x19, 0x02, 0xFF]).unwrap();
+//! let resp = sock.read().unwrap();
+//! ```
+
+use crate::{frame::id_to_canid_t, CanAddr, Id, IoError, IoErrorKind, IoResult};
+use libc::{c_int, socklen_t, AF_CAN, SOL_CAN_BASE};
+use socket2::SockAddr;
+use std::{
+    io::{Read, Write},
+    mem,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, RawFd},
+    time::Duration,
+};
+
+/// The protocol number for an ISO-TP socket.
+pub const CAN_ISOTP: c_int = 6;
+
+/// The socket option level for an ISO-TP socket.
+pub const SOL_CAN_ISOTP: c_int = SOL_CAN_BASE + CAN_ISOTP;
+
+/// Socket option for the general ISO-TP options
+pub const CAN_ISOTP_OPTS: c_int = 1;
+/// Socket option for the flow control options sent to the peer
+pub const CAN_ISOTP_RECV_FC: c_int = 2;
+
+/// Pad transmitted frames to the full 8 bytes
+pub const CAN_ISOTP_TX_PADDING: u32 = 0x0004;
+/// Expect received frames to be padded
+pub const CAN_ISOTP_RX_PADDING: u32 = 0x0008;
+
+/// The largest message that the kernel accepts by default.
+///
+/// This is the default `max_pdu_size` of the `can-isotp` module, and is
+/// the size of the buffer used to read a message.
+pub const MAX_PDU_SIZE: usize = 8300;
+
+/// The default padding byte used by the kernel.
+const DEFAULT_PAD: u8 = 0xCC;
+
+/// The `struct can_isotp_options` from linux/can/isotp.h
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct IsoTpOptions {
+    flags: u32,
+    frame_txtime: u32,
+    ext_address: u8,
+    txpad_content: u8,
+    rxpad_content: u8,
+    rx_ext_address: u8,
+}
+
+/// The `struct can_isotp_fc_options` from linux/can/isotp.h
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct IsoTpFcOptions {
+    bs: u8,
+    stmin: u8,
+    wftmax: u8,
+}
+
+/// Converts a minimum separation time into its ISO-TP encoding.
+///
+/// Times of a millisecond or more are sent in whole milliseconds, up to a
+/// maximum of 127ms. Shorter times are sent in steps of 100us, rounded up.
+fn st_min_to_raw(st_min: Duration) -> u8 {
+    let us = st_min.as_micros();
+    match us {
+        0 => 0,
+        1..=900 => 0xF0 + ((us + 99) / 100) as u8,
+        901..=1999 => 1,
+        _ => (us / 1000).min(0x7F) as u8,
+    }
+}
+
+/// Sets an ISO-TP option on the socket.
+fn set_isotp_option<T>(sock: &socket2::Socket, name: c_int, val: &T) -> IoResult<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            sock.as_raw_fd(),
+            SOL_CAN_ISOTP,
+            name,
+            (val as *const T).cast(),
+            mem::size_of::<T>() as socklen_t,
+        )
+    };
+
+    match ret {
+        0 => Ok(()),
+        _ => Err(IoError::last_os_error()),
+    }
+}
+
+// ===== IsoTpSocketBuilder =====
+
+/// A builder to configure and open an ISO-TP socket.
+///
+/// The options must be set before the socket is bound to the interface,
+/// so they are collected here, then applied by [`open`](Self::open).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IsoTpSocketBuilder {
+    block_size: u8,
+    st_min: Duration,
+    tx_padding: Option<u8>,
+    rx_padding: Option<u8>,
+    nonblocking: bool,
+}
+
+impl IsoTpSocketBuilder {
+    /// Creates a new builder with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the block size (BS) sent to the peer in flow control frames.
+    ///
+    /// This is the number of consecutive frames the peer can send before
+    /// waiting for the next flow control frame. Zero, the default, lets the
+    /// peer send all of them without waiting.
+    pub fn block_size(mut self, block_size: u8) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Sets the minimum separation time (STmin) sent to the peer in flow
+    /// control frames.
+    ///
+    /// This is the shortest time that the peer should leave between the
+    /// consecutive frames it sends. It is rounded to the resolution that
+    /// ISO-TP can encode, and capped at 127ms.
+    pub fn st_min(mut self, st_min: Duration) -> Self {
+        self.st_min = st_min;
+        self
+    }
+
+    /// Sets the byte used to pad transmitted frames to the full 8 bytes.
+    ///
+    /// By default, frames are not padded, and the last frame of a message
+    /// is only as long as it needs to be. Many ECUs require padding.
+    pub fn padding(mut self, pad: u8) -> Self {
+        self.tx_padding = Some(pad);
+        self
+    }
+
+    /// Sets the byte expected to pad received frames.
+    pub fn rx_padding(mut self, pad: u8) -> Self {
+        self.rx_padding = Some(pad);
+        self
+    }
+
+    /// Opens the socket in nonblocking mode.
+    pub fn nonblocking(mut self, nonblocking: bool) -> Self {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    /// Gets the general options for the socket.
+    fn options(&self) -> IsoTpOptions {
+        let mut opts = IsoTpOptions {
+            txpad_content: self.tx_padding.unwrap_or(DEFAULT_PAD),
+            rxpad_content: self.rx_padding.unwrap_or(DEFAULT_PAD),
+            ..IsoTpOptions::default()
+        };
+        if self.tx_padding.is_some() {
+            opts.flags |= CAN_ISOTP_TX_PADDING;
+        }
+        if self.rx_padding.is_some() {
+            opts.flags |= CAN_ISOTP_RX_PADDING;
+        }
+        opts
+    }
+
+    /// Gets the flow control options for the socket.
+    fn fc_options(&self) -> IsoTpFcOptions {
+        IsoTpFcOptions {
+            bs: self.block_size,
+            stmin: st_min_to_raw(self.st_min),
+            wftmax: 0,
+        }
+    }
+
+    /// Opens the socket on the named CAN interface, with the IDs to
+    /// transmit and receive on.
+    pub fn open(
+        &self,
+        ifname: &str,
+        tx_id: impl Into<Id>,
+        rx_id: impl Into<Id>,
+    ) -> IoResult<CanIsoTpSocket> {
+        let addr = CanAddr::from_iface(ifname)?;
+        let ifindex = addr.as_ref().can_ifindex as u32;
+        self.open_iface(ifindex, tx_id, rx_id)
+    }
+
+    /// Opens the socket by kernel interface number, with the IDs to
+    /// transmit and receive on.
+    pub fn open_iface(
+        &self,
+        ifindex: u32,
+        tx_id: impl Into<Id>,
+        rx_id: impl Into<Id>,
+    ) -> IoResult<CanIsoTpSocket> {
+        let af_can = socket2::Domain::from(AF_CAN);
+        let can_isotp = socket2::Protocol::from(CAN_ISOTP);
+        let sock = socket2::Socket::new(af_can, socket2::Type::DGRAM, Some(can_isotp))?;
+
+        set_isotp_option(&sock, CAN_ISOTP_OPTS, &self.options())?;
+        set_isotp_option(&sock, CAN_ISOTP_RECV_FC, &self.fc_options())?;
+        if self.nonblocking {
+            sock.set_nonblocking(true)?;
+        }
+
+        let addr = CanAddr::new_tp(ifindex, id_to_canid_t(rx_id), id_to_canid_t(tx_id));
+        sock.bind(&SockAddr::from(addr))?;
+        Ok(CanIsoTpSocket(sock))
+    }
+}
+
+// ===== CanIsoTpSocket =====
+
+/// An ISO-TP socket, bound to a pair of CAN IDs.
+///
+/// Each read or write transfers one complete message. See the
+/// [module documentation](crate::isotp) for details.
+#[derive(Debug)]
+pub struct CanIsoTpSocket(socket2::Socket);
+
+impl CanIsoTpSocket {
+    /// Opens the socket on the named CAN interface, with the IDs to
+    /// transmit and receive on, and the default options.
+    ///
+    /// Use an [`IsoTpSocketBuilder`] to set the flow control or padding
+    /// options.
+    pub fn bind(ifname: &str, tx_id: impl Into<Id>, rx_id: impl Into<Id>) -> IoResult<Self> {
+        IsoTpSocketBuilder::new().open(ifname, tx_id, rx_id)
+    }
+
+    /// Change socket to non-blocking mode or back to blocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> IoResult<()> {
+        self.0.set_nonblocking(nonblocking)
+    }
+
+    /// Sets the read timeout on the socket.
+    ///
+    /// If the duration is set to `None` then read calls will block
+    /// indefinitely.
+    pub fn set_read_timeout<D>(&self, duration: D) -> IoResult<()>
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.0.set_read_timeout(duration.into())
+    }
+
+    /// Reads the next complete message from the socket.
+    pub fn read(&self) -> IoResult<Vec<u8>> {
+        let mut buf = vec![0u8; MAX_PDU_SIZE];
+        let n = (&self.0).read(&mut buf)?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+
+    /// Writes a complete message to the socket.
+    ///
+    /// The kernel segments the message into frames, and blocks until it
+    /// has been sent, or until the peer fails to respond with flow control
+    /// in time.
+    pub fn write(&self, data: &[u8]) -> IoResult<()> {
+        match (&self.0).write(data)? {
+            n if n == data.len() => Ok(()),
+            _ => Err(IoErrorKind::WriteZero.into()),
+        }
+    }
+}
+
+impl AsRawFd for CanIsoTpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for CanIsoTpSocket {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+
+impl AsFd for CanIsoTpSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_st_min() {
+        assert_eq!(0x00, st_min_to_raw(Duration::ZERO));
+        assert_eq!(0xF1, st_min_to_raw(Duration::from_micros(100)));
+        assert_eq!(0xF2, st_min_to_raw(Duration::from_micros(101)));
+        assert_eq!(0xF9, st_min_to_raw(Duration::from_micros(900)));
+        assert_eq!(0x01, st_min_to_raw(Duration::from_micros(901)));
+        assert_eq!(0x01, st_min_to_raw(Duration::from_millis(1)));
+        assert_eq!(0x7F, st_min_to_raw(Duration::from_millis(127)));
+        assert_eq!(0x7F, st_min_to_raw(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(12, mem::size_of::<IsoTpOptions>());
+        assert_eq!(3, mem::size_of::<IsoTpFcOptions>());
+
+        let opts = IsoTpSocketBuilder::new().options();
+        assert_eq!(0, opts.flags);
+        assert_eq!(DEFAULT_PAD, opts.txpad_content);
+
+        let builder = IsoTpSocketBuilder::new()
+            .block_size(4)
+            .st_min(Duration::from_millis(5))
+            .padding(0xAA);
+        let opts = builder.options();
+        assert_eq!(CAN_ISOTP_TX_PADDING, opts.flags);
+        assert_eq!(0xAA, opts.txpad_content);
+
+        let fc = builder.fc_options();
+        assert_eq!(4, fc.bs);
+        assert_eq!(5, fc.stmin);
+    }
+}
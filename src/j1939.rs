@@ -0,0 +1,473 @@
+// socketcan/src/j1939.rs
+//
+// SAE J1939 sockets and identifiers.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! SAE J1939 sockets and identifiers.
+//!
+//! J1939 is the higher-layer protocol used on the CAN buses of trucks,
+//! buses, and agricultural and construction equipment. It uses 29-bit
+//! extended IDs, which are split into a priority, a parameter group number
+//! (PGN) that identifies the content of the message, and the addresses of
+//! the sender and, for some PGNs, the receiver. A [`J1939Id`] can be used to
+//! take apart, or build up, these IDs, with any type of socket.
+//!
+//! The protocol itself is implemented by the kernel's `can-j1939` module,
+//! which must be loaded (and is part of mainline Linux since 5.4). A
+//! [`J1939Socket`] is bound to an ECU NAME, a PGN, and an address. Each
+//! send or receive transfers a whole message. The kernel takes care of the
+//! transport protocols (TP and ETP), so messages longer than eight bytes
+//! are segmented and reassembled transparently, and of tracking address
+//! claims on the bus.
+//!
+//! ```no_run
+//! use socketcan::j1939::{J1939Socket, J1939_NO_NAME, J1939_NO_PGN};
+//!
+//! // Bind to address 0x80, receiving all PGNs
+//! let sock = J1939Socket::open("vcan0", J1939_NO_NAME, J1939_NO_PGN, 0x80).unwrap();
+//!
+//! // Send a 12-byte message to address 0x30, using the transport protocol
+//! sock.send_to(0x12300, 0x30, &[0x55; 12]).unwrap();
+//!
+//! let (src, pgn, data) = sock.recv_from().unwrap();
+//! println!("{:02X} {:05X} {:?}", src.addr, pgn, data);
+//! ```
+
+use crate::{CanAddr, ExtendedId, Id, IoError, IoErrorKind, IoResult};
+use libc::{c_int, sockaddr_can, socklen_t, AF_CAN, SOL_CAN_BASE};
+use socket2::SockAddr;
+use std::{
+    mem,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, RawFd},
+    ptr,
+    time::Duration,
+};
+
+/// The protocol number for a J1939 socket.
+pub const CAN_J1939: c_int = 7;
+
+/// The socket option level for a J1939 socket.
+pub const SOL_CAN_J1939: c_int = SOL_CAN_BASE + CAN_J1939;
+
+// Socket options, from linux/can/j1939.h
+const SO_J1939_PROMISC: c_int = 2;
+const SO_J1939_SEND_PRIO: c_int = 3;
+
+/// The largest unicast address.
+pub const J1939_MAX_UNICAST_ADDR: u8 = 0xFD;
+/// The address used by an ECU that hasn't claimed one yet.
+pub const J1939_IDLE_ADDR: u8 = 0xFE;
+/// No address, which is the broadcast address for a destination.
+pub const J1939_NO_ADDR: u8 = 0xFF;
+/// No NAME, for binding to a fixed address without address claiming.
+pub const J1939_NO_NAME: u64 = 0;
+/// No PGN, for binding a socket to receive all PGNs.
+pub const J1939_NO_PGN: u32 = 0x40000;
+/// The largest PGN.
+pub const J1939_PGN_MAX: u32 = 0x3FFFF;
+/// The PGN of a request message.
+pub const J1939_PGN_REQUEST: u32 = 0x0EA00;
+/// The PGN of an address claimed message.
+pub const J1939_PGN_ADDRESS_CLAIMED: u32 = 0x0EE00;
+
+/// The largest message that can be sent with the transport protocol (TP).
+///
+/// Larger messages, up to about 117MB, are sent by the kernel with the
+/// extended transport protocol (ETP).
+pub const J1939_MAX_TP_SIZE: usize = 1785;
+
+// ===== J1939Id =====
+
+/// A J1939 CAN identifier.
+///
+/// This is a 29-bit extended ID, made up of, from the most significant
+/// bits:
+///
+/// - A 3-bit priority, where 0 is the highest.
+/// - An 18-bit parameter group number (PGN), which includes a PDU format
+///   (PF) byte and a PDU specific (PS) byte.
+/// - The 8-bit source address.
+///
+/// When the PDU format is less than 240 (PDU1), the PS byte is the
+/// destination address of the message, rather than part of the PGN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct J1939Id(u32);
+
+impl J1939Id {
+    /// Creates an ID from its parts.
+    ///
+    /// For a PDU1 PGN, the low byte of the PGN is taken as the destination
+    /// address, which can also be set with [`with_dest`](Self::with_dest).
+    /// This returns `None` if the priority is larger than 7, or the PGN
+    /// is larger than 18 bits.
+    pub fn new(priority: u8, pgn: u32, source: u8) -> Option<Self> {
+        if priority > 7 || pgn > J1939_PGN_MAX {
+            return None;
+        }
+        Some(Self(
+            (u32::from(priority) << 26) | (pgn << 8) | u32::from(source),
+        ))
+    }
+
+    /// Creates an ID from a raw, 29-bit, CAN ID.
+    ///
+    /// This returns `None` if the value is larger than 29 bits.
+    pub fn from_raw(id: u32) -> Option<Self> {
+        ExtendedId::new(id).map(Self::from)
+    }
+
+    /// Gets the raw, 29-bit, CAN ID.
+    pub fn as_raw(&self) -> u32 {
+        self.0
+    }
+
+    /// Gets the priority, from 0 (highest) to 7 (lowest).
+    pub fn priority(&self) -> u8 {
+        ((self.0 >> 26) & 0x07) as u8
+    }
+
+    /// Gets the parameter group number (PGN).
+    ///
+    /// For a PDU1 message, the destination address is not part of the PGN,
+    /// so the low byte is zero.
+    pub fn pgn(&self) -> u32 {
+        let pgn = (self.0 >> 8) & J1939_PGN_MAX;
+        if self.is_pdu1() {
+            pgn & !0xFF
+        } else {
+            pgn
+        }
+    }
+
+    /// Gets the PDU format (PF) byte.
+    pub fn pdu_format(&self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    /// Gets the PDU specific (PS) byte.
+    ///
+    /// This is the destination address for a PDU1 message, and the group
+    /// extension of the PGN for a PDU2 message.
+    pub fn pdu_specific(&self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    /// Determines if this is a PDU1 message, which is sent to a specific
+    /// destination address.
+    pub fn is_pdu1(&self) -> bool {
+        self.pdu_format() < 240
+    }
+
+    /// Gets the destination address, if this is a PDU1 message.
+    pub fn dest_addr(&self) -> Option<u8> {
+        self.is_pdu1().then_some(self.pdu_specific())
+    }
+
+    /// Gets the source address.
+    pub fn source_addr(&self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Sets the destination address of a PDU1 message.
+    ///
+    /// A PDU2 message has no destination, so it is returned unchanged.
+    pub fn with_dest(self, dest: u8) -> Self {
+        if self.is_pdu1() {
+            Self((self.0 & !0xFF00) | (u32::from(dest) << 8))
+        } else {
+            self
+        }
+    }
+}
+
+impl From<ExtendedId> for J1939Id {
+    fn from(id: ExtendedId) -> Self {
+        Self(id.as_raw())
+    }
+}
+
+impl From<J1939Id> for ExtendedId {
+    fn from(id: J1939Id) -> Self {
+        // The value is always in range, by construction.
+        ExtendedId::new(id.0).unwrap()
+    }
+}
+
+impl From<J1939Id> for Id {
+    fn from(id: J1939Id) -> Self {
+        Id::Extended(id.into())
+    }
+}
+
+// ===== J1939Addr =====
+
+/// The address of a node on a J1939 bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct J1939Addr {
+    /// The 64-bit NAME of the ECU, or [`J1939_NO_NAME`] if unknown
+    pub name: u64,
+    /// The 8-bit address
+    pub addr: u8,
+}
+
+// ===== J1939Socket =====
+
+/// A J1939 socket.
+///
+/// Each send or receive transfers one complete message. See the
+/// [module documentation](crate::j1939) for details.
+#[derive(Debug)]
+pub struct J1939Socket(socket2::Socket);
+
+impl J1939Socket {
+    /// Opens a socket on the named CAN interface, bound to a NAME, PGN, and
+    /// address.
+    ///
+    /// The PGN filters the messages received, or can be [`J1939_NO_PGN`]
+    /// to receive all of them. Binding with a NAME lets the kernel use the
+    /// address that the ECU has claimed on the bus, while binding with
+    /// [`J1939_NO_NAME`] uses a fixed address.
+    pub fn open(ifname: &str, name: u64, pgn: u32, addr: u8) -> IoResult<Self> {
+        let caddr = CanAddr::from_iface(ifname)?;
+        let ifindex = caddr.as_ref().can_ifindex as u32;
+        Self::open_iface(ifindex, name, pgn, addr)
+    }
+
+    /// Opens a socket by kernel interface number, bound to a NAME, PGN,
+    /// and address.
+    pub fn open_iface(ifindex: u32, name: u64, pgn: u32, addr: u8) -> IoResult<Self> {
+        let af_can = socket2::Domain::from(AF_CAN);
+        let can_j1939 = socket2::Protocol::from(CAN_J1939);
+        let sock = socket2::Socket::new(af_can, socket2::Type::DGRAM, Some(can_j1939))?;
+
+        let caddr = CanAddr::new_j1939(ifindex, name, pgn, addr);
+        sock.bind(&SockAddr::from(caddr))?;
+        Ok(Self(sock))
+    }
+
+    /// Change socket to non-blocking mode or back to blocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> IoResult<()> {
+        self.0.set_nonblocking(nonblocking)
+    }
+
+    /// Sets the read timeout on the socket.
+    ///
+    /// If the duration is set to `None` then read calls will block
+    /// indefinitely.
+    pub fn set_read_timeout<D>(&self, duration: D) -> IoResult<()>
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.0.set_read_timeout(duration.into())
+    }
+
+    /// Enables or disables sending broadcast messages.
+    ///
+    /// This must be enabled to send to [`J1939_NO_ADDR`], including to
+    /// claim an address.
+    pub fn set_broadcast(&self, on: bool) -> IoResult<()> {
+        self.0.set_broadcast(on)
+    }
+
+    /// Enables or disables promiscuous mode.
+    ///
+    /// In promiscuous mode, the socket receives all messages on the bus,
+    /// not just those sent to its address or broadcast.
+    pub fn set_promiscuous(&self, on: bool) -> IoResult<()> {
+        self.set_option(SO_J1939_PROMISC, &c_int::from(on))
+    }
+
+    /// Sets the priority of the messages sent, from 0 (highest) to 7
+    /// (lowest).
+    ///
+    /// The default is 6. Raising the priority above 2 requires the
+    /// `CAP_NET_ADMIN` capability.
+    pub fn set_send_priority(&self, priority: u8) -> IoResult<()> {
+        if priority > 7 {
+            return Err(IoErrorKind::InvalidInput.into());
+        }
+        self.set_option(SO_J1939_SEND_PRIO, &c_int::from(priority))
+    }
+
+    /// Sends a message with the specified PGN to an address.
+    ///
+    /// The destination can be [`J1939_NO_ADDR`] to broadcast the message,
+    /// which requires [`set_broadcast`](Self::set_broadcast). Messages
+    /// longer than eight bytes are sent by the kernel with the transport
+    /// protocol, which blocks until the transfer completes.
+    pub fn send_to(&self, pgn: u32, dst: u8, data: &[u8]) -> IoResult<()> {
+        let caddr = CanAddr::new_j1939(0, J1939_NO_NAME, pgn, dst);
+        match self.0.send_to(data, &SockAddr::from(caddr))? {
+            n if n == data.len() => Ok(()),
+            _ => Err(IoErrorKind::WriteZero.into()),
+        }
+    }
+
+    /// Broadcasts an address claim for the NAME and address to which the
+    /// socket is bound.
+    ///
+    /// The kernel tracks the claims on the bus, so that a socket bound to
+    /// a NAME sends from the address claimed for it. This returns an
+    /// `InvalidInput` error if the socket wasn't bound with a NAME.
+    /// Broadcasting must be enabled with
+    /// [`set_broadcast`](Self::set_broadcast).
+    pub fn claim_address(&self) -> IoResult<()> {
+        let (name, _, _) = self.local_addr()?.j1939();
+        if name == J1939_NO_NAME {
+            return Err(IoErrorKind::InvalidInput.into());
+        }
+        self.send_to(
+            J1939_PGN_ADDRESS_CLAIMED,
+            J1939_NO_ADDR,
+            &name.to_le_bytes(),
+        )
+    }
+
+    /// Receives the next message, returning the source, the PGN, and the
+    /// data.
+    ///
+    /// This can receive messages of up to [`J1939_MAX_TP_SIZE`] bytes.
+    /// Use [`recv_from_buf`](Self::recv_from_buf) for larger, ETP, messages.
+    pub fn recv_from(&self) -> IoResult<(J1939Addr, u32, Vec<u8>)> {
+        let mut buf = vec![0u8; J1939_MAX_TP_SIZE];
+        let (src, pgn, n) = self.recv_from_buf(&mut buf)?;
+        buf.truncate(n);
+        Ok((src, pgn, buf))
+    }
+
+    /// Receives the next message into the buffer, returning the source, the
+    /// PGN, and the length of the data.
+    ///
+    /// If the message doesn't fit in the buffer, it is discarded, and this
+    /// returns an `InvalidData` error.
+    pub fn recv_from_buf(&self, buf: &mut [u8]) -> IoResult<(J1939Addr, u32, usize)> {
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr().cast(),
+            iov_len: buf.len(),
+        };
+        let mut saddr: sockaddr_can = unsafe { mem::zeroed() };
+
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = (&mut saddr as *mut sockaddr_can).cast();
+        msg.msg_namelen = CanAddr::len() as socklen_t;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+
+        let n = unsafe { libc::recvmsg(self.as_raw_fd(), &mut msg, 0) };
+        if n < 0 {
+            return Err(IoError::last_os_error());
+        }
+        if msg.msg_flags & libc::MSG_TRUNC != 0 {
+            return Err(IoError::new(
+                IoErrorKind::InvalidData,
+                "J1939 message larger than the buffer",
+            ));
+        }
+
+        let (name, pgn, addr) = CanAddr::from(saddr).j1939();
+        Ok((J1939Addr { name, addr }, pgn, n as usize))
+    }
+
+    /// Gets the address to which the socket is bound.
+    fn local_addr(&self) -> IoResult<CanAddr> {
+        let addr = self.0.local_addr()?;
+        // SAFETY: The address came from a CAN socket, and the storage is
+        // large enough for any socket address.
+        let caddr = unsafe { ptr::read(addr.as_ptr().cast::<sockaddr_can>()) };
+        Ok(CanAddr::from(caddr))
+    }
+
+    /// Sets a J1939 option on the socket.
+    fn set_option<T>(&self, name: c_int, val: &T) -> IoResult<()> {
+        let ret = unsafe {
+            libc::setsockopt(
+                self.as_raw_fd(),
+                SOL_CAN_J1939,
+                name,
+                (val as *const T).cast(),
+                mem::size_of::<T>() as socklen_t,
+            )
+        };
+
+        match ret {
+            0 => Ok(()),
+            _ => Err(IoError::last_os_error()),
+        }
+    }
+}
+
+impl AsRawFd for J1939Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for J1939Socket {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+
+impl AsFd for J1939Socket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_j1939_id_pdu2() {
+        // EEC1, engine speed, from the engine (0x00) at priority 3
+        let id = J1939Id::from_raw(0x0CF00400).unwrap();
+        assert_eq!(3, id.priority());
+        assert_eq!(0xF004, id.pgn());
+        assert_eq!(0x00, id.source_addr());
+        assert!(!id.is_pdu1());
+        assert_eq!(None, id.dest_addr());
+        assert_eq!(id, id.with_dest(0x30));
+
+        let id = J1939Id::new(6, 0xFEF1, 0x80).unwrap();
+        assert_eq!(0x18FEF180, id.as_raw());
+        assert_eq!(
+            Id::Extended(ExtendedId::new(0x18FEF180).unwrap()),
+            id.into()
+        );
+    }
+
+    #[test]
+    fn test_j1939_id_pdu1() {
+        // A request, from 0xF9 to 0x00
+        let id = J1939Id::from_raw(0x18EA00F9).unwrap();
+        assert_eq!(6, id.priority());
+        assert_eq!(J1939_PGN_REQUEST, id.pgn());
+        assert_eq!(0xEA, id.pdu_format());
+        assert_eq!(Some(0x00), id.dest_addr());
+        assert_eq!(0xF9, id.source_addr());
+
+        let id = id.with_dest(J1939_NO_ADDR);
+        assert_eq!(0x18EAFFF9, id.as_raw());
+        assert_eq!(J1939_PGN_REQUEST, id.pgn());
+        assert_eq!(Some(J1939_NO_ADDR), id.dest_addr());
+    }
+
+    #[test]
+    fn test_j1939_id_range() {
+        assert!(J1939Id::new(8, 0xF004, 0).is_none());
+        assert!(J1939Id::new(0, J1939_NO_PGN, 0).is_none());
+        assert!(J1939Id::from_raw(0x2000_0000).is_none());
+
+        let id = J1939Id::new(7, J1939_PGN_MAX, 0xFF).unwrap();
+        assert_eq!(0x1FFF_FFFF, id.as_raw());
+    }
+}
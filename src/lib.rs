@@ -89,6 +89,19 @@
 //!   with a submodule aliased for [smol](https://crates.io/crates/smol) and examples
 //!   for that runtime.
 //!
+//! * **test_util** -
+//!   Include helpers for testing applications built on this crate, such as
+//!   frame comparisons with useful diagnostics and temporary virtual CAN
+//!   interfaces.
+//!
+//! * **ffi** -
+//!   Include a C-compatible frame type for passing frames across an FFI
+//!   boundary to C or C++ code.
+//!
+//! * **serde** -
+//!   Implement [serde](https://crates.io/crates/serde) `Serialize` and
+//!   `Deserialize` for the classic and FD frames, and the error types.
+//!
 
 // clippy: do not warn about things like "SocketCAN" inside the docs
 #![allow(clippy::doc_markdown)]
@@ -114,8 +127,8 @@ pub use embedded_can::{
 
 pub mod errors;
 pub use errors::{
-    CanError, CanErrorDecodingFailure, ConstructionError, Error, IoError, IoErrorKind, IoResult,
-    Result,
+    CanError, CanErrorClass, CanErrorDecodingFailure, CanErrorFilter, ConstructionError, Error,
+    ErrorSeverity, IoError, IoErrorKind, IoResult, Result,
 };
 
 pub mod addr;
@@ -123,15 +136,44 @@ pub use addr::CanAddr;
 
 pub mod frame;
 pub use frame::{
-    CanAnyFrame, CanDataFrame, CanErrorFrame, CanFdFrame, CanFrame, CanRawFrame, CanRemoteFrame,
-    Frame,
+    fd_dlc_to_len, fd_len_to_dlc, format_can_id, CanAnyFrame, CanDataFrame, CanErrorFrame,
+    CanFdFrame, CanFrame, CanRawFrame, CanRemoteFrame, Frame, IdStyle, Payload,
 };
 
 #[cfg(feature = "dump")]
 pub mod dump;
 
+pub mod monitor;
+
+pub mod ring;
+
+pub mod schedule;
+pub use schedule::Schedule;
+
+pub mod bundle;
+pub use bundle::CanBundle;
+
+pub mod pipeline;
+pub use pipeline::{FrameTransform, Pipeline};
+
+pub mod assembler;
+pub use assembler::SeqAssembler;
+
+pub mod bcm;
+pub use bcm::CanBcmSocket;
+
+pub mod isotp;
+pub use isotp::{CanIsoTpSocket, IsoTpSocketBuilder};
+
+pub mod j1939;
+pub use j1939::{J1939Id, J1939Socket};
+
 pub mod socket;
-pub use socket::{CanFdSocket, CanFilter, CanSocket, ShouldRetry, Socket, SocketOptions};
+pub use socket::{
+    can_abi_info, CanAbiInfo, CanFdSocket, CanFilter, CanMsg, CanSocket, CanSocketBuilder,
+    FilterSet, FrameIter, FrameOrigin, ShouldRetry, Socket, SocketOptions, TimestampClock,
+    TimestampMode,
+};
 
 #[cfg(feature = "netlink")]
 pub mod nl;
@@ -159,6 +201,14 @@ pub mod async_std {
     pub use crate::async_io::*;
 }
 
+#[cfg(feature = "test_util")]
+pub mod test_util;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "ffi")]
+pub use ffi::CCanFrame;
+
 #[cfg(feature = "enumerate")]
 pub mod enumerate;
 #[cfg(feature = "enumerate")]
@@ -0,0 +1,2164 @@
+// socketcan/src/monitor.rs
+//
+// Helpers for monitoring the traffic on a CAN bus.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Helpers for monitoring the traffic on a CAN bus.
+//!
+//! These are small, self-contained state machines that an application
+//! feeds with the frames it receives (or the times at which it received
+//! them), and which report on the health or behavior of the bus.
+//!
+//! None of these perform any I/O themselves, so they can be used with
+//! any of the socket types, blocking or async, and are easily tested with
+//! synthetic timestamps.
+
+use crate::{CanError, CanErrorClass, CanErrorFrame, Frame, Id};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+// ===== SilenceDetector =====
+
+/// An event reported by a [`SilenceDetector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SilenceEvent {
+    /// No frames have been received for longer than the threshold.
+    Silent {
+        /// The time of the last frame received, if any
+        last_frame: Option<Instant>,
+        /// How long the bus has been silent
+        elapsed: Duration,
+    },
+    /// Frames have resumed after the bus had been reported silent.
+    Resumed {
+        /// How long the bus was silent
+        silent_for: Duration,
+    },
+}
+
+/// Detects when a bus has gone silent.
+///
+/// The application reports the time of each received frame with
+/// [`frame`](SilenceDetector::frame), and periodically checks for
+/// silence with [`observe`](SilenceDetector::observe), such as after
+/// a read times out.
+///
+/// Once a silence is reported, the detector is disarmed and will not
+/// report it again, no matter how many more times it is observed. It is
+/// re-armed by the next received frame, which reports a
+/// [`SilenceEvent::Resumed`] event. The threshold for the next silence is
+/// then measured from that frame.
+#[derive(Debug, Clone, Copy)]
+pub struct SilenceDetector {
+    threshold: Duration,
+    start: Instant,
+    last_frame: Option<Instant>,
+    silent: bool,
+}
+
+impl SilenceDetector {
+    /// Creates a detector that reports silence when no frames are received
+    /// for longer than the `threshold`.
+    ///
+    /// Until the first frame is received, silence is measured from the
+    /// time the detector is created.
+    pub fn new(threshold: Duration) -> Self {
+        Self::new_at(threshold, Instant::now())
+    }
+
+    /// Creates a detector that measures silence from the specified `start`
+    /// time until the first frame is received.
+    pub fn new_at(threshold: Duration, start: Instant) -> Self {
+        Self {
+            threshold,
+            start,
+            last_frame: None,
+            silent: false,
+        }
+    }
+
+    /// Gets the silence threshold.
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Determines if the bus is currently considered silent.
+    pub fn is_silent(&self) -> bool {
+        self.silent
+    }
+
+    /// Gets the time that the last frame was received, if any.
+    pub fn last_frame(&self) -> Option<Instant> {
+        self.last_frame
+    }
+
+    /// Reports that a frame was received at the specified time.
+    ///
+    /// If the bus had been reported silent, this re-arms the detector and
+    /// returns a [`SilenceEvent::Resumed`] event.
+    pub fn frame(&mut self, at: Instant) -> Option<SilenceEvent> {
+        let since = self.last_frame.unwrap_or(self.start);
+        self.last_frame = Some(at);
+
+        if self.silent {
+            self.silent = false;
+            Some(SilenceEvent::Resumed {
+                silent_for: at.saturating_duration_since(since),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Checks for silence at the specified time.
+    ///
+    /// This returns a [`SilenceEvent::Silent`] event the first time that
+    /// the gap since the last frame exceeds the threshold, and `None` after
+    /// that until the detector is re-armed by a received frame.
+    pub fn observe(&mut self, now: Instant) -> Option<SilenceEvent> {
+        if self.silent {
+            return None;
+        }
+
+        let elapsed = now.saturating_duration_since(self.last_frame.unwrap_or(self.start));
+        if elapsed > self.threshold {
+            self.silent = true;
+            Some(SilenceEvent::Silent {
+                last_frame: self.last_frame,
+                elapsed,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Resets the detector, as if it had just been created at `now`.
+    pub fn reset(&mut self, now: Instant) {
+        *self = Self::new_at(self.threshold, now);
+    }
+}
+
+// ===== TrafficClassifier =====
+
+/// The classification of the traffic for a single CAN ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrafficClass {
+    /// The ID is sent at a regular interval.
+    Periodic {
+        /// The estimated period, the mean of the regular intervals
+        period: Duration,
+        /// The jitter, the standard deviation of the regular intervals
+        jitter: Duration,
+    },
+    /// The ID is sent irregularly, such as in response to events.
+    Sporadic,
+    /// Too few frames were seen to classify the ID.
+    Undetermined,
+}
+
+/// The traffic report for a single CAN ID from a [`TrafficClassifier`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdTraffic {
+    /// The CAN ID
+    pub id: Id,
+    /// The number of frames seen with the ID
+    pub count: usize,
+    /// The classification of the traffic
+    pub class: TrafficClass,
+}
+
+/// Classifies the IDs on a bus as periodic or sporadic.
+///
+/// The application feeds the classifier with the frames it receives, along
+/// with their timestamps, for a while, and then calls
+/// [`finish`](TrafficClassifier::finish) to get a report for each ID.
+///
+/// The heuristic works on the intervals between consecutive frames with
+/// the same ID:
+///
+/// - An ID with fewer than `min_intervals` intervals is `Undetermined`.
+/// - The median interval is taken as the candidate period. The median is
+///   used, rather than the mean, so that the occasional dropped or delayed
+///   frame does not skew it.
+/// - An interval is "regular" if it is within `tolerance` (a fraction of
+///   the median) of the median.
+/// - If at least `min_regular` (a fraction) of the intervals are regular,
+///   the ID is `Periodic`, with the period and jitter computed as the mean
+///   and standard deviation of the regular intervals. Otherwise it is
+///   `Sporadic`.
+///
+/// The defaults are 4 intervals, a tolerance of 0.25, and 0.8 of the
+/// intervals regular.
+///
+/// All the intervals are kept until the classifier is finished, so it is
+/// meant for observing a bus over a limited period of time.
+#[derive(Debug, Clone)]
+pub struct TrafficClassifier {
+    min_intervals: usize,
+    tolerance: f64,
+    min_regular: f64,
+    ids: BTreeMap<Id, IdHistory>,
+}
+
+// The history of the frames for a single ID.
+#[derive(Debug, Clone)]
+struct IdHistory {
+    count: usize,
+    last: Instant,
+    intervals: Vec<Duration>,
+}
+
+impl TrafficClassifier {
+    /// Creates a classifier with the default tunables.
+    pub fn new() -> Self {
+        Self {
+            min_intervals: 4,
+            tolerance: 0.25,
+            min_regular: 0.8,
+            ids: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the minimum number of intervals needed to classify an ID.
+    pub fn min_intervals(mut self, n: usize) -> Self {
+        self.min_intervals = n.max(1);
+        self
+    }
+
+    /// Sets how far an interval can be from the median, as a fraction of
+    /// the median, and still be considered regular.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the fraction of the intervals that must be regular for an ID
+    /// to be considered periodic.
+    pub fn min_regular(mut self, fraction: f64) -> Self {
+        self.min_regular = fraction;
+        self
+    }
+
+    /// Reports that a frame was received at the specified time.
+    ///
+    /// Frames should be reported in the order they were received.
+    pub fn frame<F: Frame>(&mut self, frame: &F, at: Instant) {
+        self.id(frame.id(), at);
+    }
+
+    /// Reports that a frame with the ID was received at the specified time.
+    pub fn id(&mut self, id: Id, at: Instant) {
+        self.ids
+            .entry(id)
+            .and_modify(|hist| {
+                hist.count += 1;
+                hist.intervals.push(at.saturating_duration_since(hist.last));
+                hist.last = at;
+            })
+            .or_insert(IdHistory {
+                count: 1,
+                last: at,
+                intervals: Vec::new(),
+            });
+    }
+
+    /// Classifies the traffic for each ID seen, in ID order.
+    pub fn finish(self) -> Vec<IdTraffic> {
+        self.ids
+            .iter()
+            .map(|(id, hist)| IdTraffic {
+                id: *id,
+                count: hist.count,
+                class: self.classify(&hist.intervals),
+            })
+            .collect()
+    }
+
+    // Classifies a single ID from the intervals between its frames.
+    fn classify(&self, intervals: &[Duration]) -> TrafficClass {
+        if intervals.len() < self.min_intervals {
+            return TrafficClass::Undetermined;
+        }
+
+        let mut sorted: Vec<f64> = intervals.iter().map(Duration::as_secs_f64).collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = sorted[sorted.len() / 2];
+
+        if median <= 0.0 {
+            return TrafficClass::Sporadic;
+        }
+
+        let tol = self.tolerance * median;
+        let regular: Vec<f64> = sorted
+            .into_iter()
+            .filter(|t| (t - median).abs() <= tol)
+            .collect();
+
+        if (regular.len() as f64) < self.min_regular * intervals.len() as f64 {
+            return TrafficClass::Sporadic;
+        }
+
+        let n = regular.len() as f64;
+        let mean = regular.iter().sum::<f64>() / n;
+        let var = regular.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / n;
+
+        TrafficClass::Periodic {
+            period: Duration::from_secs_f64(mean),
+            jitter: Duration::from_secs_f64(var.sqrt()),
+        }
+    }
+}
+
+impl Default for TrafficClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ===== BusLoadMeter =====
+
+/// Estimates the load on a bus from the frames seen on it.
+///
+/// The application reports each frame received, with its timestamp, and
+/// the meter estimates the fraction of the bus capacity (its bitrate) that
+/// was used over a sliding window of time, ending at the last frame
+/// reported or the time it is observed.
+///
+/// The length of each frame on the wire is estimated from its ID format
+/// and data length, including the worst-case number of stuff bits and the
+/// inter-frame space, so the load is a conservative (high) estimate. FD
+/// frames are counted as if the whole frame was sent at the nominal
+/// bitrate, which over-estimates their load when the data phase uses a
+/// faster bitrate.
+///
+/// The peak load is the highest load computed over the window, checked
+/// each time a frame is reported, since the meter was created or reset.
+#[derive(Debug, Clone)]
+pub struct BusLoadMeter {
+    bitrate: u32,
+    window: Duration,
+    bits: u64,
+    frames: VecDeque<(Instant, u32)>,
+    peak: f32,
+}
+
+impl BusLoadMeter {
+    /// Creates a meter for a bus with the specified bitrate, which measures
+    /// the load over the `window` of time.
+    pub fn new(bitrate: u32, window: Duration) -> Self {
+        Self {
+            bitrate: bitrate.max(1),
+            window,
+            bits: 0,
+            frames: VecDeque::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// Gets the measurement window.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Estimates the number of bits a frame takes on the bus.
+    ///
+    /// This includes the worst-case bit stuffing, and the inter-frame
+    /// space.
+    pub fn frame_bits<F: Frame>(frame: &F) -> u32 {
+        let len = if frame.is_remote_frame() {
+            0
+        } else {
+            frame.len() as u32
+        };
+
+        // The bits from SOF to the end of the CRC are subject to stuffing,
+        // then come the CRC delimiter, ACK, EOF, and IFS.
+        let stuffable = if frame.is_extended() { 54 } else { 34 } + 8 * len;
+        stuffable + (stuffable - 1) / 4 + 13
+    }
+
+    /// Reports that a frame was received at the specified time.
+    ///
+    /// Frames should be reported in the order they were received.
+    pub fn frame<F: Frame>(&mut self, frame: &F, at: Instant) {
+        let bits = Self::frame_bits(frame);
+        self.frames.push_back((at, bits));
+        self.bits += u64::from(bits);
+
+        let load = self.load_at(at);
+        if load > self.peak {
+            self.peak = load;
+        }
+    }
+
+    /// Gets the load over the window ending at the time `now`.
+    ///
+    /// This is the fraction of the bus capacity that was used, from 0.0
+    /// (idle) to 1.0 (fully loaded).
+    pub fn load_at(&mut self, now: Instant) -> f32 {
+        while let Some(&(at, bits)) = self.frames.front() {
+            if now.saturating_duration_since(at) <= self.window {
+                break;
+            }
+            self.frames.pop_front();
+            self.bits -= u64::from(bits);
+        }
+        self.load()
+    }
+
+    /// Gets the load over the window ending at the last frame reported.
+    pub fn load(&self) -> f32 {
+        let capacity = self.bitrate as f64 * self.window.as_secs_f64();
+        if capacity <= 0.0 {
+            return 0.0;
+        }
+        (self.bits as f64 / capacity).min(1.0) as f32
+    }
+
+    /// Gets the highest load seen so far.
+    pub fn peak_load(&self) -> f32 {
+        self.peak
+    }
+
+    /// Gets the fraction of the bus capacity that is still free over the
+    /// window ending at the last frame reported.
+    ///
+    /// This is the answer to "can more traffic be added?" To plan against
+    /// the busiest times, rather than the current load, use
+    /// `1.0 - peak_load()`.
+    pub fn headroom(&self) -> f32 {
+        1.0 - self.load()
+    }
+
+    /// Clears the frames and the peak load.
+    pub fn reset(&mut self) {
+        self.bits = 0;
+        self.frames.clear();
+        self.peak = 0.0;
+    }
+}
+
+// ===== DuplicateDetector =====
+
+/// Detects frames that are likely retransmissions of the previous one.
+///
+/// A node that does not get its frame acknowledged will keep resending
+/// it, which shows up on the bus as back-to-back identical frames. This
+/// flags a frame as a likely retransmission if it has the same ID and
+/// contents as the last frame seen with that ID, and arrives within a
+/// short window of it.
+///
+/// This is a heuristic. A node that legitimately sends the same data at a
+/// rate faster than the window will have its frames flagged, and a
+/// retransmission that is delayed longer than the window will be missed.
+/// The window should be tuned to the bus; something on the order of a few
+/// frame times is a reasonable start.
+#[derive(Debug, Clone)]
+pub struct DuplicateDetector {
+    window: Duration,
+    count: usize,
+    ids: BTreeMap<Id, LastFrame>,
+}
+
+// The last frame seen for a single ID.
+#[derive(Debug, Clone)]
+struct LastFrame {
+    remote: bool,
+    dlc: usize,
+    data: Vec<u8>,
+    at: Instant,
+}
+
+impl DuplicateDetector {
+    /// Creates a detector that flags identical frames arriving within the
+    /// `window` of each other.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            count: 0,
+            ids: BTreeMap::new(),
+        }
+    }
+
+    /// Gets the window for detecting duplicates.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Gets the number of frames flagged as duplicates so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Reports that a frame was received at the specified time.
+    ///
+    /// This returns `true` if the frame looks like a retransmission of the
+    /// previous frame with the same ID. Frames should be reported in the
+    /// order they were received.
+    pub fn observe<F: Frame>(&mut self, frame: &F, at: Instant) -> bool {
+        let (remote, dlc, data) = (frame.is_remote_frame(), frame.dlc(), frame.data());
+
+        let dup = match self.ids.get_mut(&frame.id()) {
+            Some(last) => {
+                let dup = last.remote == remote
+                    && last.dlc == dlc
+                    && last.data == data
+                    && at.saturating_duration_since(last.at) <= self.window;
+                last.remote = remote;
+                last.dlc = dlc;
+                last.data.clear();
+                last.data.extend_from_slice(data);
+                last.at = at;
+                dup
+            }
+            None => {
+                self.ids.insert(
+                    frame.id(),
+                    LastFrame {
+                        remote,
+                        dlc,
+                        data: data.to_vec(),
+                        at,
+                    },
+                );
+                false
+            }
+        };
+
+        if dup {
+            self.count += 1;
+        }
+        dup
+    }
+
+    /// Forgets all the frames seen so far, and resets the count.
+    pub fn reset(&mut self) {
+        self.count = 0;
+        self.ids.clear();
+    }
+}
+
+// ===== SamplingFilter =====
+
+/// Decimates received frames, passing at most one per ID per interval.
+///
+/// This is useful for something like a dashboard that only needs a few
+/// updates per second from IDs that are sent at a much higher rate. Every
+/// ID still gets through, but at a reduced rate. The first frame for an
+/// ID is always passed, and after that, the next one to arrive at least
+/// an interval after the last one that was passed.
+///
+/// The filter keeps the time of the last passed frame for every ID it
+/// has seen, so the memory grows with the number of distinct IDs: a few
+/// dozen bytes each. That's bounded for standard IDs, but on a bus with
+/// many extended IDs, or traffic with random IDs, call
+/// [`prune`](SamplingFilter::prune) periodically to forget the IDs that
+/// have gone quiet.
+#[derive(Debug, Clone)]
+pub struct SamplingFilter {
+    interval: Duration,
+    ids: BTreeMap<Id, Instant>,
+}
+
+impl SamplingFilter {
+    /// Creates a filter that passes at most one frame per ID in each
+    /// `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            ids: BTreeMap::new(),
+        }
+    }
+
+    /// Gets the minimum interval between frames passed for each ID.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Gets the number of IDs that the filter is tracking.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Determines if the filter is not tracking any IDs.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Determines if a frame received at the time `now` should be passed
+    /// on, or dropped.
+    ///
+    /// Frames should be reported in the order they were received.
+    pub fn should_pass<F: Frame>(&mut self, frame: &F, now: Instant) -> bool {
+        self.should_pass_id(frame.id(), now)
+    }
+
+    /// Determines if a frame with the ID received at the time `now` should
+    /// be passed on, or dropped.
+    pub fn should_pass_id(&mut self, id: Id, now: Instant) -> bool {
+        match self.ids.get_mut(&id) {
+            Some(last) if now.saturating_duration_since(*last) < self.interval => false,
+            Some(last) => {
+                *last = now;
+                true
+            }
+            None => {
+                self.ids.insert(id, now);
+                true
+            }
+        }
+    }
+
+    /// Forgets the IDs that haven't had a frame passed since `before`.
+    ///
+    /// The next frame for any of these IDs will be passed.
+    pub fn prune(&mut self, before: Instant) {
+        self.ids.retain(|_, last| *last >= before);
+    }
+
+    /// Forgets all the IDs.
+    pub fn reset(&mut self) {
+        self.ids.clear();
+    }
+}
+
+// ===== ChangeFilter =====
+
+/// Tracks the last data sent for each ID, to detect when it changes.
+///
+/// This is useful for something like a gateway that forwards slowly
+/// changing, state-like values, and only needs to transmit a frame when
+/// its contents differ from the last one sent with the same ID. See
+/// [`CanSocket::write_frame_if_changed`](crate::CanSocket::write_frame_if_changed).
+///
+/// Remote frames carry no data, and are a request for the current value,
+/// so they are always considered changed, and are never recorded.
+///
+/// Like the [`SamplingFilter`], the memory grows with the number of
+/// distinct IDs seen.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeFilter {
+    ids: BTreeMap<Id, Vec<u8>>,
+}
+
+impl ChangeFilter {
+    /// Creates a new, empty, filter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the number of IDs that the filter is tracking.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Determines if the filter is not tracking any IDs.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Determines if the frame differs from the last one recorded with the
+    /// same ID, without recording it.
+    ///
+    /// A frame with an ID that hasn't been seen yet is always changed.
+    pub fn is_changed<F: Frame>(&self, frame: &F) -> bool {
+        frame.is_remote_frame()
+            || self
+                .ids
+                .get(&frame.id())
+                .map_or(true, |last| last.as_slice() != frame.data())
+    }
+
+    /// Records the frame as the last one with its ID.
+    pub fn record<F: Frame>(&mut self, frame: &F) {
+        if frame.is_remote_frame() {
+            return;
+        }
+        let data = self.ids.entry(frame.id()).or_default();
+        data.clear();
+        data.extend_from_slice(frame.data());
+    }
+
+    /// Determines if the frame differs from the last one with the same ID,
+    /// and records it if so.
+    pub fn check<F: Frame>(&mut self, frame: &F) -> bool {
+        let changed = self.is_changed(frame);
+        if changed {
+            self.record(frame);
+        }
+        changed
+    }
+
+    /// Forgets the last data for the ID, so that the next frame with it is
+    /// considered changed.
+    pub fn forget(&mut self, id: Id) {
+        self.ids.remove(&id);
+    }
+
+    /// Forgets all the IDs.
+    pub fn reset(&mut self) {
+        self.ids.clear();
+    }
+}
+
+// ===== ContentFilter =====
+
+/// A software filter that matches frames on the contents of their data.
+///
+/// The kernel filters can only select frames by ID. This picks out frames
+/// by the values of individual data bytes, such as the sub-command or
+/// multiplexer byte of a message that carries several different signals
+/// under the same ID.
+///
+/// Simple byte tests are created with [`byte_eq`](ContentFilter::byte_eq)
+/// and [`byte_masked`](ContentFilter::byte_masked), and then combined with
+/// [`and`](ContentFilter::and) and [`or`](ContentFilter::or). A test on a
+/// byte past the end of the frame's data never matches, so remote frames,
+/// which have no data, never match any byte test.
+///
+/// ```
+/// use socketcan::{monitor::ContentFilter, CanFrame, EmbeddedFrame, StandardId};
+///
+/// // Byte 0 is 0x10 or 0x11, and the high nibble of byte 2 is 0xA
+/// let filt = ContentFilter::byte_eq(0, 0x10)
+///     .or(ContentFilter::byte_eq(0, 0x11))
+///     .and(ContentFilter::byte_masked(2, 0xF0, 0xA0));
+///
+/// let id = StandardId::new(0x100).unwrap();
+/// assert!(filt.matches(&CanFrame::new(id, &[0x11, 0x00, 0xA5]).unwrap()));
+/// assert!(!filt.matches(&CanFrame::new(id, &[0x12, 0x00, 0xA5]).unwrap()));
+/// assert!(!filt.matches(&CanFrame::new(id, &[0x10, 0x00]).unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentFilter {
+    /// The byte at the index, masked, equals the value
+    Byte {
+        /// The index of the byte in the data
+        index: usize,
+        /// The bits of the byte to compare
+        mask: u8,
+        /// The value to compare against the masked byte
+        value: u8,
+    },
+    /// All of the filters match. An empty list always matches.
+    All(Vec<ContentFilter>),
+    /// Any of the filters match. An empty list never matches.
+    Any(Vec<ContentFilter>),
+}
+
+impl ContentFilter {
+    /// Creates a filter that matches if the byte at the index equals the
+    /// value.
+    pub fn byte_eq(index: usize, value: u8) -> Self {
+        Self::byte_masked(index, 0xFF, value)
+    }
+
+    /// Creates a filter that matches if the byte at the index, masked,
+    /// equals the value, masked.
+    pub fn byte_masked(index: usize, mask: u8, value: u8) -> Self {
+        Self::Byte {
+            index,
+            mask,
+            value: value & mask,
+        }
+    }
+
+    /// Combines this filter with another, matching only if both match.
+    pub fn and(self, other: ContentFilter) -> Self {
+        match self {
+            Self::All(mut filts) => {
+                filts.push(other);
+                Self::All(filts)
+            }
+            filt => Self::All(vec![filt, other]),
+        }
+    }
+
+    /// Combines this filter with another, matching if either one matches.
+    pub fn or(self, other: ContentFilter) -> Self {
+        match self {
+            Self::Any(mut filts) => {
+                filts.push(other);
+                Self::Any(filts)
+            }
+            filt => Self::Any(vec![filt, other]),
+        }
+    }
+
+    /// Determines if the frame matches the filter.
+    ///
+    /// Remote frames carry no data, so they are tested as if empty.
+    pub fn matches<F: Frame>(&self, frame: &F) -> bool {
+        if frame.is_remote_frame() {
+            self.matches_data(&[])
+        } else {
+            self.matches_data(frame.data())
+        }
+    }
+
+    /// Determines if the data bytes of a frame match the filter.
+    pub fn matches_data(&self, data: &[u8]) -> bool {
+        match self {
+            Self::Byte { index, mask, value } => {
+                data.get(*index).map_or(false, |b| b & mask == *value)
+            }
+            Self::All(filts) => filts.iter().all(|filt| filt.matches_data(data)),
+            Self::Any(filts) => filts.iter().any(|filt| filt.matches_data(data)),
+        }
+    }
+}
+
+// ===== ErrorGate =====
+
+/// A gate that can temporarily suppress error reports.
+///
+/// During expected disturbances, like a node rebooting, the bus may
+/// produce a burst of errors that are of no interest to the application.
+/// The gate can be muted for a time, during which the errors passed
+/// through [`filter`](ErrorGate::filter) are dropped, and it unmutes
+/// itself automatically when the time is up. Otherwise, errors pass
+/// through unchanged.
+///
+/// This is purely an application-side filter. Muting it doesn't change
+/// the error mask on the socket, so the kernel still delivers the error
+/// frames, and they still need to be read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ErrorGate {
+    mute: Mute,
+    suppressed: u64,
+}
+
+/// The muted state of an [`ErrorGate`].
+#[derive(Debug, Clone, Copy, Default)]
+enum Mute {
+    /// Not muted
+    #[default]
+    Open,
+    /// Muted until the specified time
+    Until(Instant),
+    /// Muted until explicitly unmuted
+    Indefinite,
+}
+
+impl ErrorGate {
+    /// Creates a gate that is not muted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mutes the gate for the specified duration, starting now.
+    pub fn mute(&mut self, duration: Duration) {
+        self.mute_at(duration, Instant::now());
+    }
+
+    /// Mutes the gate for the specified duration, starting at `now`.
+    ///
+    /// If the gate is already muted, this replaces the end of the quiet
+    /// period; it does not add to it. A duration too long to represent,
+    /// like `Duration::MAX`, mutes the gate until [`unmute`](Self::unmute)
+    /// is called.
+    pub fn mute_at(&mut self, duration: Duration, now: Instant) {
+        self.mute = match now.checked_add(duration) {
+            Some(until) => Mute::Until(until),
+            None => Mute::Indefinite,
+        };
+    }
+
+    /// Unmutes the gate immediately.
+    pub fn unmute(&mut self) {
+        self.mute = Mute::Open;
+    }
+
+    /// Determines if the gate is muted at the time `now`.
+    pub fn is_muted_at(&self, now: Instant) -> bool {
+        match self.mute {
+            Mute::Open => false,
+            Mute::Until(until) => now < until,
+            Mute::Indefinite => true,
+        }
+    }
+
+    /// Gets the number of errors that were suppressed while muted.
+    pub fn suppressed(&self) -> u64 {
+        self.suppressed
+    }
+
+    /// Filters an error received now.
+    ///
+    /// This returns the error if the gate is open, or `None` if it is
+    /// muted.
+    pub fn filter(&mut self, err: CanError) -> Option<CanError> {
+        self.filter_at(err, Instant::now())
+    }
+
+    /// Filters an error received at the time `now`.
+    ///
+    /// This returns the error if the gate is open, or `None` if it is
+    /// muted.
+    pub fn filter_at(&mut self, err: CanError, now: Instant) -> Option<CanError> {
+        if self.is_muted_at(now) {
+            self.suppressed += 1;
+            None
+        } else {
+            self.mute = Mute::Open;
+            Some(err)
+        }
+    }
+}
+
+// ===== LatencyTracker =====
+
+/// The latency statistics for a request/response ID pair.
+///
+/// This is reported by a [`LatencyTracker`]. The durations are all zero
+/// if no responses were matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// The ID of the request frames
+    pub request_id: Id,
+    /// The ID of the response frames
+    pub response_id: Id,
+    /// The number of requests that were matched with a response
+    pub count: usize,
+    /// The shortest round-trip time
+    pub min: Duration,
+    /// The average round-trip time
+    pub avg: Duration,
+    /// The longest round-trip time
+    pub max: Duration,
+    /// The 99th percentile round-trip time
+    pub p99: Duration,
+    /// The number of requests that got no response within the timeout
+    pub timeouts: usize,
+    /// The number of requests still waiting for a response
+    pub pending: usize,
+    /// The number of responses that arrived with no request waiting
+    pub unmatched_responses: usize,
+}
+
+/// Measures the round-trip time between request and response frames.
+///
+/// The tracker is configured with the pairs of IDs that make up a request
+/// and its response, using [`pair`](LatencyTracker::pair). The
+/// application then reports each request frame it sends, and each frame
+/// it receives, and the tracker matches the responses to the requests.
+///
+/// Requests with the same ID are matched to responses in the order they
+/// were sent. A request that doesn't get a response within the timeout is
+/// counted as lost, so that a late response doesn't get matched to the
+/// next request, and skew all the measurements after it.
+///
+/// Every matched round-trip time is kept, to calculate the percentile, so
+/// the memory grows with the number of responses. For a long-running
+/// measurement, take a [`report`](LatencyTracker::report) periodically
+/// and then [`reset`](LatencyTracker::reset) the tracker.
+#[derive(Debug, Clone)]
+pub struct LatencyTracker {
+    timeout: Duration,
+    pairs: BTreeMap<Id, LatencyPair>,
+    responses: BTreeMap<Id, Id>,
+}
+
+// The state of a single request/response pair.
+#[derive(Debug, Clone)]
+struct LatencyPair {
+    response_id: Id,
+    pending: VecDeque<Instant>,
+    samples: Vec<Duration>,
+    timeouts: usize,
+    unmatched_responses: usize,
+}
+
+impl LatencyTracker {
+    /// Creates a tracker that considers a request lost if it doesn't get a
+    /// response within the `timeout`.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            pairs: BTreeMap::new(),
+            responses: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a request/response ID pair to track.
+    ///
+    /// Each request ID should have a single response ID, and vice versa.
+    /// Adding a pair with a request or response ID that is already in use
+    /// replaces the earlier pair.
+    pub fn pair(mut self, request_id: impl Into<Id>, response_id: impl Into<Id>) -> Self {
+        let (request_id, response_id) = (request_id.into(), response_id.into());
+
+        if let Some(old) = self.responses.insert(response_id, request_id) {
+            self.pairs.remove(&old);
+        }
+        if let Some(old) = self.pairs.insert(
+            request_id,
+            LatencyPair {
+                response_id,
+                pending: VecDeque::new(),
+                samples: Vec::new(),
+                timeouts: 0,
+                unmatched_responses: 0,
+            },
+        ) {
+            if old.response_id != response_id {
+                self.responses.remove(&old.response_id);
+            }
+        }
+        self
+    }
+
+    /// Gets the timeout for a response.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Reports that a frame with the ID was sent at the time `now`.
+    ///
+    /// This returns `true` if the ID is a tracked request.
+    pub fn on_send(&mut self, id: impl Into<Id>, now: Instant) -> bool {
+        let timeout = self.timeout;
+        match self.pairs.get_mut(&id.into()) {
+            Some(pair) => {
+                pair.expire(now, timeout);
+                pair.pending.push_back(now);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reports that a frame with the ID was received at the time `now`.
+    ///
+    /// If the ID is a tracked response, and a request is waiting for it,
+    /// this returns the round-trip time.
+    pub fn on_recv(&mut self, id: impl Into<Id>, now: Instant) -> Option<Duration> {
+        let timeout = self.timeout;
+        let request_id = self.responses.get(&id.into())?;
+        let pair = self.pairs.get_mut(request_id)?;
+
+        pair.expire(now, timeout);
+        match pair.pending.pop_front() {
+            Some(sent) => {
+                let rtt = now.saturating_duration_since(sent);
+                pair.samples.push(rtt);
+                Some(rtt)
+            }
+            None => {
+                pair.unmatched_responses += 1;
+                None
+            }
+        }
+    }
+
+    /// Counts any requests that have been waiting longer than the timeout
+    /// as lost, at the time `now`.
+    ///
+    /// This is done automatically as frames are reported, but can be
+    /// called before taking a report, to make sure it is up to date.
+    pub fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        for pair in self.pairs.values_mut() {
+            pair.expire(now, timeout);
+        }
+    }
+
+    /// Gets the statistics for each of the ID pairs, in order of the
+    /// request ID.
+    pub fn report(&self) -> Vec<LatencyStats> {
+        self.pairs
+            .iter()
+            .map(|(request_id, pair)| {
+                let mut samples = pair.samples.clone();
+                samples.sort_unstable();
+
+                let count = samples.len();
+                let (min, avg, max, p99) = if count == 0 {
+                    Default::default()
+                } else {
+                    let total: Duration = samples.iter().sum();
+                    // Nearest-rank percentile
+                    let p99 = samples[(count * 99 + 99) / 100 - 1];
+                    (samples[0], total / count as u32, samples[count - 1], p99)
+                };
+
+                LatencyStats {
+                    request_id: *request_id,
+                    response_id: pair.response_id,
+                    count,
+                    min,
+                    avg,
+                    max,
+                    p99,
+                    timeouts: pair.timeouts,
+                    pending: pair.pending.len(),
+                    unmatched_responses: pair.unmatched_responses,
+                }
+            })
+            .collect()
+    }
+
+    /// Clears all the measurements, keeping the ID pairs.
+    pub fn reset(&mut self) {
+        for pair in self.pairs.values_mut() {
+            pair.pending.clear();
+            pair.samples.clear();
+            pair.timeouts = 0;
+            pair.unmatched_responses = 0;
+        }
+    }
+}
+
+impl LatencyPair {
+    // Drops the pending requests that have timed out.
+    fn expire(&mut self, now: Instant, timeout: Duration) {
+        while let Some(&sent) = self.pending.front() {
+            if now.saturating_duration_since(sent) <= timeout {
+                break;
+            }
+            self.pending.pop_front();
+            self.timeouts += 1;
+        }
+    }
+}
+
+// ===== EpisodeAggregator =====
+
+/// The state of a controller, as far as it can be told from the errors it
+/// reports.
+///
+/// These are in order of increasing severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BusState {
+    /// The controller is error active; the normal state.
+    ErrorActive,
+    /// The controller's error counters reached the warning level.
+    ErrorWarning,
+    /// The controller is error passive.
+    ErrorPassive,
+    /// The controller is bus-off.
+    BusOff,
+}
+
+impl BusState {
+    /// Gets the controller state that an error indicates, if any.
+    ///
+    /// The controller problems report the state changes, a bus-off error
+    /// means the controller is bus-off, and a restart means it is error
+    /// active again. The error counters give the state by their levels.
+    /// The other errors don't say anything about the state.
+    pub fn from_error(err: &CanError) -> Option<Self> {
+        use crate::errors::ControllerProblem::*;
+
+        match err {
+            CanError::BusOff => Some(BusState::BusOff),
+            CanError::Restarted => Some(BusState::ErrorActive),
+            CanError::ErrorCounters { tx, rx } => Some(Self::from_counters(*tx, *rx)),
+            CanError::ControllerProblem(prob) => match prob {
+                ReceiveErrorWarning | TransmitErrorWarning => Some(BusState::ErrorWarning),
+                ReceiveErrorPassive | TransmitErrorPassive => Some(BusState::ErrorPassive),
+                Active => Some(BusState::ErrorActive),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Gets the controller state indicated by the error counters.
+    ///
+    /// The state is error passive if either counter is 128 or more, and
+    /// error warning if either is at the usual warning level of 96 or
+    /// more. Bus-off can't be detected this way, as the 8-bit counters
+    /// can't reach that level (256).
+    pub fn from_counters(tx: u8, rx: u8) -> Self {
+        match tx.max(rx) {
+            128.. => BusState::ErrorPassive,
+            96.. => BusState::ErrorWarning,
+            _ => BusState::ErrorActive,
+        }
+    }
+}
+
+/// A burst of errors on the bus, reported by an [`EpisodeAggregator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Episode {
+    /// The time of the first error
+    pub start: Instant,
+    /// The time of the last error
+    pub end: Instant,
+    /// The number of errors
+    pub count: usize,
+    /// The most frequent class of error, if any of the errors had one
+    pub dominant_class: Option<CanErrorClass>,
+    /// The most severe controller state reported, if any
+    pub peak_state: Option<BusState>,
+}
+
+impl Episode {
+    /// Gets the time from the first to the last error in the episode.
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_duration_since(self.start)
+    }
+}
+
+/// Groups a stream of errors into episodes, separated by quiet periods.
+///
+/// This turns a noisy stream of error frames into a concise timeline of
+/// incidents. Each error is reported with [`error`](EpisodeAggregator::error),
+/// and errors that follow one another within the `gap` threshold are part
+/// of the same episode. When an error arrives more than the `gap` after
+/// the previous one, the episode is closed and returned, and a new one is
+/// started. The episode in progress can also be closed when the bus has
+/// been quiet for longer than the gap, with
+/// [`observe`](EpisodeAggregator::observe), or at the end of the stream,
+/// with [`finish`](EpisodeAggregator::finish).
+///
+/// The gap should be longer than the interval between the errors of a
+/// single incident, like the retries of a frame that isn't acknowledged,
+/// but shorter than the time between unrelated incidents. A second or
+/// so is a reasonable start.
+///
+/// The dominant class of an episode is the [`CanErrorClass`] with the most
+/// errors in it. Ties go to the class with the lowest bit. Errors that
+/// couldn't be decoded have no class, and are only counted in the total.
+#[derive(Debug, Clone)]
+pub struct EpisodeAggregator {
+    gap: Duration,
+    current: Option<EpisodeState>,
+}
+
+// The state of the episode in progress.
+#[derive(Debug, Clone)]
+struct EpisodeState {
+    start: Instant,
+    end: Instant,
+    count: usize,
+    class_counts: [usize; CanErrorClass::ALL.len()],
+    peak_state: Option<BusState>,
+}
+
+impl EpisodeState {
+    fn new(at: Instant) -> Self {
+        Self {
+            start: at,
+            end: at,
+            count: 0,
+            class_counts: [0; CanErrorClass::ALL.len()],
+            peak_state: None,
+        }
+    }
+
+    fn add(&mut self, err: &CanError, at: Instant) {
+        self.end = at;
+        self.count += 1;
+
+        let bits = err.error_class();
+        for (i, class) in CanErrorClass::ALL.iter().enumerate() {
+            if bits & class.bit() != 0 {
+                self.class_counts[i] += 1;
+            }
+        }
+
+        if let Some(state) = BusState::from_error(err) {
+            self.peak_state = self.peak_state.max(Some(state));
+        }
+    }
+
+    fn episode(&self) -> Episode {
+        let mut dominant_class = None;
+        let mut max = 0;
+        for (class, &n) in CanErrorClass::ALL.iter().zip(&self.class_counts) {
+            if n > max {
+                max = n;
+                dominant_class = Some(*class);
+            }
+        }
+
+        Episode {
+            start: self.start,
+            end: self.end,
+            count: self.count,
+            dominant_class,
+            peak_state: self.peak_state,
+        }
+    }
+}
+
+impl EpisodeAggregator {
+    /// Creates an aggregator that ends an episode after a quiet period
+    /// longer than the `gap`.
+    pub fn new(gap: Duration) -> Self {
+        Self { gap, current: None }
+    }
+
+    /// Gets the quiet period that separates episodes.
+    pub fn gap(&self) -> Duration {
+        self.gap
+    }
+
+    /// Reports an error received at the specified time.
+    ///
+    /// If this error starts a new episode, the previous one is returned.
+    /// Errors should be reported in the order they were received.
+    pub fn error(&mut self, err: &CanError, at: Instant) -> Option<Episode> {
+        let ended = self.observe(at);
+        self.current
+            .get_or_insert_with(|| EpisodeState::new(at))
+            .add(err, at);
+        ended
+    }
+
+    /// Checks for the end of the current episode at the time `now`.
+    ///
+    /// If there have been no errors for longer than the gap, this closes
+    /// the episode in progress and returns it.
+    pub fn observe(&mut self, now: Instant) -> Option<Episode> {
+        match &self.current {
+            Some(cur) if now.saturating_duration_since(cur.end) > self.gap => self.finish(),
+            _ => None,
+        }
+    }
+
+    /// Closes the episode in progress, if any, and returns it.
+    pub fn finish(&mut self) -> Option<Episode> {
+        self.current.take().map(|cur| cur.episode())
+    }
+}
+
+// ===== CounterTracker =====
+
+/// Tracks the controller's error counters from the stream of error frames.
+///
+/// This is a lightweight way to follow the transmit and receive error
+/// counters without netlink, which might not be available, such as inside
+/// a restricted container. The socket must have the error counter class
+/// enabled in its error filter, like with
+/// `CanErrorFilter::new(CanErrorClass::ErrorCounters.bit())`, or any mask
+/// with the 0x0200 bit.
+///
+/// The counters are only as fresh as the last error frame that reported
+/// them. Many drivers report them along with another error, so when the
+/// frame is available, prefer [`frame`](CounterTracker::frame), which
+/// reads the counters out of any error frame that has them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CounterTracker {
+    current: Option<(u8, u8)>,
+    peak: Option<(u8, u8)>,
+}
+
+impl CounterTracker {
+    /// Creates a new tracker, which hasn't seen any counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the counters from an error.
+    ///
+    /// An `ErrorCounters` error sets the counters, and a `Restarted` error
+    /// clears them to zero, as the controller resets its counters when it
+    /// restarts. Any other error is ignored. This returns `true` if the
+    /// counters were updated.
+    pub fn update(&mut self, err: &CanError) -> bool {
+        match *err {
+            CanError::ErrorCounters { tx, rx } => self.set(tx, rx),
+            CanError::Restarted => self.set(0, 0),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Updates the counters from an error frame, if it reports them.
+    ///
+    /// This reads the counters from any error frame with the counter bit
+    /// set, even if it also reports another error. This returns `true` if
+    /// the counters were updated.
+    pub fn frame(&mut self, frame: &CanErrorFrame) -> bool {
+        match frame.error_counters() {
+            Some((tx, rx)) => {
+                self.set(tx, rx);
+                true
+            }
+            None => self.update(&frame.into_error()),
+        }
+    }
+
+    fn set(&mut self, tx: u8, rx: u8) {
+        self.current = Some((tx, rx));
+        self.peak = Some(match self.peak {
+            Some((ptx, prx)) => (ptx.max(tx), prx.max(rx)),
+            None => (tx, rx),
+        });
+    }
+
+    /// Gets the last reported counters, as `(tx, rx)`, if any.
+    pub fn current(&self) -> Option<(u8, u8)> {
+        self.current
+    }
+
+    /// Gets the highest value reported for each counter, as `(tx, rx)`.
+    pub fn peak(&self) -> Option<(u8, u8)> {
+        self.peak
+    }
+
+    /// Gets the controller state indicated by the last reported counters.
+    pub fn state(&self) -> Option<BusState> {
+        self.current.map(|(tx, rx)| BusState::from_counters(tx, rx))
+    }
+
+    /// Forgets the counters.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// ===== CollisionDetector =====
+
+/// A report from a [`CollisionDetector`] that an ID was seen coming
+/// from more than one interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionEvent {
+    /// The ID of the frames
+    pub id: Id,
+    /// The index of the interface on which the ID was just seen
+    pub ifindex: u32,
+    /// The index of the interface on which the ID was previously seen
+    pub other_ifindex: u32,
+    /// The time between the two frames
+    pub interval: Duration,
+}
+
+/// Detects the same frame ID originating from more than one interface.
+///
+/// This is a safety check for a gateway that bridges two or more buses.
+/// If a node on each bus sends frames with the same ID, forwarding them
+/// can cause routing loops, or confuse the receivers. The gateway reports
+/// each frame that it receives from a bus, along with the index of the
+/// interface it came in on, and the detector reports a collision if the
+/// ID was last seen on a different interface within the window.
+///
+/// The frames that the gateway forwards itself should _not_ be reported,
+/// only those that originate on each bus.
+///
+/// Only the last interface and time are kept for each ID, so an ID that
+/// alternates between two interfaces within the window is reported on
+/// every change. The memory grows with the number of distinct IDs: a few
+/// dozen bytes each. Call [`prune`](CollisionDetector::prune)
+/// periodically to forget the IDs that have gone quiet.
+#[derive(Debug, Clone)]
+pub struct CollisionDetector {
+    window: Duration,
+    count: usize,
+    ids: BTreeMap<Id, (u32, Instant)>,
+}
+
+impl CollisionDetector {
+    /// Creates a detector that reports an ID seen on different interfaces
+    /// within the `window` of each other.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            count: 0,
+            ids: BTreeMap::new(),
+        }
+    }
+
+    /// Gets the window for detecting collisions.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Gets the number of collisions reported so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Gets the number of IDs that the detector is tracking.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Determines if the detector is not tracking any IDs.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Reports that a frame was received from the interface with the
+    /// specified index, at the specified time.
+    ///
+    /// Frames should be reported in the order they were received.
+    pub fn observe<F: Frame>(
+        &mut self,
+        ifindex: u32,
+        frame: &F,
+        at: Instant,
+    ) -> Option<CollisionEvent> {
+        self.observe_id(ifindex, frame.id(), at)
+    }
+
+    /// Reports that a frame with the ID was received from the interface
+    /// with the specified index, at the specified time.
+    pub fn observe_id(&mut self, ifindex: u32, id: Id, at: Instant) -> Option<CollisionEvent> {
+        let (other_ifindex, last) = self.ids.insert(id, (ifindex, at))?;
+        let interval = at.saturating_duration_since(last);
+
+        if other_ifindex != ifindex && interval <= self.window {
+            self.count += 1;
+            Some(CollisionEvent {
+                id,
+                ifindex,
+                other_ifindex,
+                interval,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Forgets the IDs that haven't been seen since `before`.
+    pub fn prune(&mut self, before: Instant) {
+        self.ids.retain(|_, (_, last)| *last >= before);
+    }
+
+    /// Forgets all the IDs, and resets the count.
+    pub fn reset(&mut self) {
+        self.count = 0;
+        self.ids.clear();
+    }
+}
+
+// ===== JitterMonitor =====
+
+/// The timing statistics of a periodic ID, reported by a [`JitterMonitor`].
+///
+/// The durations are all zero if no gaps were measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JitterReport {
+    /// The ID of the frames
+    pub id: Id,
+    /// The expected period between frames
+    pub period: Duration,
+    /// The number of gaps measured
+    pub samples: usize,
+    /// The average gap between frames
+    pub mean: Duration,
+    /// The standard deviation of the gaps
+    pub std_dev: Duration,
+    /// The largest difference between a gap and the expected period,
+    /// whether early or late
+    pub max_deviation: Duration,
+    /// The shortest gap
+    pub min_gap: Duration,
+    /// The longest gap
+    pub max_gap: Duration,
+    /// The estimated number of frames that were missed
+    pub missed: u64,
+}
+
+/// Measures the jitter of the gaps between the frames of a periodic ID.
+///
+/// This is for checking that a node sending an ID periodically meets its
+/// timing spec. The application reports every frame that it receives,
+/// and the monitor measures the gap between each frame with the ID and the
+/// one before it.
+///
+/// The first frame has nothing before it, so it only sets the reference
+/// for the next one, and doesn't produce a measurement.
+///
+/// A gap of one and a half periods or more is taken to be one or more
+/// missed frames, rather than a very late one. The number of missed frames
+/// is estimated from the gap, and counted in the report, but the gap is
+/// left out of the statistics, so that a single lost frame doesn't swamp
+/// the jitter measurement.
+///
+/// Only running totals are kept, so the memory use is constant.
+#[derive(Debug, Clone, Copy)]
+pub struct JitterMonitor {
+    id: Id,
+    period: Duration,
+    last: Option<Instant>,
+    samples: usize,
+    sum: f64,
+    sum_sq: f64,
+    max_deviation: Duration,
+    min_gap: Duration,
+    max_gap: Duration,
+    missed: u64,
+}
+
+impl JitterMonitor {
+    /// Creates a monitor for frames with the ID, which are expected every
+    /// `period`.
+    ///
+    /// # Panics
+    ///
+    /// If the period is zero.
+    #[track_caller]
+    pub fn new(id: impl Into<Id>, period: Duration) -> Self {
+        assert!(!period.is_zero(), "the jitter period must not be zero");
+        Self {
+            id: id.into(),
+            period,
+            last: None,
+            samples: 0,
+            sum: 0.0,
+            sum_sq: 0.0,
+            max_deviation: Duration::ZERO,
+            min_gap: Duration::MAX,
+            max_gap: Duration::ZERO,
+            missed: 0,
+        }
+    }
+
+    /// Gets the ID of the frames being monitored.
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    /// Gets the expected period between frames.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Reports that a frame was received at the specified time.
+    ///
+    /// Frames with other IDs are ignored, so all the received frames can
+    /// be passed in. This returns the gap since the previous frame with
+    /// the ID, if there was one.
+    pub fn observe<F: Frame>(&mut self, frame: &F, now: Instant) -> Option<Duration> {
+        if frame.id() == self.id {
+            self.observe_id(now)
+        } else {
+            None
+        }
+    }
+
+    /// Reports that a frame with the monitored ID was received at the
+    /// specified time.
+    pub fn observe_id(&mut self, now: Instant) -> Option<Duration> {
+        let gap = now.saturating_duration_since(self.last.replace(now)?);
+
+        let periods = gap.as_secs_f64() / self.period.as_secs_f64();
+        if periods >= 1.5 {
+            self.missed = self.missed.saturating_add(periods.round() as u64 - 1);
+            return Some(gap);
+        }
+
+        let secs = gap.as_secs_f64();
+        self.samples += 1;
+        self.sum += secs;
+        self.sum_sq += secs * secs;
+
+        let deviation = if gap > self.period {
+            gap - self.period
+        } else {
+            self.period - gap
+        };
+        self.max_deviation = self.max_deviation.max(deviation);
+        self.min_gap = self.min_gap.min(gap);
+        self.max_gap = self.max_gap.max(gap);
+        Some(gap)
+    }
+
+    /// Gets the statistics of the gaps measured so far.
+    pub fn report(&self) -> JitterReport {
+        let (mean, std_dev, min_gap) = match self.samples {
+            0 => (Duration::ZERO, Duration::ZERO, Duration::ZERO),
+            n => {
+                let mean = self.sum / n as f64;
+                let var = (self.sum_sq / n as f64 - mean * mean).max(0.0);
+                (
+                    Duration::from_secs_f64(mean),
+                    Duration::from_secs_f64(var.sqrt()),
+                    self.min_gap,
+                )
+            }
+        };
+
+        JitterReport {
+            id: self.id,
+            period: self.period,
+            samples: self.samples,
+            mean,
+            std_dev,
+            max_deviation: self.max_deviation,
+            min_gap,
+            max_gap: self.max_gap,
+            missed: self.missed,
+        }
+    }
+
+    /// Clears the statistics, and forgets the last frame.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.id, self.period);
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StandardId;
+
+    const THRESHOLD: Duration = Duration::from_millis(100);
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_silence() {
+        let t0 = Instant::now();
+        let mut det = SilenceDetector::new_at(THRESHOLD, t0);
+
+        assert!(det.observe(t0 + ms(50)).is_none());
+        assert!(det.frame(t0 + ms(60)).is_none());
+        assert!(det.observe(t0 + ms(150)).is_none());
+        assert!(!det.is_silent());
+
+        let ev = det.observe(t0 + ms(200)).unwrap();
+        assert_eq!(
+            SilenceEvent::Silent {
+                last_frame: Some(t0 + ms(60)),
+                elapsed: ms(140)
+            },
+            ev
+        );
+        assert!(det.is_silent());
+    }
+
+    #[test]
+    fn test_no_frames() {
+        let t0 = Instant::now();
+        let mut det = SilenceDetector::new_at(THRESHOLD, t0);
+
+        let ev = det.observe(t0 + ms(101)).unwrap();
+        assert!(matches!(
+            ev,
+            SilenceEvent::Silent {
+                last_frame: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_rearm() {
+        let t0 = Instant::now();
+        let mut det = SilenceDetector::new_at(THRESHOLD, t0);
+
+        assert!(det.observe(t0 + ms(200)).is_some());
+
+        // No spurious re-alerts while still silent
+        assert!(det.observe(t0 + ms(300)).is_none());
+        assert!(det.observe(t0 + ms(1000)).is_none());
+
+        // Recovery
+        let ev = det.frame(t0 + ms(1100)).unwrap();
+        assert_eq!(
+            SilenceEvent::Resumed {
+                silent_for: ms(1100)
+            },
+            ev
+        );
+        assert!(!det.is_silent());
+        assert!(det.frame(t0 + ms(1110)).is_none());
+
+        // Threshold is measured from the latest frame
+        assert!(det.observe(t0 + ms(1200)).is_none());
+        assert!(det.observe(t0 + ms(1211)).is_some());
+    }
+
+    #[test]
+    fn test_classify_periodic() {
+        let t0 = Instant::now();
+        let id = Id::Standard(StandardId::new(0x100).unwrap());
+        let mut tc = TrafficClassifier::new();
+
+        // Every 10ms, +/- 1ms, with one dropped frame
+        for (i, jitter) in [0, 1, 0, 1, 0, 1, 0, 1, 0, 1].iter().enumerate() {
+            if i != 5 {
+                tc.id(id, t0 + ms(10 * i as u64 + jitter));
+            }
+        }
+
+        let report = tc.finish();
+        assert_eq!(1, report.len());
+        assert_eq!(id, report[0].id);
+        assert_eq!(9, report[0].count);
+
+        match report[0].class {
+            TrafficClass::Periodic { period, jitter } => {
+                assert!(period > ms(9) && period < ms(11));
+                assert!(jitter <= ms(1));
+            }
+            class => panic!("Expected periodic, got {:?}", class),
+        }
+    }
+
+    #[test]
+    fn test_classify_sporadic() {
+        let t0 = Instant::now();
+        let id = Id::Standard(StandardId::new(0x200).unwrap());
+        let other = Id::Standard(StandardId::new(0x300).unwrap());
+        let mut tc = TrafficClassifier::new();
+
+        for t in [0, 3, 50, 52, 200, 410, 415] {
+            tc.id(id, t0 + ms(t));
+        }
+        tc.id(other, t0);
+        tc.id(other, t0 + ms(10));
+
+        let report = tc.finish();
+        assert_eq!(TrafficClass::Sporadic, report[0].class);
+        assert_eq!(TrafficClass::Undetermined, report[1].class);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        use crate::{CanFrame, EmbeddedFrame};
+
+        let t0 = Instant::now();
+        let mut det = DuplicateDetector::new(ms(2));
+
+        let id = StandardId::new(0x100).unwrap();
+        let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+        let other = CanFrame::new(id, &[1, 2, 4]).unwrap();
+        let remote = CanFrame::new_remote(id, 3).unwrap();
+
+        assert!(!det.observe(&frame, t0));
+        assert!(det.observe(&frame, t0 + ms(1)));
+        assert!(det.observe(&frame, t0 + ms(2)));
+
+        // Too late, or different contents
+        assert!(!det.observe(&frame, t0 + ms(10)));
+        assert!(!det.observe(&other, t0 + ms(11)));
+        assert!(!det.observe(&remote, t0 + ms(12)));
+
+        // Another ID doesn't interfere
+        let frame2 = CanFrame::new(StandardId::new(0x200).unwrap(), &[1, 2, 3]).unwrap();
+        assert!(!det.observe(&frame2, t0 + ms(12)));
+        assert!(det.observe(&remote, t0 + ms(13)));
+
+        assert_eq!(3, det.count());
+    }
+
+    #[test]
+    fn test_bus_load() {
+        use crate::{CanFrame, EmbeddedFrame};
+
+        let t0 = Instant::now();
+        let mut meter = BusLoadMeter::new(125_000, ms(100));
+
+        // An 8-byte standard frame is 135 bits, worst case
+        let frame = CanFrame::new(StandardId::new(0x100).unwrap(), &[0; 8]).unwrap();
+        assert_eq!(135, BusLoadMeter::frame_bits(&frame));
+
+        // 50 frames in 100ms is 6750 bits of the 12,500 available
+        for i in 0..50 {
+            meter.frame(&frame, t0 + ms(2 * i));
+        }
+        assert!((meter.load() - 0.54).abs() < 0.001);
+        assert!((meter.headroom() - 0.46).abs() < 0.001);
+        assert!((meter.peak_load() - 0.54).abs() < 0.001);
+
+        // Quiet later on, but the peak remains
+        assert_eq!(0.0, meter.load_at(t0 + ms(500)));
+        assert_eq!(1.0, meter.headroom());
+        assert!((meter.peak_load() - 0.54).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_sampling() {
+        use crate::{CanFrame, EmbeddedFrame};
+
+        let t0 = Instant::now();
+        let mut filt = SamplingFilter::new(ms(100));
+
+        let frame = CanFrame::new(StandardId::new(0x100).unwrap(), &[1]).unwrap();
+        let other = CanFrame::new(StandardId::new(0x200).unwrap(), &[2]).unwrap();
+
+        // A frame every 10ms passes one in ten
+        let passed: Vec<_> = (0..25)
+            .filter(|i| filt.should_pass(&frame, t0 + ms(10 * i)))
+            .collect();
+        assert_eq!(vec![0, 10, 20], passed);
+
+        // Other IDs are independent
+        assert!(filt.should_pass(&other, t0 + ms(240)));
+        assert!(!filt.should_pass(&other, t0 + ms(250)));
+        assert_eq!(2, filt.len());
+
+        filt.prune(t0 + ms(220));
+        assert_eq!(1, filt.len());
+        assert!(filt.should_pass(&frame, t0 + ms(250)));
+    }
+
+    #[test]
+    fn test_change_filter() {
+        use crate::{CanFrame, EmbeddedFrame};
+
+        let id = StandardId::new(0x100).unwrap();
+        let frame = CanFrame::new(id, &[1, 2]).unwrap();
+        let other = CanFrame::new(id, &[1, 2, 3]).unwrap();
+        let remote = CanFrame::new_remote(id, 2).unwrap();
+
+        let mut filt = ChangeFilter::new();
+        assert!(filt.check(&frame));
+        assert!(!filt.check(&frame));
+        assert!(filt.is_changed(&other));
+        assert!(filt.check(&remote));
+        assert!(filt.check(&remote));
+        assert!(!filt.check(&frame));
+        assert!(filt.check(&other));
+        assert!(!filt.check(&other));
+
+        filt.forget(id.into());
+        assert!(filt.is_empty());
+        assert!(filt.check(&other));
+    }
+
+    #[test]
+    fn test_content_filter() {
+        use crate::{CanFrame, EmbeddedFrame};
+
+        let id = StandardId::new(0x100).unwrap();
+        let frame = CanFrame::new(id, &[0x01, 0x5A, 0xFF]).unwrap();
+
+        assert!(ContentFilter::byte_eq(1, 0x5A).matches(&frame));
+        assert!(!ContentFilter::byte_eq(1, 0x5B).matches(&frame));
+        assert!(ContentFilter::byte_masked(1, 0x0F, 0xEA).matches(&frame));
+        assert!(!ContentFilter::byte_masked(1, 0xF0, 0x40).matches(&frame));
+
+        // Past the end of the data
+        assert!(!ContentFilter::byte_eq(3, 0).matches(&frame));
+        assert!(!ContentFilter::byte_masked(3, 0, 0).matches(&frame));
+        let remote = CanFrame::new_remote(id, 4).unwrap();
+        assert!(!ContentFilter::byte_masked(0, 0, 0).matches(&remote));
+
+        let filt = ContentFilter::byte_eq(0, 0x01).and(ContentFilter::byte_eq(2, 0xFF));
+        assert!(filt.matches(&frame));
+        let filt = filt.and(ContentFilter::byte_eq(1, 0x00));
+        assert!(!filt.matches(&frame));
+        assert!(matches!(&filt, ContentFilter::All(v) if v.len() == 3));
+
+        let filt = ContentFilter::byte_eq(0, 0x02).or(ContentFilter::byte_eq(5, 0x01));
+        assert!(!filt.matches(&frame));
+        let filt = filt.or(ContentFilter::byte_eq(2, 0xFF));
+        assert!(filt.matches(&frame));
+
+        assert!(ContentFilter::All(vec![]).matches(&frame));
+        assert!(!ContentFilter::Any(vec![]).matches(&frame));
+    }
+
+    #[test]
+    fn test_error_gate() {
+        let t0 = Instant::now();
+        let mut gate = ErrorGate::new();
+
+        assert!(gate.filter_at(CanError::NoAck, t0).is_some());
+
+        gate.mute_at(ms(100), t0);
+        assert!(gate.is_muted_at(t0 + ms(50)));
+        assert!(gate.filter_at(CanError::NoAck, t0 + ms(50)).is_none());
+        assert!(gate.filter_at(CanError::BusOff, t0 + ms(99)).is_none());
+
+        assert!(!gate.is_muted_at(t0 + ms(100)));
+        assert!(matches!(
+            gate.filter_at(CanError::BusOff, t0 + ms(100)),
+            Some(CanError::BusOff)
+        ));
+        assert_eq!(2, gate.suppressed());
+
+        gate.mute_at(Duration::MAX, t0);
+        assert!(gate.is_muted_at(t0 + ms(1_000_000)));
+        assert!(gate
+            .filter_at(CanError::NoAck, t0 + ms(1_000_000))
+            .is_none());
+        assert_eq!(3, gate.suppressed());
+
+        gate.unmute();
+        assert!(!gate.is_muted_at(t0));
+    }
+
+    #[test]
+    fn test_latency() {
+        let t0 = Instant::now();
+        let req = StandardId::new(0x7E0).unwrap();
+        let resp = StandardId::new(0x7E8).unwrap();
+        let mut tracker = LatencyTracker::new(ms(50)).pair(req, resp);
+
+        for i in 0..100 {
+            let t = t0 + ms(100 * i);
+            assert!(tracker.on_send(req, t));
+            let rtt = ms(1 + i % 10);
+            assert_eq!(Some(rtt), tracker.on_recv(resp, t + rtt));
+        }
+
+        // A lost request, a late response, and an unexpected one
+        let t = t0 + ms(10_000);
+        tracker.on_send(req, t);
+        assert_eq!(None, tracker.on_recv(resp, t + ms(60)));
+        assert_eq!(None, tracker.on_recv(StandardId::new(0x123).unwrap(), t));
+        assert!(!tracker.on_send(StandardId::new(0x123).unwrap(), t));
+
+        let report = tracker.report();
+        assert_eq!(1, report.len());
+        let stats = report[0];
+        assert_eq!(Id::from(req), stats.request_id);
+        assert_eq!(100, stats.count);
+        assert_eq!(ms(1), stats.min);
+        assert_eq!(ms(10), stats.max);
+        assert_eq!(Duration::from_micros(5500), stats.avg);
+        assert_eq!(ms(10), stats.p99);
+        assert_eq!(1, stats.timeouts);
+        assert_eq!(1, stats.unmatched_responses);
+        assert_eq!(0, stats.pending);
+    }
+
+    #[test]
+    fn test_episodes() {
+        use crate::errors::ControllerProblem;
+
+        let t0 = Instant::now();
+        let mut agg = EpisodeAggregator::new(ms(100));
+
+        let warning = CanError::ControllerProblem(ControllerProblem::TransmitErrorWarning);
+        let passive = CanError::ControllerProblem(ControllerProblem::TransmitErrorPassive);
+
+        assert!(agg.error(&CanError::NoAck, t0).is_none());
+        assert!(agg.error(&warning, t0 + ms(50)).is_none());
+        assert!(agg.error(&CanError::NoAck, t0 + ms(100)).is_none());
+        assert!(agg.error(&passive, t0 + ms(150)).is_none());
+        assert!(agg.error(&CanError::NoAck, t0 + ms(200)).is_none());
+        assert!(agg.observe(t0 + ms(300)).is_none());
+
+        // A new error after the gap closes the first episode
+        let ep = agg.error(&CanError::BusError, t0 + ms(1000)).unwrap();
+        assert_eq!(t0, ep.start);
+        assert_eq!(ms(200), ep.duration());
+        assert_eq!(5, ep.count);
+        assert_eq!(Some(CanErrorClass::NoAck), ep.dominant_class);
+        assert_eq!(Some(BusState::ErrorPassive), ep.peak_state);
+
+        let counters = CanError::ErrorCounters { tx: 100, rx: 3 };
+        assert_eq!(
+            Some(BusState::ErrorWarning),
+            BusState::from_error(&counters)
+        );
+        assert_eq!(BusState::ErrorPassive, BusState::from_counters(0, 128));
+        assert_eq!(BusState::ErrorActive, BusState::from_counters(95, 0));
+
+        let ep = agg.observe(t0 + ms(1101)).unwrap();
+        assert_eq!(1, ep.count);
+        assert_eq!(Duration::ZERO, ep.duration());
+        assert_eq!(Some(CanErrorClass::BusError), ep.dominant_class);
+        assert_eq!(None, ep.peak_state);
+
+        assert!(agg.finish().is_none());
+    }
+
+    #[test]
+    fn test_collisions() {
+        use crate::{CanFrame, EmbeddedFrame};
+
+        let t0 = Instant::now();
+        let mut det = CollisionDetector::new(ms(100));
+
+        let frame = CanFrame::new(StandardId::new(0x100).unwrap(), &[1]).unwrap();
+        let other = CanFrame::new(StandardId::new(0x200).unwrap(), &[2]).unwrap();
+
+        assert!(det.observe(1, &frame, t0).is_none());
+        assert!(det.observe(1, &frame, t0 + ms(10)).is_none());
+        assert!(det.observe(2, &other, t0 + ms(20)).is_none());
+
+        let ev = det.observe(2, &frame, t0 + ms(50)).unwrap();
+        assert_eq!(frame.id(), ev.id);
+        assert_eq!(2, ev.ifindex);
+        assert_eq!(1, ev.other_ifindex);
+        assert_eq!(ms(40), ev.interval);
+        assert_eq!(1, det.count());
+
+        // Outside the window
+        assert!(det.observe(1, &frame, t0 + ms(200)).is_none());
+        assert_eq!(2, det.len());
+
+        det.prune(t0 + ms(100));
+        assert_eq!(1, det.len());
+        det.reset();
+        assert!(det.is_empty());
+        assert_eq!(0, det.count());
+    }
+
+    #[test]
+    fn test_counters() {
+        let mut tracker = CounterTracker::new();
+        assert_eq!(None, tracker.current());
+
+        assert!(!tracker.update(&CanError::NoAck));
+        assert!(tracker.update(&CanError::ErrorCounters { tx: 100, rx: 5 }));
+        assert_eq!(Some((100, 5)), tracker.current());
+        assert_eq!(Some(BusState::ErrorWarning), tracker.state());
+
+        // Counters that come along with another error
+        let frame = CanErrorFrame::new_error(0x0204, &[0, 0x20, 0, 0, 0, 0, 130, 9]).unwrap();
+        assert!(tracker.frame(&frame));
+        assert_eq!(Some((130, 9)), tracker.current());
+        assert_eq!(Some(BusState::ErrorPassive), tracker.state());
+
+        assert!(tracker.update(&CanError::Restarted));
+        assert_eq!(Some((0, 0)), tracker.current());
+        assert_eq!(Some((130, 9)), tracker.peak());
+
+        tracker.reset();
+        assert_eq!(None, tracker.peak());
+    }
+
+    #[test]
+    fn test_jitter() {
+        use crate::{CanFrame, EmbeddedFrame};
+
+        let t0 = Instant::now();
+        let id = StandardId::new(0x100).unwrap();
+        let mut mon = JitterMonitor::new(id, ms(10));
+
+        let frame = CanFrame::new(id, &[1]).unwrap();
+        let other = CanFrame::new(StandardId::new(0x200).unwrap(), &[2]).unwrap();
+
+        assert_eq!(None, mon.observe(&frame, t0));
+        assert_eq!(None, mon.observe(&other, t0 + ms(5)));
+        assert_eq!(Some(ms(8)), mon.observe(&frame, t0 + ms(8)));
+        assert_eq!(Some(ms(12)), mon.observe(&frame, t0 + ms(20)));
+
+        // Two missed frames
+        assert_eq!(Some(ms(30)), mon.observe(&frame, t0 + ms(50)));
+
+        let rpt = mon.report();
+        assert_eq!(2, rpt.samples);
+        assert_eq!(2, rpt.missed);
+        assert_eq!(ms(10), rpt.mean);
+        assert_eq!(ms(2), rpt.max_deviation);
+        assert_eq!(ms(8), rpt.min_gap);
+        assert_eq!(ms(12), rpt.max_gap);
+        assert!((rpt.std_dev.as_secs_f64() - 0.002).abs() < 1e-9);
+
+        mon.reset();
+        let rpt = mon.report();
+        assert_eq!(0, rpt.samples);
+        assert_eq!(Duration::ZERO, rpt.min_gap);
+    }
+
+    #[test]
+    #[should_panic(expected = "must not be zero")]
+    fn test_jitter_zero_period() {
+        JitterMonitor::new(StandardId::new(0x100).unwrap(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,293 @@
+// socketcan/src/nl/bittiming.rs
+//
+// Pure-Rust calculation of CAN bit-timing parameters.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Calculation of CAN bit-timing parameters.
+//!
+//! This is a port of the algorithm the Linux kernel uses when an interface
+//! is configured with only a bitrate (and, optionally, a sample point). It
+//! lets an application preview or validate the timing that the kernel would
+//! choose, or compute exact raw timing to apply to the interface.
+//!
+//! See: linux/drivers/net/can/dev/calc_bittiming.c
+//!
+
+use super::{CanBitTiming, CanBitTimingConst};
+
+/// The synchronization segment is always one time quantum.
+const CAN_SYNC_SEG: u32 = 1;
+
+/// The maximum bitrate error, in one-tenth of a percent.
+const CAN_CALC_MAX_ERROR: u64 = 50;
+
+/// Gets the CiA recommended sample point for the bitrate, in one-tenth
+/// of a percent.
+fn default_sample_point(bitrate: u32) -> u32 {
+    if bitrate > 800_000 {
+        750
+    } else if bitrate > 500_000 {
+        800
+    } else {
+        875
+    }
+}
+
+/// Finds the split of `tseg` into (tseg1, tseg2) that gets closest to the
+/// nominal sample point without exceeding it.
+///
+/// Returns the resulting sample point, the split, and the sample point
+/// error. The split is `None` if no valid split was found.
+fn update_sample_point(
+    btc: &CanBitTimingConst,
+    sample_point_nominal: u32,
+    tseg: u32,
+) -> (u32, Option<(u32, u32)>, u32) {
+    let mut best_sample_point = 0;
+    let mut best_sample_point_error = u32::MAX;
+    let mut best_split = None;
+
+    let tsegall = tseg + CAN_SYNC_SEG;
+
+    for i in 0..=1 {
+        let tseg2 = (tsegall - (sample_point_nominal * tsegall) / 1000)
+            .saturating_sub(i)
+            .clamp(btc.tseg2_min, btc.tseg2_max);
+
+        let (tseg1, tseg2) = match tseg.checked_sub(tseg2) {
+            Some(tseg1) if tseg1 > btc.tseg1_max => (btc.tseg1_max, tseg - btc.tseg1_max),
+            Some(tseg1) => (tseg1, tseg2),
+            None => continue,
+        };
+
+        let sample_point = 1000 * (tsegall - tseg2) / tsegall;
+        let sample_point_error = sample_point_nominal.abs_diff(sample_point);
+
+        if sample_point <= sample_point_nominal && sample_point_error < best_sample_point_error {
+            best_sample_point = sample_point;
+            best_sample_point_error = sample_point_error;
+            best_split = Some((tseg1, tseg2));
+        }
+    }
+
+    (best_sample_point, best_split, best_sample_point_error)
+}
+
+/// Calculates the bit-timing parameters for a CAN controller.
+///
+/// This computes a valid bit-rate prescaler (`brp`), time segments, and
+/// synchronization jump width (`sjw`) that produce the requested `bitrate`
+/// from the controller's clock, `clock_hz`, as close as possible to the
+/// requested sample point, within the limits of the controller's timing
+/// constants, `btc`.
+///
+/// The sample point is given in one-tenth of a percent (i.e. 875 is 87.5%),
+/// and must be less than 1000. If it is `None` or zero, the CiA recommended
+/// sample point for the bitrate is used.
+///
+/// This mirrors the calculation done by the Linux kernel, so the result
+/// should match what the kernel would choose for the interface if given
+/// only the bitrate and sample point. The returned timing has the actual
+/// bitrate and sample point that would be achieved.
+///
+/// Returns `None` if the bitrate can not be achieved with an error of
+/// less than 5%, or if the inputs are invalid.
+pub fn calc_bittiming<P>(
+    clock_hz: u32,
+    bitrate: u32,
+    sample_point: P,
+    btc: &CanBitTimingConst,
+) -> Option<CanBitTiming>
+where
+    P: Into<Option<u32>>,
+{
+    if clock_hz == 0 || bitrate == 0 || btc.brp_inc == 0 {
+        return None;
+    }
+
+    let sample_point_nominal = match sample_point.into() {
+        Some(sp) if sp >= 1000 => return None,
+        Some(sp) if sp != 0 => sp,
+        _ => default_sample_point(bitrate),
+    };
+
+    let mut best_bitrate_error = u32::MAX;
+    let mut best_sample_point_error = u32::MAX;
+    let mut best_tseg = 0;
+    let mut best_brp = 0;
+
+    // tseg even = round down, odd = round up
+    let tseg_max = (btc.tseg1_max + btc.tseg2_max) * 2 + 1;
+    let tseg_min = (btc.tseg1_min + btc.tseg2_min) * 2;
+
+    for tseg in (tseg_min..=tseg_max).rev() {
+        let tsegall = CAN_SYNC_SEG + tseg / 2;
+
+        // Compute all possible tseg choices (tseg = tseg1 + tseg2)
+        let brp = (clock_hz as u64 / (tsegall as u64 * bitrate as u64)) as u32 + tseg % 2;
+
+        // Choose a brp step which is possible in the system
+        let brp = (brp / btc.brp_inc) * btc.brp_inc;
+        if brp < btc.brp_min || brp > btc.brp_max || brp == 0 {
+            continue;
+        }
+
+        let actual = clock_hz / (brp * tsegall);
+        let bitrate_error = bitrate.abs_diff(actual);
+
+        if bitrate_error > best_bitrate_error {
+            continue;
+        }
+
+        // Reset the sample point error if we have a better bitrate
+        if bitrate_error < best_bitrate_error {
+            best_sample_point_error = u32::MAX;
+        }
+
+        let (_, _, sample_point_error) = update_sample_point(btc, sample_point_nominal, tseg / 2);
+        if sample_point_error >= best_sample_point_error {
+            continue;
+        }
+
+        best_sample_point_error = sample_point_error;
+        best_bitrate_error = bitrate_error;
+        best_tseg = tseg / 2;
+        best_brp = brp;
+
+        if bitrate_error == 0 && sample_point_error == 0 {
+            break;
+        }
+    }
+
+    if best_brp == 0 {
+        return None;
+    }
+
+    // Error in one-tenth of a percent
+    if best_bitrate_error as u64 * 1000 / bitrate as u64 > CAN_CALC_MAX_ERROR {
+        return None;
+    }
+
+    let (sample_point, split, _) = update_sample_point(btc, sample_point_nominal, best_tseg);
+    let (tseg1, tseg2) = split?;
+
+    let prop_seg = tseg1 / 2;
+    let phase_seg1 = tseg1 - prop_seg;
+    let phase_seg2 = tseg2;
+
+    let mut sjw = phase_seg1.min(phase_seg2 / 2).max(1);
+    if btc.sjw_max != 0 {
+        sjw = sjw.min(btc.sjw_max);
+    }
+
+    Some(CanBitTiming {
+        bitrate: clock_hz / (best_brp * (CAN_SYNC_SEG + tseg1 + tseg2)),
+        sample_point,
+        tq: (best_brp as u64 * 1_000_000_000 / clock_hz as u64) as u32,
+        prop_seg,
+        phase_seg1,
+        phase_seg2,
+        sjw,
+        brp: best_brp,
+    })
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The timing constants of the SJA1000 controller
+    const SJA1000: CanBitTimingConst = CanBitTimingConst {
+        name: [0; 16],
+        tseg1_min: 1,
+        tseg1_max: 16,
+        tseg2_min: 1,
+        tseg2_max: 8,
+        sjw_max: 4,
+        brp_min: 1,
+        brp_max: 64,
+        brp_inc: 1,
+    };
+
+    // The timing constants of the Bosch M_CAN (nominal) controller
+    const MCAN: CanBitTimingConst = CanBitTimingConst {
+        name: [0; 16],
+        tseg1_min: 2,
+        tseg1_max: 256,
+        tseg2_min: 2,
+        tseg2_max: 128,
+        sjw_max: 128,
+        brp_min: 1,
+        brp_max: 512,
+        brp_inc: 1,
+    };
+
+    fn segs(bt: &CanBitTiming) -> u32 {
+        CAN_SYNC_SEG + bt.prop_seg + bt.phase_seg1 + bt.phase_seg2
+    }
+
+    #[test]
+    fn test_sja1000_8mhz() {
+        let bt = calc_bittiming(8_000_000, 500_000, None, &SJA1000).unwrap();
+        assert_eq!(500_000, bt.bitrate);
+        assert_eq!(875, bt.sample_point);
+        assert_eq!(125, bt.tq);
+        assert_eq!(6, bt.prop_seg);
+        assert_eq!(7, bt.phase_seg1);
+        assert_eq!(2, bt.phase_seg2);
+        assert_eq!(1, bt.sjw);
+        assert_eq!(1, bt.brp);
+
+        let bt = calc_bittiming(8_000_000, 250_000, None, &SJA1000).unwrap();
+        assert_eq!(250_000, bt.bitrate);
+        assert_eq!(875, bt.sample_point);
+        assert_eq!(2, bt.brp);
+        assert_eq!(16, segs(&bt));
+
+        let bt = calc_bittiming(8_000_000, 1_000_000, None, &SJA1000).unwrap();
+        assert_eq!(1_000_000, bt.bitrate);
+        assert_eq!(750, bt.sample_point);
+        assert_eq!(1, bt.brp);
+        assert_eq!(8, segs(&bt));
+    }
+
+    #[test]
+    fn test_sample_point() {
+        let bt = calc_bittiming(8_000_000, 500_000, 750, &SJA1000).unwrap();
+        assert_eq!(500_000, bt.bitrate);
+        assert_eq!(750, bt.sample_point);
+
+        // Zero means the CiA default
+        let bt = calc_bittiming(8_000_000, 500_000, 0, &SJA1000).unwrap();
+        assert_eq!(875, bt.sample_point);
+    }
+
+    #[test]
+    fn test_mcan_40mhz() {
+        for bitrate in [125_000, 250_000, 500_000, 1_000_000] {
+            let bt = calc_bittiming(40_000_000, bitrate, None, &MCAN).unwrap();
+            assert_eq!(bitrate, bt.bitrate);
+            assert_eq!(default_sample_point(bitrate), bt.sample_point);
+            assert_eq!(40_000_000, bt.bitrate * bt.brp * segs(&bt));
+        }
+    }
+
+    #[test]
+    fn test_impossible() {
+        // Can't reach this bitrate from the clock
+        assert!(calc_bittiming(8_000_000, 5_000_000, None, &SJA1000).is_none());
+        // Invalid inputs
+        assert!(calc_bittiming(0, 500_000, None, &SJA1000).is_none());
+        assert!(calc_bittiming(8_000_000, 0, None, &SJA1000).is_none());
+        assert!(calc_bittiming(8_000_000, 500_000, 1000, &SJA1000).is_none());
+        assert!(calc_bittiming(8_000_000, 500_000, u32::MAX, &SJA1000).is_none());
+    }
+}
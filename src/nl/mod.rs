@@ -52,6 +52,7 @@
 //! <https://github.com/lalten/libsocketcan>
 //!
 
+use crate::monitor::BusState;
 use neli::{
     attr::Attribute,
     consts::{
@@ -72,12 +73,21 @@ use rt::IflaCan;
 use std::{
     ffi::CStr,
     fmt::Debug,
-    os::raw::{c_int, c_uint},
+    fs,
+    os::raw::{c_char, c_int, c_uint},
+    path::Path,
+    time::Duration,
 };
 
 /// Low-level Netlink CAN struct bindings.
 mod rt;
 
+pub mod bittiming;
+pub use bittiming::calc_bittiming;
+
+pub mod watch;
+pub use watch::{CanLinkMonitor, LinkEvent, LinkEventKind, LinkEvents, StateChange, StateWatcher};
+
 use rt::can_ctrlmode;
 pub use rt::CanState;
 
@@ -87,6 +97,118 @@ type NlResult<T> = Result<T, NlError>;
 /// A Netlink error from an info query
 type NlInfoError = NlError<Rtm, Ifinfomsg>;
 
+/// An error from configuring a CAN interface.
+///
+/// The configuration functions of [`CanInterface`] return the raw netlink
+/// error, which can be converted into this to pick out the common failures
+/// that an application may want to handle.
+///
+/// ```no_run
+/// use socketcan::{nl::CanConfigError, CanInterface};
+///
+/// let iface = CanInterface::open("can0").unwrap();
+/// match iface.set_bitrate(500_000, None).map_err(CanConfigError::from) {
+///     Err(CanConfigError::Busy) => {
+///         iface.bring_down().unwrap();
+///         iface.set_bitrate(500_000, None).unwrap();
+///     }
+///     res => res.unwrap(),
+/// }
+/// iface.bring_up().unwrap();
+/// ```
+#[derive(Debug, thiserror::Error)]
+pub enum CanConfigError {
+    /// The interface is busy (`EBUSY`).
+    ///
+    /// Most parameters, like the bitrate, can only be changed while the
+    /// interface is down.
+    #[error("the interface is busy, and may need to be brought down first")]
+    Busy,
+    /// The process is not allowed to configure the interface (`EPERM`).
+    ///
+    /// This requires the `CAP_NET_ADMIN` capability.
+    #[error("permission denied to configure the interface")]
+    PermissionDenied,
+    /// The driver rejected the parameters (`EINVAL` or `ERANGE`).
+    ///
+    /// This is usually a bitrate or sample point that can't be achieved
+    /// with the controller's clock, or a combination of settings that the
+    /// controller doesn't support, such as a data bitrate without FD mode.
+    #[error("the driver rejected the parameters")]
+    InvalidParams,
+    /// The driver does not support the operation (`EOPNOTSUPP`).
+    #[error("the operation is not supported by the driver")]
+    Unsupported,
+    /// The bitrate must be set before the sample point can be changed.
+    #[error("the bitrate has not been set")]
+    NoBitrate,
+    /// Any other netlink error
+    #[error(transparent)]
+    Netlink(NlError),
+}
+
+impl CanConfigError {
+    /// Gets the error for one of the common error codes, if it is one.
+    ///
+    /// Netlink reports the negated errno.
+    fn from_nl_errno(err: c_int) -> Option<Self> {
+        match -err {
+            libc::EBUSY => Some(Self::Busy),
+            libc::EPERM => Some(Self::PermissionDenied),
+            libc::EINVAL | libc::ERANGE => Some(Self::InvalidParams),
+            libc::EOPNOTSUPP => Some(Self::Unsupported),
+            _ => None,
+        }
+    }
+}
+
+impl From<NlError> for CanConfigError {
+    fn from(err: NlError) -> Self {
+        match err {
+            NlError::Nlmsgerr(ref msg) => Self::from_nl_errno(msg.error),
+            _ => None,
+        }
+        .unwrap_or(Self::Netlink(err))
+    }
+}
+
+impl From<NlInfoError> for CanConfigError {
+    fn from(err: NlInfoError) -> Self {
+        match err {
+            NlError::Nlmsgerr(ref msg) => Self::from_nl_errno(msg.error),
+            _ => None,
+        }
+        .unwrap_or_else(|| Self::Netlink(NlError::Msg(err.to_string())))
+    }
+}
+
+/// Converts a sample point, as a fraction of the bit time, to the tenths
+/// of a percent used by the kernel.
+fn sample_point_from_fraction(sample_point: f32) -> Option<u32> {
+    if sample_point > 0.0 && sample_point < 1.0 {
+        Some((sample_point * 1000.0).round() as u32)
+    } else {
+        None
+    }
+}
+
+impl CanState {
+    /// Gets the equivalent bus state, as reported by error frames.
+    ///
+    /// This allows the state polled from the interface to be compared or
+    /// combined with the state tracked from the error frames received on a
+    /// socket. A stopped or sleeping controller has no bus state.
+    pub fn bus_state(&self) -> Option<BusState> {
+        match *self {
+            CanState::ErrorActive => Some(BusState::ErrorActive),
+            CanState::ErrorWarning => Some(BusState::ErrorWarning),
+            CanState::ErrorPassive => Some(BusState::ErrorPassive),
+            CanState::BusOff => Some(BusState::BusOff),
+            CanState::Stopped | CanState::Sleeping => None,
+        }
+    }
+}
+
 /// CAN bit-timing parameters
 pub type CanBitTiming = rt::can_bittiming;
 /// CAN bit-timing const parameters
@@ -173,6 +295,18 @@ pub struct InterfaceCanParams {
     pub termination: u16,
 }
 
+impl InterfaceCanParams {
+    /// Gets the nominal bit rate, if configured.
+    pub fn bitrate(&self) -> Option<u32> {
+        self.bit_timing.map(|timing| timing.bitrate)
+    }
+
+    /// Gets the CAN FD data phase bit rate, if configured.
+    pub fn data_bitrate(&self) -> Option<u32> {
+        self.data_bit_timing.map(|timing| timing.bitrate)
+    }
+}
+
 impl TryFrom<&Rtattr<Ifla, Buffer>> for InterfaceCanParams {
     type Error = NlInfoError;
 
@@ -226,6 +360,125 @@ impl TryFrom<&Rtattr<Ifla, Buffer>> for InterfaceCanParams {
     }
 }
 
+// ===== LinkStats =====
+
+/// The generic network statistics counters for an interface.
+///
+/// These are the standard packet and error counts that the kernel keeps
+/// for every network interface, which give a coarse view of the health of
+/// the interface beyond the CAN-specific error counters.
+///
+/// The kernel does not provide a way to clear these counters. To count
+/// from a known point, keep a snapshot and use [`LinkStats::since`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LinkStats {
+    /// The number of packets received
+    pub rx_packets: u64,
+    /// The number of packets transmitted
+    pub tx_packets: u64,
+    /// The number of bad packets received
+    pub rx_errors: u64,
+    /// The number of packet transmit problems
+    pub tx_errors: u64,
+    /// The number of received packets dropped, such as for lack of buffer space
+    pub rx_dropped: u64,
+    /// The number of packets dropped on transmit
+    pub tx_dropped: u64,
+}
+
+impl LinkStats {
+    /// Gets the change in the counters since an earlier snapshot.
+    ///
+    /// This is the equivalent of having cleared the counters at the time
+    /// the `earlier` snapshot was taken.
+    pub fn since(&self, earlier: &LinkStats) -> LinkStats {
+        LinkStats {
+            rx_packets: self.rx_packets.wrapping_sub(earlier.rx_packets),
+            tx_packets: self.tx_packets.wrapping_sub(earlier.tx_packets),
+            rx_errors: self.rx_errors.wrapping_sub(earlier.rx_errors),
+            tx_errors: self.tx_errors.wrapping_sub(earlier.tx_errors),
+            rx_dropped: self.rx_dropped.wrapping_sub(earlier.rx_dropped),
+            tx_dropped: self.tx_dropped.wrapping_sub(earlier.tx_dropped),
+        }
+    }
+}
+
+impl From<rt::rtnl_link_stats64> for LinkStats {
+    fn from(stats: rt::rtnl_link_stats64) -> Self {
+        Self {
+            rx_packets: stats.rx_packets,
+            tx_packets: stats.tx_packets,
+            rx_errors: stats.rx_errors,
+            tx_errors: stats.tx_errors,
+            rx_dropped: stats.rx_dropped,
+            tx_dropped: stats.tx_dropped,
+        }
+    }
+}
+
+// ===== SysfsStats =====
+
+/// The network statistics counters for an interface, read from sysfs.
+///
+/// These are the same generic counters as in [`LinkStats`], plus a few
+/// more detailed receive error counters, but read from the files in
+/// `/sys/class/net/<iface>/statistics/` rather than over netlink. This is
+/// a fallback for systems where the netlink statistics are unavailable or
+/// incomplete, and another angle on frame loss when debugging.
+///
+/// Each counter is read from a separate file, and is `None` if the file
+/// is missing or can't be parsed. Since the files are read one at a time,
+/// the counters are not an atomic snapshot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SysfsStats {
+    /// The number of packets received
+    pub rx_packets: Option<u64>,
+    /// The number of packets transmitted
+    pub tx_packets: Option<u64>,
+    /// The number of bytes received
+    pub rx_bytes: Option<u64>,
+    /// The number of bytes transmitted
+    pub tx_bytes: Option<u64>,
+    /// The number of bad packets received
+    pub rx_errors: Option<u64>,
+    /// The number of packet transmit problems
+    pub tx_errors: Option<u64>,
+    /// The number of received packets dropped, such as for lack of buffer space
+    pub rx_dropped: Option<u64>,
+    /// The number of packets dropped on transmit
+    pub tx_dropped: Option<u64>,
+    /// The number of receive overruns in the controller
+    pub rx_over_errors: Option<u64>,
+    /// The number of receive FIFO errors
+    pub rx_fifo_errors: Option<u64>,
+    /// The number of packets missed by the controller
+    pub rx_missed_errors: Option<u64>,
+}
+
+impl SysfsStats {
+    /// Reads the statistics for the named interface from sysfs.
+    pub fn read(ifname: &str) -> Self {
+        let dir = Path::new("/sys/class/net").join(ifname).join("statistics");
+        let counter = |name: &str| -> Option<u64> {
+            fs::read_to_string(dir.join(name)).ok()?.trim().parse().ok()
+        };
+
+        Self {
+            rx_packets: counter("rx_packets"),
+            tx_packets: counter("tx_packets"),
+            rx_bytes: counter("rx_bytes"),
+            tx_bytes: counter("tx_bytes"),
+            rx_errors: counter("rx_errors"),
+            tx_errors: counter("tx_errors"),
+            rx_dropped: counter("rx_dropped"),
+            tx_dropped: counter("tx_dropped"),
+            rx_over_errors: counter("rx_over_errors"),
+            rx_fifo_errors: counter("rx_fifo_errors"),
+            rx_missed_errors: counter("rx_missed_errors"),
+        }
+    }
+}
+
 // ===== CanCtrlMode(s) =====
 
 ///
@@ -349,6 +602,16 @@ impl CanInterface {
         Self { if_index }
     }
 
+    /// Gets the kernel index of the interface.
+    ///
+    /// When the interface is opened by name, this is looked up once, and
+    /// can then be used to open any number of sockets on the interface,
+    /// with [`Socket::open_iface`](crate::Socket::open_iface), without
+    /// repeating the lookup.
+    pub fn index(&self) -> u32 {
+        self.if_index
+    }
+
     /// Creates an `Ifinfomsg` for this CAN interface from a buffer
     fn info_msg(&self, buf: RtBuffer<Ifla, Buffer>) -> Ifinfomsg {
         Ifinfomsg::new(
@@ -544,6 +807,12 @@ impl CanInterface {
     }
 
     /// Attempt to query detailed information on the interface.
+    ///
+    /// This gets all the information, including all the CAN-specific
+    /// parameters like the bit rates, state, restart time, control modes,
+    /// and error counters, from a single netlink query. This is more
+    /// efficient than querying the parameters individually, and gives a
+    /// consistent snapshot of the interface.
     pub fn details(&self) -> Result<InterfaceDetails, NlInfoError> {
         match self.query_details()? {
             Some(msg_hdr) => {
@@ -580,6 +849,61 @@ impl CanInterface {
         }
     }
 
+    /// Determines if the interface can carry CAN FD frames.
+    ///
+    /// This checks, with a single netlink query, that the interface MTU
+    /// (`IFLA_MTU`) is set for FD frames, and that the driver supports
+    /// FD, which it does if it reports the limits for the data phase bit
+    /// timing (`IFLA_CAN_DATA_BITTIMING_CONST`). Virtual interfaces, like
+    /// _vcan_, that don't report any CAN parameters at all, can carry FD
+    /// frames just by having the FD MTU.
+    ///
+    /// On a hardware interface, the MTU is set to the FD size by the kernel
+    /// when FD mode is enabled in the control mode, so this is `false` for
+    /// an FD-capable controller that is configured for classic CAN only.
+    pub fn supports_fd(&self) -> Result<bool, NlInfoError> {
+        let details = self.details()?;
+        if details.mtu != Some(Mtu::Fd) {
+            return Ok(false);
+        }
+
+        let can = &details.can;
+        let is_virtual = can.clock.is_none() && can.bit_timing_const.is_none();
+        Ok(is_virtual || can.data_bit_timing_const.is_some())
+    }
+
+    /// Gets the generic network statistics counters for the interface.
+    pub fn link_stats(&self) -> Result<Option<LinkStats>, NlInfoError> {
+        if let Some(hdr) = self.query_details()? {
+            if let Ok(payload) = hdr.get_payload() {
+                for attr in payload.rtattrs.iter() {
+                    if attr.rta_type == Ifla::Stats64 {
+                        let stats = attr.get_payload_as::<rt::rtnl_link_stats64>()?;
+                        return Ok(Some(stats.into()));
+                    }
+                }
+            }
+            Ok(None)
+        } else {
+            Err(NlError::NoAck)
+        }
+    }
+
+    /// Gets the network statistics counters for the interface from sysfs.
+    ///
+    /// This is a fallback for [`link_stats`](CanInterface::link_stats)
+    /// that doesn't use netlink. It only fails if the name of the
+    /// interface can't be found from its index. Any counters that can't be
+    /// read are `None`.
+    pub fn sysfs_stats(&self) -> Result<SysfsStats, nix::Error> {
+        let mut buf = [0 as c_char; libc::IF_NAMESIZE];
+        if unsafe { libc::if_indextoname(self.if_index, buf.as_mut_ptr()) }.is_null() {
+            return Err(nix::Error::last());
+        }
+        let name = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy();
+        Ok(SysfsStats::read(&name))
+    }
+
     /// Set the MTU of this interface.
     ///
     /// PRIVILEGED: This requires root privilege.
@@ -658,6 +982,9 @@ impl CanInterface {
     /// specified in Hz (bps) while the sample point is given in tenths
     /// of a percent/
     ///
+    /// If the interface is up, this fails with `EBUSY`, which can be
+    /// detected by converting the error into a [`CanConfigError`].
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_bitrate<P>(&self, bitrate: u32, sample_point: P) -> NlResult<()>
@@ -684,7 +1011,45 @@ impl CanInterface {
         })
     }
 
+    /// Sets the sample point for the interface, keeping the current
+    /// bitrate.
+    ///
+    /// The sample point is given as a fraction of the bit time, such as
+    /// 0.75 or 0.875, and must be strictly between zero and one, or this
+    /// returns [`CanConfigError::InvalidParams`]. The kernel recomputes the
+    /// bit timing, so the sample point it actually ends up with, from
+    /// [`sample_point`](Self::sample_point), may differ slightly.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn set_sample_point(&self, sample_point: f32) -> Result<(), CanConfigError> {
+        let sample_point =
+            sample_point_from_fraction(sample_point).ok_or(CanConfigError::InvalidParams)?;
+        let bitrate = self
+            .bit_rate()?
+            .filter(|&bitrate| bitrate != 0)
+            .ok_or(CanConfigError::NoBitrate)?;
+        Ok(self.set_bitrate(bitrate, sample_point)?)
+    }
+
+    /// Gets the current sample point for the interface, in tenths of a
+    /// percent.
+    ///
+    /// When the bitrate is set without a sample point, or with one that
+    /// the controller can't hit exactly, the kernel computes the timing,
+    /// and this is the sample point that it actually ended up with.
+    pub fn sample_point(&self) -> Result<Option<u32>, NlInfoError> {
+        Ok(self.bit_timing()?.map(|timing| timing.sample_point))
+    }
+
     /// Gets the bit timing params for the interface
+    ///
+    /// This is the effective timing that the kernel computed for the
+    /// controller when the bitrate was set (`IFLA_CAN_BITTIMING`), with all
+    /// the resulting fields filled in: the actual bitrate and sample point,
+    /// the time quantum, segment lengths, and prescaler. These can differ
+    /// from what was requested, so reading them back is the way to verify
+    /// the configuration.
     pub fn bit_timing(&self) -> Result<Option<CanBitTiming>, NlInfoError> {
         self.can_param::<CanBitTiming>(IflaCan::BitTiming)
     }
@@ -710,6 +1075,10 @@ impl CanInterface {
     }
 
     /// Gets the state of the interface
+    ///
+    /// This is the error state of the controller (`IFLA_CAN_STATE`), which
+    /// can be polled even when no error frames are being delivered. A
+    /// virtual interface has no state.
     pub fn state(&self) -> Result<Option<CanState>, NlInfoError> {
         Ok(self
             .can_param::<u32>(IflaCan::State)?
@@ -746,6 +1115,37 @@ impl CanInterface {
         self.set_ctrlmodes(CanCtrlModes::from_mode(mode, on))
     }
 
+    /// Enable or disable one-shot mode on the interface.
+    ///
+    /// In one-shot mode, the controller does not automatically retransmit
+    /// a frame that lost arbitration or was not acknowledged. This is an
+    /// interface-wide setting that applies to all frames sent on the bus,
+    /// and it can only be changed while the interface is down. Not all
+    /// controllers support it.
+    ///
+    /// The kernel does not support one-shot transmission of individual
+    /// frames on a RAW socket. The closest approximation is a Broadcast
+    /// Manager (BCM) `TX_SETUP` operation with a count of one, but that
+    /// still uses the controller's retransmission setting.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn set_one_shot(&self, on: bool) -> NlResult<()> {
+        self.set_ctrlmode(CanCtrlMode::OneShot, on)
+    }
+
+    /// Enable or disable CAN FD mode on the interface.
+    ///
+    /// This sets the `CAN_CTRLMODE_FD` flag. The interface must be down,
+    /// and the controller must support FD. A data bitrate should be set
+    /// along with it, with [`set_data_bitrate`](Self::set_data_bitrate).
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn set_fd(&self, on: bool) -> NlResult<()> {
+        self.set_ctrlmode(CanCtrlMode::Fd, on)
+    }
+
     /// Gets the automatic CANbus restart time for the interface, in milliseconds.
     pub fn restart_ms(&self) -> Result<Option<u32>, NlInfoError> {
         self.can_param::<u32>(IflaCan::RestartMs)
@@ -759,6 +1159,19 @@ impl CanInterface {
         self.set_can_param(IflaCan::RestartMs, &restart_ms.to_ne_bytes()[..])
     }
 
+    /// Set the time after which the interface is automatically restarted
+    /// when it goes bus-off.
+    ///
+    /// The time is rounded down to whole milliseconds. A time of zero
+    /// disables automatic restarts.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn set_restart_time(&self, restart: Duration) -> NlResult<()> {
+        let restart_ms = u32::try_from(restart.as_millis()).unwrap_or(u32::MAX);
+        self.set_restart_ms(restart_ms)
+    }
+
     /// Manually restart the interface.
     ///
     /// Note that a manual restart if only permitted if automatic restart is
@@ -785,6 +1198,15 @@ impl CanInterface {
         self.can_param::<CanBerrCounter>(IflaCan::BerrCounter)
     }
 
+    /// Gets the transmit and receive error counters of the controller, as
+    /// a `(tx, rx)` pair.
+    ///
+    /// Not all drivers report the counters, and a virtual interface has
+    /// none.
+    pub fn error_counters(&self) -> Result<Option<(u16, u16)>, NlInfoError> {
+        Ok(self.berr_counter()?.map(|cnt| (cnt.txerr, cnt.rxerr)))
+    }
+
     /// Gets the data bit timing params for the interface
     pub fn data_bit_timing(&self) -> Result<Option<CanBitTiming>, NlInfoError> {
         self.can_param::<CanBitTiming>(IflaCan::DataBitTiming)
@@ -822,6 +1244,26 @@ impl CanInterface {
         })
     }
 
+    /// Sets the data sample point for the interface, keeping the current
+    /// data bitrate.
+    ///
+    /// This only applies to interfaces in FD mode. The sample point is
+    /// given as a fraction of the bit time, like for
+    /// [`set_sample_point`](Self::set_sample_point).
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn set_data_sample_point(&self, sample_point: f32) -> Result<(), CanConfigError> {
+        let sample_point =
+            sample_point_from_fraction(sample_point).ok_or(CanConfigError::InvalidParams)?;
+        let bitrate = self
+            .data_bit_timing()?
+            .map(|timing| timing.bitrate)
+            .filter(|&bitrate| bitrate != 0)
+            .ok_or(CanConfigError::NoBitrate)?;
+        Ok(self.set_data_bitrate(bitrate, sample_point)?)
+    }
+
     /// Gets the data bit timing const params for the interface
     pub fn data_bit_timing_const(&self) -> Result<Option<CanBitTimingConst>, NlInfoError> {
         self.can_param::<CanBitTimingConst>(IflaCan::DataBitTimingConst)
@@ -899,6 +1341,88 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn config_error() {
+        use neli::err::{Nlmsgerr, NlmsghdrErr};
+
+        let nl_err = |errno: c_int| {
+            NlError::Nlmsgerr(Nlmsgerr {
+                error: -errno,
+                nlmsg: NlmsghdrErr {
+                    nl_len: 0,
+                    nl_type: 0,
+                    nl_flags: NlmFFlags::new(&[]),
+                    nl_seq: 0,
+                    nl_pid: 0,
+                    nl_payload: Buffer::new(),
+                },
+            })
+        };
+
+        assert!(matches!(
+            CanConfigError::from(nl_err(libc::EBUSY)),
+            CanConfigError::Busy
+        ));
+        assert!(matches!(
+            CanConfigError::from(nl_err(libc::EPERM)),
+            CanConfigError::PermissionDenied
+        ));
+        assert!(matches!(
+            CanConfigError::from(nl_err(libc::EINVAL)),
+            CanConfigError::InvalidParams
+        ));
+        assert!(matches!(
+            CanConfigError::from(nl_err(libc::EOPNOTSUPP)),
+            CanConfigError::Unsupported
+        ));
+        assert!(matches!(
+            CanConfigError::from(nl_err(libc::ENOMEM)),
+            CanConfigError::Netlink(_)
+        ));
+        assert!(matches!(
+            CanConfigError::from(NlError::<u16, Buffer>::NoAck),
+            CanConfigError::Netlink(NlError::NoAck)
+        ));
+    }
+
+    #[test]
+    fn state_to_bus_state() {
+        assert_eq!(
+            Some(BusState::ErrorActive),
+            CanState::ErrorActive.bus_state()
+        );
+        assert_eq!(
+            Some(BusState::ErrorPassive),
+            CanState::ErrorPassive.bus_state()
+        );
+        assert_eq!(Some(BusState::BusOff), CanState::BusOff.bus_state());
+        assert_eq!(None, CanState::Stopped.bus_state());
+    }
+
+    #[test]
+    #[serial]
+    fn vcan_state() {
+        let interface = TemporaryInterface::new("vcan_state").unwrap();
+
+        // A virtual interface has no controller state or counters
+        assert!(interface.state().unwrap().is_none());
+        assert!(interface.error_counters().unwrap().is_none());
+    }
+
+    #[test]
+    fn interface_index() {
+        assert_eq!(42, CanInterface::open_iface(42).index());
+    }
+
+    #[test]
+    fn sample_point_fraction() {
+        assert_eq!(Some(750), sample_point_from_fraction(0.75));
+        assert_eq!(Some(875), sample_point_from_fraction(0.875));
+        assert_eq!(None, sample_point_from_fraction(0.0));
+        assert_eq!(None, sample_point_from_fraction(1.0));
+        assert_eq!(None, sample_point_from_fraction(f32::NAN));
+    }
+
     #[test]
     #[serial]
     fn up_down() {
@@ -919,6 +1443,51 @@ pub mod tests {
         assert_eq!("info", details.name.unwrap());
         assert!(details.mtu.is_some());
         assert!(!details.is_up);
+
+        // A virtual interface has no bit timing
+        assert!(details.can.bitrate().is_none());
+        assert!(details.can.data_bitrate().is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn state_watcher() {
+        let interface = TemporaryInterface::new("state_watch").unwrap();
+        let iface = CanInterface::open_iface(interface.if_index);
+        let mut watcher = StateWatcher::new(iface, std::time::Duration::from_millis(10));
+
+        // A virtual interface has no state, so it never changes
+        assert!(watcher.poll().unwrap().is_none());
+        assert!(watcher.poll().unwrap().is_none());
+        assert!(watcher.state().is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn link_monitor() {
+        let mut mon = CanLinkMonitor::new().unwrap();
+
+        let interface = TemporaryInterface::new("link_mon").unwrap();
+        let if_index = interface.if_index;
+
+        let event = mon.events().map(Result::unwrap).next().unwrap();
+        assert_eq!(if_index, event.ifindex);
+        assert_eq!(Some("link_mon"), event.name.as_deref());
+        assert_eq!(Some("vcan"), event.link_kind.as_deref());
+        assert_eq!(LinkEventKind::Changed, event.kind);
+
+        assert!(CanLinkMonitor::query_links()
+            .unwrap()
+            .iter()
+            .any(|ev| ev.ifindex == if_index));
+
+        drop(interface);
+        let event = mon
+            .events()
+            .map(Result::unwrap)
+            .find(|ev| ev.kind == LinkEventKind::Removed)
+            .unwrap();
+        assert_eq!(if_index, event.ifindex);
     }
 
     #[test]
@@ -932,4 +1501,47 @@ pub mod tests {
         assert!(interface.set_mtu(Mtu::Standard).is_ok());
         assert_eq!(Mtu::Standard, interface.details().unwrap().mtu.unwrap());
     }
+
+    #[test]
+    #[serial]
+    fn sysfs_stats() {
+        let interface = TemporaryInterface::new("sysfs_stats").unwrap();
+
+        let stats = interface.sysfs_stats().unwrap();
+        assert!(stats.rx_packets.is_some());
+        assert!(stats.rx_dropped.is_some());
+        assert_eq!(stats, SysfsStats::read("sysfs_stats"));
+    }
+
+    #[test]
+    #[serial]
+    fn sample_point() {
+        let interface = TemporaryInterface::new("sample_point").unwrap();
+
+        // A virtual interface has no bit timing
+        assert!(interface.bit_timing().unwrap().is_none());
+        assert!(interface.sample_point().unwrap().is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn supports_fd() {
+        let interface = TemporaryInterface::new("supports_fd").unwrap();
+
+        assert!(interface.set_mtu(Mtu::Standard).is_ok());
+        assert!(!interface.supports_fd().unwrap());
+
+        assert!(interface.set_mtu(Mtu::Fd).is_ok());
+        assert!(interface.supports_fd().unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn link_stats() {
+        let interface = TemporaryInterface::new("link_stats").unwrap();
+
+        let stats = interface.link_stats().unwrap().unwrap();
+        assert_eq!(0, stats.tx_packets);
+        assert_eq!(LinkStats::default(), stats.since(&stats));
+    }
 }
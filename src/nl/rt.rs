@@ -200,6 +200,56 @@ pub struct can_device_stats {
     pub restarts: u32,         // CAN controller re-starts
 }
 
+///
+/// Generic network interface statistics
+/// Missing from libc, from linux/if_link.h:
+///
+/// The kernel has appended fields to this struct over time, so it is read
+/// from however many bytes the kernel sends, with any missing fields left
+/// as zero.
+///
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rtnl_link_stats64 {
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+    pub multicast: u64,
+    pub collisions: u64,
+    // detailed rx_errors
+    pub rx_length_errors: u64,
+    pub rx_over_errors: u64,
+    pub rx_crc_errors: u64,
+    pub rx_frame_errors: u64,
+    pub rx_fifo_errors: u64,
+    pub rx_missed_errors: u64,
+    // detailed tx_errors
+    pub tx_aborted_errors: u64,
+    pub tx_carrier_errors: u64,
+    pub tx_fifo_errors: u64,
+    pub tx_heartbeat_errors: u64,
+    pub tx_window_errors: u64,
+    // for cslip etc
+    pub rx_compressed: u64,
+    pub tx_compressed: u64,
+    pub rx_nohandler: u64,
+}
+
+impl<'a> FromBytes<'a> for rtnl_link_stats64 {
+    fn from_bytes(buf: &mut Cursor<&'a [u8]>) -> Result<Self, DeError> {
+        let mut stats = rtnl_link_stats64::default();
+        match buf.read(as_bytes_mut(&mut stats))? {
+            0 => Err(DeError::UnexpectedEOB),
+            _ => Ok(stats),
+        }
+    }
+}
+
 pub const IFLA_CAN_UNSPEC: u16 = 0;
 pub const IFLA_CAN_BITTIMING: u16 = 1;
 pub const IFLA_CAN_BITTIMING_CONST: u16 = 2;
@@ -0,0 +1,414 @@
+// socketcan/src/nl/watch.rs
+//
+// Watching a CAN interface for changes in its state.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Watching a CAN interface for changes in its state.
+//!
+//! The state of the CAN controller (error active, passive, bus-off, etc)
+//! is reported by the kernel through netlink. Polling it is an independent
+//! source of truth from the error frames, and works even when the error
+//! frames are not enabled on any socket.
+//!
+//! The kernel also pushes notifications when network interfaces are added,
+//! removed, or brought up or down. These can be received with a
+//! [`CanLinkMonitor`], to react immediately when a USB-CAN adapter is
+//! plugged in, or a link goes down.
+//!
+
+use super::{CanInterface, CanState, NlInfoError};
+use neli::{
+    consts::{
+        nl::{NlmF, NlmFFlags},
+        rtnl::{Arphrd, Iff, IffFlags, Ifla, IflaInfo, RtAddrFamily, Rtm},
+        socket::NlFamily,
+    },
+    err::{NlError, WrappedError},
+    nl::{NlPayload, Nlmsghdr},
+    rtnl::Ifinfomsg,
+    socket::NlSocketHandle,
+    types::RtBuffer,
+};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    ffi::CStr,
+    fmt,
+    os::unix::io::{AsRawFd, RawFd},
+    thread,
+    time::Duration,
+};
+
+// ===== StateWatcher =====
+
+/// A change in the state of a CAN interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateChange {
+    /// The previous state, if it was known
+    pub old: Option<CanState>,
+    /// The new state, if it is known
+    pub new: Option<CanState>,
+}
+
+/// The callback for a state change.
+///
+/// It returns `true` to keep watching, or `false` to stop.
+type StateCallback = Box<dyn FnMut(StateChange) -> bool + Send>;
+
+/// Periodically polls the state of a CAN interface, and reports when it
+/// changes.
+///
+/// ```no_run
+/// use socketcan::{nl::StateWatcher, CanInterface};
+/// use std::time::Duration;
+///
+/// let iface = CanInterface::open("can0").unwrap();
+/// StateWatcher::new(iface, Duration::from_millis(500))
+///     .on_change(|chg| {
+///         println!("{:?} -> {:?}", chg.old, chg.new);
+///         true
+///     })
+///     .run()
+///     .unwrap();
+/// ```
+///
+/// Each poll is a netlink request and response with the kernel, which
+/// costs a few system calls and the parsing of the full set of interface
+/// attributes. That is negligible at intervals of tens of milliseconds or
+/// more, but a very short interval can use a noticeable amount of CPU. It
+/// also means a state that lasts less than the interval can be missed.
+pub struct StateWatcher {
+    iface: CanInterface,
+    interval: Duration,
+    last: Option<CanState>,
+    first: bool,
+    callback: Option<StateCallback>,
+}
+
+impl StateWatcher {
+    /// Creates a watcher that polls the interface at the specified
+    /// interval.
+    pub fn new(iface: CanInterface, interval: Duration) -> Self {
+        Self {
+            iface,
+            interval,
+            last: None,
+            first: true,
+            callback: None,
+        }
+    }
+
+    /// Sets the function that is called when the state changes.
+    ///
+    /// The function returns `true` to keep watching, or `false` to make
+    /// [`run`](StateWatcher::run) return.
+    pub fn on_change<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(StateChange) -> bool + Send + 'static,
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Gets the interval between polls.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Gets the last state that was read.
+    pub fn state(&self) -> Option<CanState> {
+        self.last
+    }
+
+    /// Polls the interface once.
+    ///
+    /// This returns the change if the state differs from the last time it
+    /// was polled. The first poll only establishes the initial state, and
+    /// never reports a change. This is for applications that run their own
+    /// loop, and it does not call the callback.
+    pub fn poll(&mut self) -> Result<Option<StateChange>, NlInfoError> {
+        let state = self.iface.state()?;
+        let change = StateChange {
+            old: self.last,
+            new: state,
+        };
+        self.last = state;
+
+        if std::mem::take(&mut self.first) || change.old == change.new {
+            Ok(None)
+        } else {
+            Ok(Some(change))
+        }
+    }
+
+    /// Polls the interface in a loop, calling the callback on each change.
+    ///
+    /// This blocks the calling thread until the callback returns `false`,
+    /// or an error occurs querying the interface.
+    pub fn run(mut self) -> Result<(), NlInfoError> {
+        loop {
+            if let Some(change) = self.poll()? {
+                if let Some(callback) = self.callback.as_mut() {
+                    if !callback(change) {
+                        return Ok(());
+                    }
+                }
+            }
+            thread::sleep(self.interval);
+        }
+    }
+}
+
+impl fmt::Debug for StateWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateWatcher")
+            .field("iface", &self.iface)
+            .field("interval", &self.interval)
+            .field("last", &self.last)
+            .finish()
+    }
+}
+
+// ===== CanLinkMonitor =====
+
+/// The type of change reported in a [`LinkEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkEventKind {
+    /// An interface was added, or its settings or status changed
+    Changed,
+    /// An interface was removed
+    Removed,
+}
+
+/// A notification about a CAN network interface from a [`CanLinkMonitor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkEvent {
+    /// The index of the interface
+    pub ifindex: u32,
+    /// The name of the interface, if reported
+    pub name: Option<String>,
+    /// Whether the interface is up
+    pub up: bool,
+    /// The type of link, like "can" or "vcan", if reported
+    pub link_kind: Option<String>,
+    /// What happened to the interface
+    pub kind: LinkEventKind,
+}
+
+impl LinkEvent {
+    // Parses a link notification, returning `None` if it's not for a CAN
+    // interface.
+    fn from_msg(msg: &Nlmsghdr<Rtm, Ifinfomsg>) -> Option<Self> {
+        let kind = match msg.nl_type {
+            Rtm::Newlink => LinkEventKind::Changed,
+            Rtm::Dellink => LinkEventKind::Removed,
+            _ => return None,
+        };
+
+        let payload = msg.get_payload().ok()?;
+        if u16::from(payload.ifi_type) != libc::ARPHRD_CAN {
+            return None;
+        }
+
+        let mut event = LinkEvent {
+            ifindex: payload.ifi_index as u32,
+            name: None,
+            up: payload.ifi_flags.contains(&Iff::Up),
+            link_kind: None,
+            kind,
+        };
+
+        for attr in payload.rtattrs.iter() {
+            match attr.rta_type {
+                Ifla::Ifname => {
+                    event.name = CStr::from_bytes_with_nul(attr.rta_payload.as_ref())
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .ok();
+                }
+                Ifla::Linkinfo => {
+                    if let Ok(handle) = attr.get_attr_handle::<IflaInfo>() {
+                        event.link_kind = handle
+                            .get_attrs()
+                            .iter()
+                            .find(|info| info.rta_type == IflaInfo::Kind)
+                            .and_then(|info| {
+                                CStr::from_bytes_with_nul(info.rta_payload.as_ref()).ok()
+                            })
+                            .map(|s| s.to_string_lossy().into_owned());
+                    }
+                }
+                _ => (),
+            }
+        }
+        Some(event)
+    }
+}
+
+/// Receives notifications from the kernel about CAN network interfaces.
+///
+/// This subscribes to the rtnetlink link notifications, and reports
+/// when CAN interfaces are added or removed, or are brought up or down.
+/// Notifications for other types of interfaces are ignored. Note that the
+/// kernel sends a notification for other changes to an interface as well,
+/// so an event does not necessarily mean that the up/down status changed.
+///
+/// ```no_run
+/// use socketcan::nl::CanLinkMonitor;
+///
+/// let mut mon = CanLinkMonitor::new().unwrap();
+/// for event in mon.events() {
+///     let event = event.unwrap();
+///     println!("{:?}: up: {}", event.name, event.up);
+/// }
+/// ```
+///
+/// The reads block until the next event. The monitor can be used with an
+/// async runtime or event loop by polling its file descriptor for input.
+///
+/// If the application doesn't keep up with the notifications, the kernel
+/// socket buffer can overflow, losing some of them (an `ENOBUFS` error).
+/// When that happens, the monitor queries the kernel for the current set
+/// of CAN interfaces, and reports a `Removed` event for each one it knew
+/// about that has gone, and a `Changed` event for each one that exists,
+/// so that the application's view is brought back up to date.
+pub struct CanLinkMonitor {
+    sock: NlSocketHandle,
+    known: BTreeSet<u32>,
+    pending: VecDeque<LinkEvent>,
+}
+
+impl CanLinkMonitor {
+    /// Creates a monitor subscribed to the link notifications.
+    pub fn new() -> Result<Self, NlInfoError> {
+        // Let the kernel assign the port ID, so that this doesn't collide
+        // with any other netlink sockets in the process.
+        let sock = NlSocketHandle::connect(NlFamily::Route, None, &[libc::RTNLGRP_LINK])?;
+        let known = Self::query_links()?.iter().map(|ev| ev.ifindex).collect();
+
+        Ok(Self {
+            sock,
+            known,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Gets the current set of CAN interfaces from the kernel.
+    ///
+    /// Each interface is reported as a `Changed` event.
+    pub fn query_links() -> Result<Vec<LinkEvent>, NlInfoError> {
+        let mut sock = NlSocketHandle::connect(NlFamily::Route, None, &[])?;
+
+        let info = Ifinfomsg::new(
+            RtAddrFamily::Unspecified,
+            Arphrd::Netrom,
+            0,
+            IffFlags::empty(),
+            IffFlags::empty(),
+            RtBuffer::new(),
+        );
+        let hdr = Nlmsghdr::new(
+            None,
+            Rtm::Getlink,
+            NlmFFlags::new(&[NlmF::Request, NlmF::Dump]),
+            None,
+            None,
+            NlPayload::Payload(info),
+        );
+        sock.send(hdr)?;
+
+        let mut links = Vec::new();
+        for msg in sock.iter::<Rtm, Ifinfomsg>(false) {
+            if let Some(event) = LinkEvent::from_msg(&msg?) {
+                links.push(event);
+            }
+        }
+        Ok(links)
+    }
+
+    /// Blocks until the next event for a CAN interface.
+    pub fn next_event(&mut self) -> Result<LinkEvent, NlInfoError> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            match self.sock.recv::<Rtm, Ifinfomsg>() {
+                Ok(Some(msg)) => {
+                    if let Some(event) = LinkEvent::from_msg(&msg) {
+                        match event.kind {
+                            LinkEventKind::Changed => self.known.insert(event.ifindex),
+                            LinkEventKind::Removed => self.known.remove(&event.ifindex),
+                        };
+                        return Ok(event);
+                    }
+                }
+                Ok(None) => return Err(NlError::new("The netlink socket was closed")),
+                Err(NlError::Wrapped(WrappedError::IOError(err)))
+                    if err.raw_os_error() == Some(libc::ENOBUFS) =>
+                {
+                    self.resync()?
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Gets an iterator over the events.
+    ///
+    /// The iterator never ends, and blocks waiting for each event.
+    pub fn events(&mut self) -> LinkEvents<'_> {
+        LinkEvents(self)
+    }
+
+    // Brings our view of the CAN interfaces up to date, after losing some
+    // notifications, and queues up the differences as events.
+    fn resync(&mut self) -> Result<(), NlInfoError> {
+        let links = Self::query_links()?;
+        let current: BTreeSet<u32> = links.iter().map(|ev| ev.ifindex).collect();
+
+        for &ifindex in self.known.difference(&current) {
+            self.pending.push_back(LinkEvent {
+                ifindex,
+                name: None,
+                up: false,
+                link_kind: None,
+                kind: LinkEventKind::Removed,
+            });
+        }
+        self.pending.extend(links);
+        self.known = current;
+        Ok(())
+    }
+}
+
+impl AsRawFd for CanLinkMonitor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for CanLinkMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CanLinkMonitor")
+            .field("fd", &self.sock.as_raw_fd())
+            .field("known", &self.known)
+            .finish()
+    }
+}
+
+/// A blocking iterator over the events from a [`CanLinkMonitor`].
+#[derive(Debug)]
+pub struct LinkEvents<'a>(&'a mut CanLinkMonitor);
+
+impl Iterator for LinkEvents<'_> {
+    type Item = Result<LinkEvent, NlInfoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.0.next_event())
+    }
+}
@@ -0,0 +1,281 @@
+// socketcan/src/pipeline.rs
+//
+// A chain of transforms applied to frames, such as in a gateway.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! A chain of transforms applied to frames, such as in a gateway.
+//!
+//! A [`Pipeline`] is built from a list of stages, each of which implements
+//! [`FrameTransform`]. A frame is passed through the stages in order, and
+//! each can modify the frame, or drop it. Once a stage drops a frame, the
+//! remaining stages are skipped.
+//!
+//! Any closure taking and returning a frame can be used as a stage, and a
+//! few of the helpers in this crate implement the trait as well:
+//!
+//! - [`IdRemap`] changes the IDs of frames
+//! - [`ChangeFilter`] drops a frame if its data is the same as the last
+//!   one with the same ID
+//! - [`SamplingFilter`] limits the rate of frames for each ID
+//! - [`ContentFilter`] passes only the frames with matching data bytes
+//!
+//! ```
+//! use socketcan::{
+//!     pipeline::IdRemap, CanFrame, EmbeddedFrame, Id, Pipeline, StandardId,
+//! };
+//!
+//! let id = |n| Id::Standard(StandardId::new(n).unwrap());
+//!
+//! let mut pipeline = Pipeline::new()
+//!     .stage(|frame: CanFrame| if frame.dlc() > 0 { Some(frame) } else { None })
+//!     .stage(IdRemap::new().map(id(0x100), id(0x200)));
+//!
+//! let frame = CanFrame::new(id(0x100), &[1, 2, 3]).unwrap();
+//! assert_eq!(id(0x200), pipeline.apply(frame).unwrap().id());
+//!
+//! let frame = CanFrame::new(id(0x100), &[]).unwrap();
+//! assert!(pipeline.apply(frame).is_none());
+//! ```
+
+use crate::{
+    monitor::{ChangeFilter, ContentFilter, SamplingFilter},
+    CanFrame, EmbeddedFrame, Frame, Id,
+};
+use std::{collections::BTreeMap, fmt, time::Instant};
+
+// ===== FrameTransform =====
+
+/// A single stage in a [`Pipeline`].
+pub trait FrameTransform {
+    /// Applies the transform to the frame.
+    ///
+    /// This returns the frame, possibly modified, to pass it on to the
+    /// next stage, or `None` to drop it.
+    fn apply(&mut self, frame: CanFrame) -> Option<CanFrame>;
+}
+
+impl<T> FrameTransform for T
+where
+    T: FnMut(CanFrame) -> Option<CanFrame>,
+{
+    fn apply(&mut self, frame: CanFrame) -> Option<CanFrame> {
+        self(frame)
+    }
+}
+
+impl FrameTransform for ChangeFilter {
+    /// Passes the frame if its data changed since the last one passed
+    /// with the same ID.
+    fn apply(&mut self, frame: CanFrame) -> Option<CanFrame> {
+        if self.check(&frame) {
+            Some(frame)
+        } else {
+            None
+        }
+    }
+}
+
+impl FrameTransform for SamplingFilter {
+    /// Passes the frame if the interval has passed since the last one
+    /// with the same ID.
+    ///
+    /// This uses the current time as the time that the frame was received.
+    fn apply(&mut self, frame: CanFrame) -> Option<CanFrame> {
+        if self.should_pass(&frame, Instant::now()) {
+            Some(frame)
+        } else {
+            None
+        }
+    }
+}
+
+impl FrameTransform for ContentFilter {
+    /// Passes the frame if its data matches the filter.
+    fn apply(&mut self, frame: CanFrame) -> Option<CanFrame> {
+        if self.matches(&frame) {
+            Some(frame)
+        } else {
+            None
+        }
+    }
+}
+
+// ===== Pipeline =====
+
+/// An ordered chain of frame transforms.
+///
+/// A pipeline is itself a [`FrameTransform`], so pipelines can be nested.
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn FrameTransform>>,
+}
+
+impl Pipeline {
+    /// Creates a new pipeline with no stages.
+    ///
+    /// An empty pipeline passes all frames unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a stage to the end of the pipeline.
+    pub fn stage<T>(mut self, stage: T) -> Self
+    where
+        T: FrameTransform + 'static,
+    {
+        self.push(Box::new(stage));
+        self
+    }
+
+    /// Adds a boxed stage to the end of the pipeline.
+    pub fn push(&mut self, stage: Box<dyn FrameTransform>) {
+        self.stages.push(stage);
+    }
+
+    /// Gets the number of stages in the pipeline.
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Determines if the pipeline has no stages.
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Passes the frame through each of the stages, in order.
+    ///
+    /// This returns the frame from the last stage, or `None` as soon as
+    /// any stage drops it.
+    pub fn apply(&mut self, frame: CanFrame) -> Option<CanFrame> {
+        self.stages
+            .iter_mut()
+            .try_fold(frame, |frame, stage| stage.apply(frame))
+    }
+}
+
+impl FrameTransform for Pipeline {
+    fn apply(&mut self, frame: CanFrame) -> Option<CanFrame> {
+        Pipeline::apply(self, frame)
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("stages", &self.stages.len())
+            .finish()
+    }
+}
+
+// ===== IdRemap =====
+
+/// A pipeline stage that changes the IDs of frames.
+///
+/// Data and remote frames with an ID in the map are given the new ID.
+/// All other frames, including error frames, pass through unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct IdRemap {
+    ids: BTreeMap<Id, Id>,
+}
+
+impl IdRemap {
+    /// Creates a new remap stage, without any IDs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a mapping from one ID to another.
+    pub fn map(mut self, from: impl Into<Id>, to: impl Into<Id>) -> Self {
+        self.ids.insert(from.into(), to.into());
+        self
+    }
+
+    /// Gets the new ID for the specified one, if it is mapped.
+    pub fn get(&self, id: Id) -> Option<Id> {
+        self.ids.get(&id).copied()
+    }
+}
+
+impl FrameTransform for IdRemap {
+    fn apply(&mut self, mut frame: CanFrame) -> Option<CanFrame> {
+        if !matches!(frame, CanFrame::Error(_)) {
+            if let Some(id) = self.get(frame.id()) {
+                frame.set_id(id);
+            }
+        }
+        Some(frame)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CanErrorFrame, StandardId};
+    use std::time::Duration;
+
+    fn id(n: u16) -> Id {
+        Id::Standard(StandardId::new(n).unwrap())
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let mut pipeline = Pipeline::new();
+        assert!(pipeline.is_empty());
+
+        let frame = CanFrame::new(id(0x100), &[1]).unwrap();
+        assert_eq!(id(0x100), pipeline.apply(frame).unwrap().id());
+
+        let mut pipeline = pipeline
+            .stage(IdRemap::new().map(id(0x100), id(0x101)))
+            .stage(ChangeFilter::new())
+            .stage(|mut frame: CanFrame| {
+                frame.set_data(&[0xFF]).unwrap();
+                Some(frame)
+            });
+        assert_eq!(3, pipeline.len());
+
+        let out = pipeline.apply(frame).unwrap();
+        assert_eq!(id(0x101), out.id());
+        assert_eq!(&[0xFF], out.data());
+
+        // Unchanged, so dropped by the filter
+        assert!(pipeline.apply(frame).is_none());
+
+        let frame = CanFrame::new(id(0x100), &[2]).unwrap();
+        assert!(pipeline.apply(frame).is_some());
+    }
+
+    #[test]
+    fn test_remap() {
+        let mut remap = IdRemap::new().map(id(0x010), id(0x020));
+
+        let frame = CanFrame::new(id(0x030), &[1]).unwrap();
+        assert_eq!(id(0x030), remap.apply(frame).unwrap().id());
+
+        let frame = CanFrame::new_remote(id(0x010), 2).unwrap();
+        let out = remap.apply(frame).unwrap();
+        assert!(out.is_remote_frame());
+        assert_eq!(id(0x020), out.id());
+
+        // Error frames are not remapped
+        let frame = CanFrame::from(CanErrorFrame::new_error(0x010, &[]).unwrap());
+        assert_eq!(frame.id_word(), remap.apply(frame).unwrap().id_word());
+    }
+
+    #[test]
+    fn test_sampling_stage() {
+        let mut pipeline = Pipeline::new().stage(SamplingFilter::new(Duration::from_secs(60)));
+
+        let frame = CanFrame::new(id(0x100), &[1]).unwrap();
+        assert!(pipeline.apply(frame).is_some());
+        assert!(pipeline.apply(frame).is_none());
+    }
+}
@@ -0,0 +1,211 @@
+// socketcan/src/ring.rs
+//
+// A fixed-size ring buffer for received CAN frames.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! A fixed-size ring buffer for received CAN frames.
+//!
+//! This is meant for real-time applications, like control loops, in which
+//! one thread or task receives frames and another consumes them at its own
+//! pace. The buffer never grows, and when the consumer falls behind, it
+//! favors the most recent data over the old.
+//!
+//! Like the helpers in the [`monitor`](crate::monitor) module, the buffer
+//! does not perform any I/O itself, and each frame is stored with the time
+//! it was received.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// A fixed-size buffer of received frames.
+///
+/// When the buffer is full, pushing a new frame drops the oldest one, and
+/// counts it as an overflow.
+///
+/// Optionally, a maximum age can be set for the frames, in which case
+/// [`pop`](FrameRing::pop) discards any frames that were received longer
+/// ago than the age, and counts them as expired. This implements a "latest
+/// data wins" policy, so a frame is never consumed late.
+///
+/// Each dropped frame is counted only once. A frame that is overwritten
+/// while the buffer is full counts as an overflow, even if it was already
+/// stale, since it was never looked at by `pop()`. So the overflow count
+/// shows how often the buffer was too small, and the expired count shows
+/// how often the consumer was too slow for the data that did fit.
+#[derive(Debug, Clone)]
+pub struct FrameRing<F> {
+    buf: VecDeque<(F, Instant)>,
+    capacity: usize,
+    max_age: Option<Duration>,
+    overflows: u64,
+    expired: u64,
+}
+
+impl<F> FrameRing<F> {
+    /// Creates a buffer that holds up to `capacity` frames.
+    ///
+    /// The capacity is at least one frame.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            buf: VecDeque::with_capacity(capacity),
+            capacity,
+            max_age: None,
+            overflows: 0,
+            expired: 0,
+        }
+    }
+
+    /// Sets the maximum age of the frames returned by `pop()`.
+    ///
+    /// The age of a frame is measured from the time it was pushed into the
+    /// buffer.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Gets the maximum number of frames the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Gets the number of frames in the buffer.
+    ///
+    /// This includes any stale frames that have not yet been discarded.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Determines if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Gets the number of frames that were dropped because the buffer was
+    /// full.
+    pub fn overflows(&self) -> u64 {
+        self.overflows
+    }
+
+    /// Gets the number of frames that were discarded for being older than
+    /// the maximum age.
+    pub fn expired(&self) -> u64 {
+        self.expired
+    }
+
+    /// Pushes a frame that was just received into the buffer.
+    ///
+    /// This returns `true` if the oldest frame had to be dropped to make
+    /// room for it.
+    pub fn push(&mut self, frame: F) -> bool {
+        self.push_at(frame, Instant::now())
+    }
+
+    /// Pushes a frame that was received at the specified time into the
+    /// buffer.
+    ///
+    /// This returns `true` if the oldest frame had to be dropped to make
+    /// room for it.
+    pub fn push_at(&mut self, frame: F, at: Instant) -> bool {
+        let overflow = self.buf.len() >= self.capacity;
+        if overflow {
+            self.buf.pop_front();
+            self.overflows += 1;
+        }
+        self.buf.push_back((frame, at));
+        overflow
+    }
+
+    /// Removes the oldest fresh frame from the buffer.
+    ///
+    /// Any frames older than the maximum age are discarded first.
+    pub fn pop(&mut self) -> Option<F> {
+        self.pop_at(Instant::now())
+    }
+
+    /// Removes the oldest frame that is still fresh at the time `now`.
+    ///
+    /// Any frames older than the maximum age are discarded first.
+    pub fn pop_at(&mut self, now: Instant) -> Option<F> {
+        self.pop_with_time_at(now).map(|(frame, _)| frame)
+    }
+
+    /// Removes the oldest frame that is still fresh at the time `now`,
+    /// along with the time it was received.
+    pub fn pop_with_time_at(&mut self, now: Instant) -> Option<(F, Instant)> {
+        while let Some((frame, at)) = self.buf.pop_front() {
+            match self.max_age {
+                Some(max_age) if now.saturating_duration_since(at) > max_age => self.expired += 1,
+                _ => return Some((frame, at)),
+            }
+        }
+        None
+    }
+
+    /// Removes all the frames from the buffer.
+    ///
+    /// The frames are not counted as expired or overflows.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    /// Resets the overflow and expired counters to zero.
+    pub fn reset_counters(&mut self) {
+        self.overflows = 0;
+        self.expired = 0;
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_overflow() {
+        let t0 = Instant::now();
+        let mut ring = FrameRing::new(2);
+
+        assert!(!ring.push_at(1, t0));
+        assert!(!ring.push_at(2, t0));
+        assert!(ring.push_at(3, t0));
+        assert_eq!(1, ring.overflows());
+
+        assert_eq!(Some(2), ring.pop_at(t0));
+        assert_eq!(Some(3), ring.pop_at(t0));
+        assert_eq!(None, ring.pop_at(t0));
+        assert_eq!(0, ring.expired());
+    }
+
+    #[test]
+    fn test_max_age() {
+        let t0 = Instant::now();
+        let mut ring = FrameRing::new(8).max_age(10 * MS);
+
+        ring.push_at(1, t0);
+        ring.push_at(2, t0 + 5 * MS);
+        ring.push_at(3, t0 + 20 * MS);
+
+        // Frame 1 is stale, but 2 is just in time
+        assert_eq!(Some((2, t0 + 5 * MS)), ring.pop_with_time_at(t0 + 15 * MS));
+        assert_eq!(1, ring.expired());
+
+        // Frame 3 is stale by now
+        assert_eq!(None, ring.pop_at(t0 + 31 * MS));
+        assert_eq!(2, ring.expired());
+        assert!(ring.is_empty());
+        assert_eq!(0, ring.overflows());
+    }
+}
@@ -0,0 +1,201 @@
+// socketcan/src/schedule.rs
+//
+// Sending frames from a timetable.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Sending frames from a timetable.
+//!
+//! A [`Schedule`] is a list of frames, each to be sent at an offset from a
+//! start time. It is like a programmable version of the `canplayer`
+//! utility from [can-utils](https://github.com/linux-can/can-utils), and
+//! is useful for hardware-in-the-loop testing, or to replay a recorded
+//! sequence of traffic.
+
+use crate::{frame::AsPtr, CanFrame, IoError, IoErrorKind, IoResult, Socket};
+use std::{
+    iter::FromIterator,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A single entry in a [`Schedule`].
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduleEntry<F> {
+    /// The offset from the start time at which to send the frame
+    pub offset: Duration,
+    /// The frame to send
+    pub frame: F,
+}
+
+/// A timetable of frames to send, each at an offset from a start time.
+///
+/// The entries are kept in order of their offsets. Entries with the same
+/// offset are sent in the order they were added.
+///
+/// Timing uses the monotonic clock, and each frame is sent after sleeping
+/// until its scheduled time. The accuracy is therefore that of the
+/// operating system scheduler: typically tens of microseconds to a
+/// millisecond or so on a loaded, non-realtime system. The errors don't
+/// accumulate, since each entry is timed from the start, not from the
+/// previous entry. The time is that at which the frame is written to the
+/// socket, which may differ from when it reaches the bus if the transmit
+/// queue is not empty.
+#[derive(Debug, Clone)]
+pub struct Schedule<F = CanFrame> {
+    entries: Vec<ScheduleEntry<F>>,
+}
+
+impl<F> Schedule<F> {
+    /// Creates an empty schedule.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a frame to send at the `offset` from the start time.
+    pub fn push(&mut self, offset: Duration, frame: F) {
+        let idx = self.entries.partition_point(|ent| ent.offset <= offset);
+        self.entries.insert(idx, ScheduleEntry { offset, frame });
+    }
+
+    /// Adds a frame to send at the `offset` from the start time, returning
+    /// the updated schedule.
+    pub fn with(mut self, offset: Duration, frame: F) -> Self {
+        self.push(offset, frame);
+        self
+    }
+
+    /// Gets the number of entries in the schedule.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Determines if the schedule is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Gets the entries, in the order they will be sent.
+    pub fn entries(&self) -> &[ScheduleEntry<F>] {
+        &self.entries
+    }
+
+    /// Gets the offset of the last entry, which is how long the schedule
+    /// takes to run.
+    pub fn duration(&self) -> Duration {
+        self.entries
+            .last()
+            .map(|ent| ent.offset)
+            .unwrap_or_default()
+    }
+
+    /// Sends the frames on the socket, each at its offset from the `start`
+    /// time.
+    ///
+    /// This blocks the calling thread until the last frame is sent. Any
+    /// entries whose time has already passed, such as when the start time
+    /// is in the past, or the socket blocks on a full transmit queue, are
+    /// sent immediately, in order.
+    ///
+    /// If the time of any entry is too far from the start to be
+    /// represented, this returns an `InvalidInput` error before anything is
+    /// sent.
+    pub fn run<S>(&self, sock: &S, start: Instant) -> IoResult<()>
+    where
+        S: Socket,
+        F: Into<S::FrameType> + AsPtr,
+    {
+        // The entries are sorted, so if the last one is in range, they all are.
+        if start.checked_add(self.duration()).is_none() {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "schedule offset out of range",
+            ));
+        }
+
+        for ent in &self.entries {
+            let when = start + ent.offset;
+            let now = Instant::now();
+            if when > now {
+                thread::sleep(when - now);
+            }
+            sock.write_frame_insist(&ent.frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F> Default for Schedule<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> FromIterator<(Duration, F)> for Schedule<F> {
+    fn from_iter<I: IntoIterator<Item = (Duration, F)>>(iter: I) -> Self {
+        let mut sched = Self::new();
+        for (offset, frame) in iter {
+            sched.push(offset, frame);
+        }
+        sched
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CanSocket, EmbeddedFrame, Frame};
+    use std::os::unix::io::OwnedFd;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_order() {
+        let frame = |id| CanFrame::from_raw_id(id, &[]).unwrap();
+
+        let sched: Schedule = [
+            (ms(20), frame(0x1)),
+            (ms(10), frame(0x2)),
+            (ms(20), frame(0x3)),
+            (ms(0), frame(0x4)),
+        ]
+        .into_iter()
+        .collect();
+
+        let ids: Vec<_> = sched.entries().iter().map(|e| e.frame.raw_id()).collect();
+        assert_eq!(vec![0x4, 0x2, 0x1, 0x3], ids);
+        assert_eq!(ms(20), sched.duration());
+        assert!(sched.entries()[0].frame.data().is_empty());
+    }
+
+    #[test]
+    fn test_offset_overflow() {
+        let (a, b) =
+            socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::DGRAM, None).unwrap();
+        let sock = CanSocket::from(OwnedFd::from(a));
+        b.set_nonblocking(true).unwrap();
+
+        let frame = CanFrame::from_raw_id(0x123, &[]).unwrap();
+        let sched = Schedule::new()
+            .with(ms(0), frame)
+            .with(Duration::MAX, frame);
+
+        let err = sched.run(&sock, Instant::now()).unwrap_err();
+        assert_eq!(IoErrorKind::InvalidInput, err.kind());
+
+        // Nothing was sent
+        let mut buf = [std::mem::MaybeUninit::new(0u8); 16];
+        assert!(b.recv(&mut buf).is_err());
+    }
+}
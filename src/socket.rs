@@ -13,21 +13,28 @@
 
 use crate::{
     as_bytes, as_bytes_mut,
-    frame::{can_frame_default, canfd_frame_default, AsPtr, CAN_ERR_MASK},
-    CanAddr, CanAnyFrame, CanFdFrame, CanFrame, CanRawFrame, IoError, IoErrorKind, IoResult,
+    errors::{is_terminal_io_error, CanErrorFilter},
+    frame::{
+        can_frame_default, canfd_frame_default, id_from_raw, id_to_canid_t, AsPtr, CAN_EFF_FLAG,
+        CAN_EFF_MASK, CAN_ERR_MASK, CAN_SFF_MASK,
+    },
+    monitor::ChangeFilter,
+    CanAddr, CanAnyFrame, CanDataFrame, CanError, CanFdFrame, CanFrame, CanRawFrame,
+    ConstructionError, Error, Id, IoError, IoErrorKind, IoResult, Result,
 };
 use libc::{canid_t, socklen_t, AF_CAN, EINPROGRESS};
 use socket2::SockAddr;
 use std::{
     fmt,
     io::{Read, Write},
-    mem,
+    mem, ops,
     os::{
         raw::{c_int, c_void},
         unix::io::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd, RawFd},
     },
     ptr,
-    time::Duration,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::{Duration, Instant, SystemTime},
 };
 
 pub use libc::{
@@ -35,6 +42,13 @@ pub use libc::{
     CAN_RAW_JOIN_FILTERS, CAN_RAW_LOOPBACK, CAN_RAW_RECV_OWN_MSGS, SOL_CAN_BASE, SOL_CAN_RAW,
 };
 
+/// Socket option to enable transmit times on a socket.
+/// This isn't (yet) exported by libc.
+pub const SO_TXTIME: c_int = 61;
+
+/// Control message type carrying the transmit time of a frame.
+pub const SCM_TXTIME: c_int = SO_TXTIME;
+
 /// Check an error return value for timeouts.
 ///
 /// Due to the fact that timeouts are reported as errors, calling `read_frame`
@@ -75,16 +89,58 @@ impl<E: fmt::Debug> ShouldRetry for IoResult<E> {
 
 // ===== Private local helper functions =====
 
-/// Tries to open the CAN socket by the interface number.
-fn raw_open_socket(addr: &CanAddr) -> IoResult<socket2::Socket> {
+/// Creates a raw CAN socket, which is not yet bound to an interface.
+fn raw_socket() -> IoResult<socket2::Socket> {
     let af_can = socket2::Domain::from(AF_CAN);
     let can_raw = socket2::Protocol::from(CAN_RAW);
 
-    let sock = socket2::Socket::new_raw(af_can, socket2::Type::RAW, Some(can_raw))?;
+    socket2::Socket::new_raw(af_can, socket2::Type::RAW, Some(can_raw))
+}
+
+/// Tries to open the CAN socket by the interface number.
+fn raw_open_socket(addr: &CanAddr) -> IoResult<socket2::Socket> {
+    let sock = raw_socket()?;
     sock.bind(&SockAddr::from(*addr))?;
     Ok(sock)
 }
 
+/// Queries the MTU of the interface to which the socket is bound.
+fn raw_mtu(sock: &socket2::Socket) -> IoResult<usize> {
+    let addr = sock.local_addr()?;
+    if addr.family() != AF_CAN as libc::sa_family_t {
+        return Err(IoErrorKind::InvalidInput.into());
+    }
+
+    let ifindex = unsafe { (*addr.as_ptr().cast::<libc::sockaddr_can>()).can_ifindex };
+    if ifindex == 0 {
+        return Err(IoErrorKind::InvalidInput.into());
+    }
+
+    let mut ifr: libc::ifreq = unsafe { mem::zeroed() };
+    if unsafe { libc::if_indextoname(ifindex as u32, ifr.ifr_name.as_mut_ptr()) }.is_null() {
+        return Err(IoError::last_os_error());
+    }
+
+    if unsafe { libc::ioctl(sock.as_raw_fd(), libc::SIOCGIFMTU as _, &mut ifr) } < 0 {
+        return Err(IoError::last_os_error());
+    }
+    Ok(unsafe { ifr.ifr_ifru.ifru_mtu } as usize)
+}
+
+/// Determines if the frame fits within a cached MTU, where zero means the
+/// MTU is unknown, and the decision is left to the kernel.
+fn fits_mtu<F: AsPtr>(mtu: &AtomicUsize, frame: &F) -> bool {
+    match mtu.load(Ordering::Relaxed) {
+        0 => true,
+        mtu => frame.size() <= mtu,
+    }
+}
+
+/// Converts a kernel timespec, relative to the Unix epoch, into a SystemTime.
+fn system_time_from_timespec(ts: libc::timespec) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
 /// `setsockopt` wrapper
 ///
 /// The libc `setsockopt` function is set to set various options on a socket.
@@ -151,12 +207,96 @@ pub fn set_socket_option_mult<T>(
     }
 }
 
+// ===== ABI info =====
+
+// The CAN XL raw socket option and MTU, from linux/can/raw.h and
+// linux/can.h. These are not in older versions of libc.
+const CAN_RAW_XL_FRAMES: c_int = 7;
+const CANXL_MTU: usize = 2060;
+
+/// Information about the CAN ABI used by the library and the kernel.
+///
+/// This is meant for diagnostics and bug reports, to help catch mismatches
+/// between the frame types the library expects and those supported by the
+/// running kernel. Get it with [`can_abi_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanAbiInfo {
+    /// The size of the `struct can_frame` the library was compiled against.
+    pub can_frame_size: usize,
+    /// The size of the `struct canfd_frame` the library was compiled against.
+    pub canfd_frame_size: usize,
+    /// The largest frame MTU supported by the kernel's raw CAN sockets:
+    /// `CAN_MTU`, `CANFD_MTU`, or the 2060-byte CAN XL MTU.
+    /// This is `None` if the kernel does not support CAN sockets at all.
+    pub kernel_max_mtu: Option<usize>,
+}
+
+/// Gets information about the CAN ABI used by the library and the kernel.
+///
+/// The frame sizes come from the bindings the library was compiled with.
+/// The kernel's maximum MTU is probed at runtime by opening an unbound raw
+/// CAN socket and trying to enable the FD and XL frame options on it. This
+/// does not need any privileges or an existing CAN interface, but does
+/// require the kernel's CAN modules to be available.
+pub fn can_abi_info() -> CanAbiInfo {
+    let kernel_max_mtu = raw_socket().ok().map(|sock| {
+        let enable = |opt| {
+            let val: c_int = 1;
+            let ret = unsafe {
+                libc::setsockopt(
+                    sock.as_raw_fd(),
+                    SOL_CAN_RAW,
+                    opt,
+                    &val as *const _ as *const c_void,
+                    mem::size_of::<c_int>() as socklen_t,
+                )
+            };
+            ret == 0
+        };
+
+        if enable(CAN_RAW_XL_FRAMES) {
+            CANXL_MTU
+        } else if enable(CAN_RAW_FD_FRAMES) {
+            CANFD_MTU
+        } else {
+            CAN_MTU
+        }
+    });
+
+    CanAbiInfo {
+        can_frame_size: mem::size_of::<libc::can_frame>(),
+        canfd_frame_size: mem::size_of::<libc::canfd_frame>(),
+        kernel_max_mtu,
+    }
+}
+
 // ===== Common 'Socket' trait =====
 
 /// Common trait for SocketCAN sockets.
 ///
 /// Note that a socket it created by opening it, and then closed by
 /// dropping it.
+///
+/// # Nonblocking mode and async runtimes
+///
+/// Besides the runtime-specific wrappers in this crate, a socket can be
+/// used with any reactor that can poll a raw file descriptor, such as
+/// `async_io::Async<CanSocket>` for `smol` or `async-std`. The contract
+/// is simple:
+///
+/// - [`AsRawFd`] (and [`AsFd`]) give the
+///   descriptor to register with the reactor, while the socket keeps
+///   ownership of it. [`IntoRawFd`] hands over ownership, after which the
+///   caller is responsible for closing it.
+/// - After [`set_nonblocking(true)`](Socket::set_nonblocking), a
+///   `read_frame` with no frame waiting, or a `write_frame` with a full
+///   transmit queue, returns immediately with an error of kind
+///   [`IoErrorKind::WouldBlock`]. That is the signal for the reactor to
+///   wait for the socket to become readable or writable and try again.
+/// - CAN sockets are datagram sockets, so each read returns one whole
+///   frame and each write sends one whole frame. Partial reads and writes
+///   can not happen, and there is never any buffered state to carry over
+///   between retries.
 pub trait Socket: AsRawFd {
     /// Open a named CAN device.
     ///
@@ -181,6 +321,20 @@ pub trait Socket: AsRawFd {
         Self::open_addr(&addr)
     }
 
+    /// Open a socket on all CAN interfaces.
+    ///
+    /// This binds the socket to interface index zero, so that it receives
+    /// frames from every CAN interface on the system. The interface that
+    /// each frame arrived on is reported by
+    /// [`CanSocket::read_msg`](crate::CanSocket::read_msg). A socket bound
+    /// this way has no interface to send to, so writes to it fail.
+    fn open_all() -> IoResult<Self>
+    where
+        Self: Sized,
+    {
+        Self::open_iface(0)
+    }
+
     /// Open a CAN socket by address.
     fn open_addr(addr: &CanAddr) -> IoResult<Self>
     where
@@ -193,11 +347,20 @@ pub trait Socket: AsRawFd {
     fn as_raw_socket_mut(&mut self) -> &mut socket2::Socket;
 
     /// Determines if the socket is currently in nonblocking mode.
+    ///
+    /// This queries the file status flags of the descriptor from the OS
+    /// with `fcntl(F_GETFL)`, so it reports the actual mode, even for a
+    /// socket that was created from a descriptor that was set up elsewhere.
     fn nonblocking(&self) -> IoResult<bool> {
         self.as_raw_socket().nonblocking()
     }
 
     /// Change socket to non-blocking mode or back to blocking mode.
+    ///
+    /// In nonblocking mode, reads and writes that can't complete right
+    /// away fail with an `IoErrorKind::WouldBlock` error rather than
+    /// waiting. See the [trait documentation](Socket) for using this with
+    /// an async runtime.
     fn set_nonblocking(&self, nonblocking: bool) -> IoResult<()> {
         self.as_raw_socket().set_nonblocking(nonblocking)
     }
@@ -244,19 +407,93 @@ pub trait Socket: AsRawFd {
     }
 
     /// Blocking read a single can frame.
+    ///
+    /// Most errors from a read are transient, like a timeout, and the read
+    /// can be retried. But some mean that the socket can no longer be used,
+    /// and an application reading in a loop should stop:
+    ///
+    /// - `IoErrorKind::UnexpectedEof` if the socket was shut down.
+    /// - `ENODEV` or `ENXIO` if the interface has been removed.
+    ///
+    /// These are reported as [`Error::Closed`](crate::Error::Closed) and
+    /// [`Error::InterfaceNotFound`](crate::Error::InterfaceNotFound) when
+    /// converted to the crate's composite [`Error`](crate::Error) type.
     fn read_frame(&self) -> IoResult<Self::FrameType>;
 
     /// Blocking read a single can frame with timeout.
+    ///
+    /// If no frame arrives in time, this returns an `IoErrorKind::TimedOut`
+    /// error, which is converted to [`Error::TimedOut`] in the crate's
+    /// composite error type. A zero timeout only checks for a frame that is
+    /// already waiting, without blocking.
+    ///
+    /// This waits with `poll`, so it doesn't change the read timeout set
+    /// on the socket with [`set_read_timeout`](Self::set_read_timeout).
     fn read_frame_timeout(&self, timeout: Duration) -> IoResult<Self::FrameType> {
-        use nix::poll::{poll, PollFd, PollFlags};
-        let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self
+                .read_frame_deadline(deadline)?
+                .ok_or_else(|| IoErrorKind::TimedOut.into()),
+            // Too far in the future to be distinguishable from forever, so
+            // wait for a frame in day-long steps.
+            None => loop {
+                let deadline = Instant::now() + Duration::from_secs(24 * 60 * 60);
+                if let Some(frame) = self.read_frame_deadline(deadline)? {
+                    return Ok(frame);
+                }
+            },
+        }
+    }
+
+    /// Blocking read a single can frame, waiting until an absolute deadline.
+    ///
+    /// This returns `Ok(None)` if the deadline passes before a frame is
+    /// received. Unlike a relative timeout, the deadline doesn't need to be
+    /// recomputed when reading in a loop against a shared time budget.
+    ///
+    /// If the deadline has already passed, this still returns a frame that
+    /// is already waiting to be read, without blocking.
+    fn read_frame_deadline(&self, deadline: Instant) -> IoResult<Option<Self::FrameType>> {
+        use nix::{
+            errno::Errno,
+            poll::{ppoll, PollFd, PollFlags},
+            sys::time::TimeSpec,
+        };
 
-        match poll(&mut [pollfd], timeout.as_millis() as c_int)? {
-            0 => Err(IoErrorKind::TimedOut.into()),
-            _ => self.read_frame(),
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+
+            match ppoll(&mut [pollfd], Some(TimeSpec::from(remaining)), None) {
+                Ok(0) => return Ok(None),
+                Ok(_) => match self.read_frame() {
+                    Ok(frame) => return Ok(Some(frame)),
+                    Err(err) if err.should_retry() => (),
+                    Err(err) => return Err(err),
+                },
+                // Interrupted by a signal, so recompute the time remaining.
+                Err(Errno::EINTR) => (),
+                Err(err) => return Err(err.into()),
+            }
         }
     }
 
+    /// Blocking read of a single frame, which is passed by reference to the
+    /// closure, `f`, returning its result.
+    ///
+    /// This is a convenience wrapper around
+    /// [`read_frame`](Self::read_frame) for dispatch loops that only need
+    /// to look at each frame. It doesn't avoid any copies: the frame is
+    /// read and decoded just as with `read_frame()`, and then lent to the
+    /// closure.
+    fn read_frame_with<R, F>(&self, f: F) -> IoResult<R>
+    where
+        F: FnOnce(&Self::FrameType) -> R,
+    {
+        let frame = self.read_frame()?;
+        Ok(f(&frame))
+    }
+
     /// Write a single can frame.
     ///
     /// Note that this function can fail with an `EAGAIN` error or similar.
@@ -283,6 +520,168 @@ pub trait Socket: AsRawFd {
             }
         }
     }
+
+    /// Gets the MTU of the interface to which the socket is bound.
+    ///
+    /// This is `CAN_MTU` for an interface that only supports classic
+    /// CAN 2.0 frames, and `CANFD_MTU` for one that also supports FD frames.
+    /// It fails if the socket is not bound to a specific interface, such as
+    /// a socket opened to receive from all interfaces.
+    ///
+    /// This always queries the kernel. The sockets in this crate also
+    /// update the MTU that they cache for [`can_send`](Self::can_send),
+    /// so it can be used to refresh the value if the interface was
+    /// reconfigured after the socket was opened.
+    fn mtu(&self) -> IoResult<usize> {
+        raw_mtu(self.as_raw_socket())
+    }
+
+    /// Determines if the frame would fit within the MTU of the interface.
+    ///
+    /// This is a pre-flight check to see if a frame can be sent on the
+    /// socket, such as an FD frame on an interface that may only support
+    /// classic CAN 2.0 frames.
+    ///
+    /// The sockets in this crate read the MTU once, when they are opened,
+    /// and compare the frame size against that cached value, so this is
+    /// cheap to call for every frame. Use [`mtu`](Self::mtu) to refresh
+    /// it. The default implementation queries the MTU on every call.
+    ///
+    /// It is only advisory. The authoritative check is still done by the
+    /// kernel when the frame is written. If the MTU can not be determined,
+    /// this returns `true`, leaving the decision to the kernel.
+    fn can_send<F: AsPtr>(&self, frame: &F) -> bool {
+        self.mtu().map_or(true, |mtu| frame.size() <= mtu)
+    }
+}
+
+// ===== TimestampClock =====
+
+/// The clock used for the frame timestamps.
+///
+/// The kernel stamps frames in software with the realtime clock
+/// (`CLOCK_REALTIME`), or in hardware with the controller's own clock (its
+/// PTP hardware clock), which is often synchronized to TAI by a PTP
+/// daemon. There's no way to ask the kernel for software timestamps from
+/// any other clock, so for `Monotonic` and `Tai` the socket is set for
+/// realtime software timestamps, and they are converted to the selected
+/// clock with [`convert`](TimestampClock::convert) using the current
+/// offset between the clocks. That conversion is only as good as the
+/// offset remaining constant between when the frame was stamped and when
+/// it is converted; a step of the realtime clock in between, such as when
+/// it is set by NTP, throws it off.
+///
+/// | Clock       | `SO_TIMESTAMPING` flags                    | Time base        |
+/// |-------------|--------------------------------------------|------------------|
+/// | `Realtime`  | `SOF_TIMESTAMPING_{RX,TX}_SOFTWARE`        | `CLOCK_REALTIME` |
+/// | `Monotonic` | `SOF_TIMESTAMPING_{RX,TX}_SOFTWARE`        | `CLOCK_MONOTONIC`, converted |
+/// | `Tai`       | `SOF_TIMESTAMPING_{RX,TX}_SOFTWARE`        | `CLOCK_TAI`, converted |
+/// | `Hardware`  | `SOF_TIMESTAMPING_{RX,TX}_HARDWARE`        | The controller clock |
+///
+/// With `Hardware`, RX software timestamps are enabled as well, so that a
+/// frame is still stamped if the driver doesn't support hardware stamps.
+/// The hardware timestamp is used when there is one, and the software one
+/// otherwise.
+///
+/// The default is `Monotonic`, since it is not affected by changes to the
+/// system time.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampClock {
+    /// The system realtime clock, `CLOCK_REALTIME`
+    Realtime,
+    /// The monotonic clock, `CLOCK_MONOTONIC`
+    #[default]
+    Monotonic,
+    /// International Atomic Time, `CLOCK_TAI`
+    Tai,
+    /// The raw hardware clock of the CAN controller, if supported
+    Hardware,
+}
+
+impl TimestampClock {
+    /// Gets the `SO_TIMESTAMPING` flags to enable RX and TX timestamps from
+    /// this clock.
+    pub fn timestamping_flags(&self) -> u32 {
+        use libc::{
+            SOF_TIMESTAMPING_RAW_HARDWARE, SOF_TIMESTAMPING_RX_HARDWARE,
+            SOF_TIMESTAMPING_RX_SOFTWARE, SOF_TIMESTAMPING_SOFTWARE, SOF_TIMESTAMPING_TX_HARDWARE,
+            SOF_TIMESTAMPING_TX_SOFTWARE,
+        };
+        match self {
+            Self::Hardware => {
+                SOF_TIMESTAMPING_RX_HARDWARE
+                    | SOF_TIMESTAMPING_TX_HARDWARE
+                    | SOF_TIMESTAMPING_RAW_HARDWARE
+                    | SOF_TIMESTAMPING_RX_SOFTWARE
+                    | SOF_TIMESTAMPING_SOFTWARE
+            }
+            _ => {
+                SOF_TIMESTAMPING_RX_SOFTWARE
+                    | SOF_TIMESTAMPING_TX_SOFTWARE
+                    | SOF_TIMESTAMPING_SOFTWARE
+            }
+        }
+    }
+
+    /// Gets the system clock ID, if any, for the clock.
+    pub fn clock_id(&self) -> Option<libc::clockid_t> {
+        match self {
+            Self::Realtime => Some(libc::CLOCK_REALTIME),
+            Self::Monotonic => Some(libc::CLOCK_MONOTONIC),
+            Self::Tai => Some(libc::CLOCK_TAI),
+            Self::Hardware => None,
+        }
+    }
+
+    /// Converts a timestamp from the kernel into the time since the epoch
+    /// of this clock.
+    ///
+    /// Realtime and hardware timestamps are returned as is. For the others,
+    /// the timestamp is shifted by the current offset between the realtime
+    /// clock and this one.
+    pub fn convert(&self, ts: SystemTime) -> Duration {
+        let ts = ts
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let clk = match self.clock_id() {
+            Some(clk) if clk != libc::CLOCK_REALTIME => clk,
+            _ => return ts,
+        };
+
+        let now_rt = Self::clock_now(libc::CLOCK_REALTIME);
+        let now = Self::clock_now(clk);
+        (now + ts).saturating_sub(now_rt)
+    }
+
+    // Reads the current time from a system clock.
+    fn clock_now(clk: libc::clockid_t) -> Duration {
+        let mut ts: libc::timespec = unsafe { mem::zeroed() };
+        unsafe { libc::clock_gettime(clk, &mut ts) };
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+}
+
+// ===== TimestampMode =====
+
+/// The kind of receive timestamps reported by a socket.
+///
+/// This selects between the two kernel timestamping interfaces:
+/// `SO_TIMESTAMP` for simple software timestamps, or `SO_TIMESTAMPING`
+/// for hardware timestamps from the CAN controller. Either way, the
+/// timestamp is reported with each frame by [`CanSocket::read_msg`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampMode {
+    /// No receive timestamps
+    #[default]
+    None,
+    /// Software timestamps from the system realtime clock, using
+    /// `SO_TIMESTAMP`, with microsecond resolution
+    Software,
+    /// Hardware timestamps from the controller's clock, using
+    /// `SO_TIMESTAMPING`, falling back to software timestamps when the
+    /// driver doesn't supply them
+    Hardware,
 }
 
 /// Traits for setting CAN socket options.
@@ -361,6 +760,49 @@ pub trait SocketOptions: AsRawFd {
         self.set_socket_option_mult(SOL_CAN_RAW, CAN_RAW_FILTER, &filters)
     }
 
+    /// Gets the CAN ID filters currently applied to the socket.
+    ///
+    /// A new socket has the kernel's default filter, which is a single
+    /// filter that accepts all frames, so this returns `[(0, 0)]`. If
+    /// reception was disabled, with an empty set of filters, then the
+    /// returned vector is empty.
+    fn filters(&self) -> IoResult<Vec<CanFilter>> {
+        let fd = self.as_raw_fd();
+        let sz = mem::size_of::<CanFilter>();
+        let mut n = 64;
+
+        loop {
+            // Newer kernels report a buffer that is too small with an ERANGE
+            // error and the required size. Older ones silently truncate the
+            // filters to the buffer, so a full buffer might be missing some.
+            let mut filters = vec![CanFilter::new(0, 0); n];
+            let mut len = (n * sz) as socklen_t;
+            let ret = unsafe {
+                libc::getsockopt(
+                    fd,
+                    SOL_CAN_RAW,
+                    CAN_RAW_FILTER,
+                    filters.as_mut_ptr().cast(),
+                    &mut len,
+                )
+            };
+
+            if ret == 0 {
+                if (len as usize) < n * sz {
+                    filters.truncate(len as usize / sz);
+                    return Ok(filters);
+                }
+                n *= 2;
+            } else {
+                let err = IoError::last_os_error();
+                if err.raw_os_error() != Some(libc::ERANGE) {
+                    return Err(err);
+                }
+                n = (len as usize / sz).max(2 * n);
+            }
+        }
+    }
+
     /// Disable reception of CAN frames.
     ///
     /// Sets a completely empty filter; disabling all CAN frame reception.
@@ -388,6 +830,22 @@ pub trait SocketOptions: AsRawFd {
         self.set_socket_option(SOL_CAN_RAW, CAN_RAW_ERR_FILTER, &mask)
     }
 
+    /// Sets the error filter on the socket to receive the selected errors.
+    ///
+    /// This is the same as [`set_error_filter`](Self::set_error_filter),
+    /// with the mask built from a [`CanErrorFilter`], like:
+    ///
+    /// ```no_run
+    /// use socketcan::{CanErrorFilter, CanSocket, Socket, SocketOptions};
+    ///
+    /// let sock = CanSocket::open("can0").unwrap();
+    /// sock.set_can_error_filter(CanErrorFilter::none().bus_off().controller_problem())
+    ///     .unwrap();
+    /// ```
+    fn set_can_error_filter(&self, filter: CanErrorFilter) -> IoResult<()> {
+        self.set_error_filter(filter.bits())
+    }
+
     /// Sets the error mask on the socket to reject all errors.
     #[inline(always)]
     fn set_error_filter_drop_all(&self) -> IoResult<()> {
@@ -415,6 +873,10 @@ pub trait SocketOptions: AsRawFd {
     /// By default, loopback is enabled, causing other applications that open
     /// the same CAN bus to see frames emitted by different applications on
     /// the same system.
+    ///
+    /// Disabling it is useful for something like a bridge between two
+    /// interfaces, where frames looped back to other local sockets could
+    /// be forwarded back again, causing an echo.
     fn set_loopback(&self, enabled: bool) -> IoResult<()> {
         let loopback = c_int::from(enabled);
         self.set_socket_option(SOL_CAN_RAW, CAN_RAW_LOOPBACK, &loopback)
@@ -423,7 +885,10 @@ pub trait SocketOptions: AsRawFd {
     /// Enable or disable receiving of own frames.
     ///
     /// When loopback is enabled, this settings controls if CAN frames sent
-    /// are received back immediately by sender. Default is off.
+    /// are received back immediately by sender. Default is off, so by
+    /// default a socket doesn't receive its own frames, while the other
+    /// sockets on the same interface do. This has no effect if loopback is
+    /// disabled.
     fn set_recv_own_msgs(&self, enabled: bool) -> IoResult<()> {
         let recv_own_msgs = c_int::from(enabled);
         self.set_socket_option(SOL_CAN_RAW, CAN_RAW_RECV_OWN_MSGS, &recv_own_msgs)
@@ -432,41 +897,199 @@ pub trait SocketOptions: AsRawFd {
     /// Enable or disable join filters.
     ///
     /// By default a frame is accepted if it matches any of the filters set
-    /// with `set_filters`. If join filters is enabled, a frame has to match
-    /// _all_ filters to be accepted.
+    /// with `set_filters`, so the filters are effectively OR'ed together.
+    /// If join filters is enabled (`CAN_RAW_JOIN_FILTERS`), they are AND'ed
+    /// instead, and a frame has to match _all_ filters to be accepted.
+    ///
+    /// This makes it possible to narrow down a range of IDs, such as
+    /// accepting a block of IDs with one filter while rejecting a part of
+    /// it with an inverted filter. Note that the filters only ever match
+    /// on the CAN ID and the ID flags; they can't match the data in a frame.
+    ///
+    /// ```no_run
+    /// use socketcan::{CanSocket, FilterSet, Socket, SocketOptions, StandardId};
+    ///
+    /// // Accept 0x100-0x1FF, except for 0x180-0x18F
+    /// let filters = FilterSet::new()
+    ///     .accept(StandardId::new(0x100).unwrap(), 0x700)
+    ///     .reject(StandardId::new(0x180).unwrap(), 0x7F0);
+    ///
+    /// let sock = CanSocket::open("vcan0").unwrap();
+    /// sock.set_filters(&filters).unwrap();
+    /// sock.set_join_filters(true).unwrap();
+    /// ```
     fn set_join_filters(&self, enabled: bool) -> IoResult<()> {
         let join_filters = c_int::from(enabled);
         self.set_socket_option(SOL_CAN_RAW, CAN_RAW_JOIN_FILTERS, &join_filters)
     }
-}
 
-// TODO: We need to restore this, but preferably with TIMESTAMPING
+    /// Enable or disable reporting of the count of dropped frames.
+    ///
+    /// When enabled, the kernel attaches a running count of the frames
+    /// dropped by the socket, because its receive buffer was full, to each
+    /// received message. This is reported in [`CanMsg::dropped`].
+    fn set_rxq_overflow(&self, enabled: bool) -> IoResult<()> {
+        let rxq_ovfl = c_int::from(enabled);
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_RXQ_OVFL, &rxq_ovfl)
+    }
 
-/*
-impl CanSocket {
+    /// Sets the priority of the frames sent from the socket (`SO_PRIORITY`).
+    ///
+    /// This is the queuing priority in the kernel, not the CAN ID priority
+    /// on the bus. The interface's queuing discipline (qdisc) uses it to
+    /// pick a band for the outgoing frames, so with a priority qdisc like
+    /// `prio` or `mqprio`, frames from a higher priority socket can jump
+    /// ahead of bulk traffic from other sockets. With the default
+    /// `pfifo_fast` on a CAN interface, everything shares one queue.
+    ///
+    /// Values 0 through 6 can be set by any process. Anything higher needs
+    /// the `CAP_NET_ADMIN` capability, otherwise it fails with a permission
+    /// error.
+    fn set_tx_priority(&self, priority: i32) -> IoResult<()> {
+        let priority = priority as c_int;
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_PRIORITY, &priority)
+    }
 
-    /// Blocking read a single can frame with timestamp
+    /// Enables transmit times for frames sent on the socket (`SO_TXTIME`).
     ///
-    /// Note that reading a frame and retrieving the timestamp requires two
-    /// consecutive syscalls. To avoid race conditions, exclusive access
-    /// to the socket is enforce through requiring a `mut &self`.
-    pub fn read_frame_with_timestamp(&mut self) -> IoResult<(CanFrame, time::SystemTime)> {
-        let frame = self.read_frame()?;
+    /// Once enabled, a frame can be written with a time at which the
+    /// kernel should send it, such as with [`CanSocket::write_frame_at`].
+    /// The `clockid` selects the clock of those times, usually
+    /// `CLOCK_TAI` to match an `etf` qdisc, and the `flags` are a
+    /// combination of `SOF_TXTIME_DEADLINE_MODE` and
+    /// `SOF_TXTIME_REPORT_ERRORS` from libc.
+    ///
+    /// Any clock other than `CLOCK_MONOTONIC` needs the `CAP_NET_ADMIN`
+    /// capability. The kernel only holds the frames until their time if
+    /// the interface has a qdisc that honors it, like `etf`.
+    fn set_txtime(&self, clockid: libc::clockid_t, flags: u32) -> IoResult<()> {
+        let txtime = libc::sock_txtime { clockid, flags };
+        self.set_socket_option(libc::SOL_SOCKET, SO_TXTIME, &txtime)
+    }
 
-        let mut ts = timespec { tv_sec: 0, tv_nsec: 0 };
-        let ret = unsafe {
-            libc::ioctl(self.fd, SIOCGSTAMPNS as c_ulong, &mut ts as *mut timespec)
+    /// Sets the `SO_TIMESTAMPING` flags on the socket.
+    ///
+    /// The flags are a combination of the `SOF_TIMESTAMPING_*` values from
+    /// libc, selecting which timestamps the kernel generates and which it
+    /// reports. For example, to get transmit timestamps from the error queue
+    /// with [`CanSocket::read_tx_timestamp`], enable the generation of
+    /// software and/or hardware TX timestamps and their reporting:
+    ///
+    /// ```text
+    /// SOF_TIMESTAMPING_TX_SOFTWARE | SOF_TIMESTAMPING_SOFTWARE |
+    /// SOF_TIMESTAMPING_TX_HARDWARE | SOF_TIMESTAMPING_RAW_HARDWARE
+    /// ```
+    ///
+    /// Hardware timestamps also require them to be enabled on the interface,
+    /// and supported by its driver.
+    fn set_timestamping(&self, flags: u32) -> IoResult<()> {
+        let flags = flags as c_int;
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_TIMESTAMPING, &flags)
+    }
+
+    /// Enables RX and TX timestamps from the selected clock.
+    ///
+    /// This sets the `SO_TIMESTAMPING` flags for the clock. Software
+    /// timestamps are always reported by the kernel in realtime, and need to
+    /// be converted to the monotonic or TAI clocks with
+    /// [`TimestampClock::convert`].
+    fn set_timestamp_clock(&self, clock: TimestampClock) -> IoResult<()> {
+        self.set_timestamping(clock.timestamping_flags())
+    }
+
+    /// Sets the kind of receive timestamps reported by the socket.
+    ///
+    /// `Software` enables `SO_TIMESTAMP`, and `Hardware` enables
+    /// `SO_TIMESTAMPING` with the hardware flags of
+    /// [`TimestampClock::Hardware`]. The other option is turned off, so
+    /// only one kind of timestamp is reported. `None` turns both off.
+    ///
+    /// Hardware timestamps are in the time base of the controller's clock,
+    /// not the system clock. See [`CanMsg::timestamp`].
+    fn set_timestamp(&self, mode: TimestampMode) -> IoResult<()> {
+        let software = c_int::from(mode == TimestampMode::Software);
+        let flags = match mode {
+            TimestampMode::Hardware => TimestampClock::Hardware.timestamping_flags(),
+            _ => 0,
         };
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_TIMESTAMP, &software)?;
+        self.set_timestamping(flags)
+    }
+}
 
-        if ret == -1 {
-            return Err(IoError::last_os_error());
-        }
+// ===== FrameOrigin =====
+
+/// Where a received frame came from.
+///
+/// This is determined from the flags that the kernel sets on a received
+/// message, as described in the kernel's SocketCAN documentation:
+///
+/// - `MSG_CONFIRM` is set when the frame was sent by the socket on which it
+///   was received. These are only received when `recv_own_msgs` is enabled.
+/// - `MSG_DONTROUTE` is set when the frame was sent by any socket on the
+///   local host. These are received when loopback is enabled (the default).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOrigin {
+    /// The frame was sent by this socket, and echoed back to it.
+    Local,
+    /// The frame was sent by another socket on the same host.
+    Host,
+    /// The frame was received from the bus.
+    Remote,
+}
 
-        Ok((frame, system_time_from_timespec(ts)))
+impl FrameOrigin {
+    /// Determines the origin from the flags of a received message.
+    fn from_msg_flags(flags: c_int) -> Self {
+        if flags & libc::MSG_CONFIRM != 0 {
+            FrameOrigin::Local
+        } else if flags & libc::MSG_DONTROUTE != 0 {
+            FrameOrigin::Host
+        } else {
+            FrameOrigin::Remote
+        }
     }
+}
 
+// ===== CanMsg =====
+
+/// A received frame, along with the metadata that the kernel reports
+/// with it.
+///
+/// This is returned by [`CanSocket::read_msg`]. Some of the metadata is
+/// only reported if it is enabled on the socket beforehand:
+///
+/// - The `timestamp` requires RX timestamps to be enabled, such as with
+///   [`SocketOptions::set_timestamp`],
+///   [`SocketOptions::set_timestamp_clock`], or
+///   [`SocketOptions::set_timestamping`].
+/// - The `dropped` count requires [`SocketOptions::set_rxq_overflow`].
+#[derive(Debug, Clone, Copy)]
+pub struct CanMsg {
+    /// The received frame
+    pub frame: CanFrame,
+    /// Where the frame came from
+    pub origin: FrameOrigin,
+    /// The time the frame was received, if timestamps are enabled.
+    ///
+    /// This is the hardware timestamp, if one is available, otherwise the
+    /// software timestamp.
+    ///
+    /// A software timestamp is from the system realtime clock. A hardware
+    /// timestamp is the raw time of the controller's clock (its PTP
+    /// hardware clock), which is only returned as a `SystemTime` for
+    /// convenience. It matches the system time only if that clock is
+    /// synchronized to it, such as by `phc2sys`, and otherwise should only
+    /// be compared to other hardware timestamps from the same controller.
+    pub timestamp: Option<SystemTime>,
+    /// The index of the interface on which the frame was received.
+    ///
+    /// This is mainly useful for a socket bound to all interfaces.
+    pub ifindex: u32,
+    /// The total number of frames dropped by the socket so far, if the
+    /// reporting is enabled.
+    pub dropped: Option<u32>,
 }
-*/
 
 // ===== CanSocket =====
 
@@ -481,15 +1104,594 @@ impl CanSocket {
 /// (file) descriptor.
 #[allow(missing_copy_implementations)]
 #[derive(Debug)]
-pub struct CanSocket(socket2::Socket);
+pub struct CanSocket(
+    socket2::Socket,
+    // The MTU of the interface, or zero if unknown
+    AtomicUsize,
+);
 
 impl CanSocket {
+    // Creates the socket object from a bound OS socket, caching the MTU.
+    fn from_socket(sock: socket2::Socket) -> Self {
+        let mtu = raw_mtu(&sock).unwrap_or(0);
+        Self(sock, AtomicUsize::new(mtu))
+    }
+
     /// Reads a low-level libc `can_frame` from the socket.
     pub fn read_raw_frame(&self) -> IoResult<libc::can_frame> {
         let mut frame = can_frame_default();
         self.as_raw_socket().read_exact(as_bytes_mut(&mut frame))?;
         Ok(frame)
     }
+
+    /// Reads the next frame from the socket as raw bytes, without any
+    /// decoding.
+    ///
+    /// The bytes of the kernel's `struct can_frame` are copied into the
+    /// caller's buffer, and the number of bytes read is returned. This is
+    /// for applications that want to do their own parsing, or just pass
+    /// the frames along to somewhere else.
+    ///
+    /// The buffer must be at least `CAN_MTU` (16) bytes long, or this
+    /// returns an `InvalidInput` error without reading from the socket.
+    /// A shorter buffer would silently truncate the frame, since the
+    /// kernel discards whatever part of the frame doesn't fit.
+    pub fn read_raw_bytes(&self, buf: &mut [u8]) -> IoResult<usize> {
+        if buf.len() < CAN_MTU {
+            return Err(IoErrorKind::InvalidInput.into());
+        }
+        self.as_raw_socket().read(buf)
+    }
+
+    /// Blocking read of the next data frame from the socket.
+    ///
+    /// This discards any remote or error frames that are received, and
+    /// only returns data frames. It is useful for applications that only
+    /// deal with data, even though the socket may have an error filter
+    /// enabled for some other purpose.
+    ///
+    /// Note that, since the unwanted frames are read and dropped
+    /// internally, this can block for longer than a single call to
+    /// `read_frame()`. If error or remote frames are frequent, any read
+    /// timeout on the socket applies to each frame read, not to the call
+    /// as a whole.
+    pub fn read_data_frame(&self) -> IoResult<CanDataFrame> {
+        loop {
+            if let CanFrame::Data(frame) = self.read_frame()? {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Blocking read of the next frame, which is expected to have a
+    /// standard (11-bit) ID.
+    ///
+    /// If a frame with an extended ID is received, this returns an
+    /// [`Error::UnexpectedFrameType`](crate::Error::UnexpectedFrameType)
+    /// error, which contains the frame. The frame is still consumed from
+    /// the socket's queue. Error frames are returned as usual.
+    pub fn read_standard_frame(&self) -> Result<CanFrame> {
+        self.read_frame_of_type(false)
+    }
+
+    /// Blocking read of the next frame, which is expected to have an
+    /// extended (29-bit) ID.
+    ///
+    /// If a frame with a standard ID is received, this returns an
+    /// [`Error::UnexpectedFrameType`](crate::Error::UnexpectedFrameType)
+    /// error, which contains the frame. The frame is still consumed from
+    /// the socket's queue. Error frames are returned as usual.
+    pub fn read_extended_frame(&self) -> Result<CanFrame> {
+        self.read_frame_of_type(true)
+    }
+
+    // Reads a frame, checking that it has the expected ID type.
+    fn read_frame_of_type(&self, extended: bool) -> Result<CanFrame> {
+        use crate::EmbeddedFrame;
+
+        match self.read_frame()? {
+            frame @ CanFrame::Error(_) => Ok(frame),
+            frame if frame.is_extended() == extended => Ok(frame),
+            frame => Err(Error::UnexpectedFrameType(frame)),
+        }
+    }
+
+    /// Reads a frame from the socket, along with where it came from.
+    ///
+    /// This lets a single socket both transmit and receive, and tell the
+    /// echoes of its own frames apart from the real bus traffic, when
+    /// `recv_own_msgs` is enabled. See [`FrameOrigin`] for how the origin
+    /// is determined.
+    pub fn read_frame_with_origin(&self) -> IoResult<(CanFrame, FrameOrigin)> {
+        let mut frame = can_frame_default();
+        let mut iov = libc::iovec {
+            iov_base: (&mut frame as *mut libc::can_frame).cast(),
+            iov_len: CAN_MTU,
+        };
+
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+
+        let n = unsafe { libc::recvmsg(self.as_raw_fd(), &mut msg, 0) };
+        match n {
+            n if n < 0 => Err(IoError::last_os_error()),
+            0 => Err(IoErrorKind::UnexpectedEof.into()),
+            n if n as usize == CAN_MTU => {
+                Ok((frame.into(), FrameOrigin::from_msg_flags(msg.msg_flags)))
+            }
+            _ => Err(IoErrorKind::InvalidData.into()),
+        }
+    }
+
+    /// Reads a frame from the socket, along with all the metadata that the
+    /// kernel reports with it.
+    ///
+    /// This gets the frame, its origin, receive timestamp, the interface
+    /// on which it arrived, and the count of dropped frames, in a single
+    /// system call. See [`CanMsg`] for the socket options that need to be
+    /// enabled to get the timestamp and dropped count.
+    pub fn read_msg(&self) -> IoResult<CanMsg> {
+        let mut frame = can_frame_default();
+        let mut iov = libc::iovec {
+            iov_base: (&mut frame as *mut libc::can_frame).cast(),
+            iov_len: CAN_MTU,
+        };
+
+        let mut addr: libc::sockaddr_can = unsafe { mem::zeroed() };
+
+        // Enough, suitably aligned, space for the timestamp and dropped
+        // count control messages.
+        let mut cbuf = [0u64; 32];
+
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = (&mut addr as *mut libc::sockaddr_can).cast();
+        msg.msg_namelen = mem::size_of_val(&addr) as _;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = cbuf.as_mut_ptr().cast();
+        msg.msg_controllen = mem::size_of_val(&cbuf) as _;
+
+        let n = unsafe { libc::recvmsg(self.as_raw_fd(), &mut msg, 0) };
+        match n {
+            n if n < 0 => return Err(IoError::last_os_error()),
+            0 => return Err(IoErrorKind::UnexpectedEof.into()),
+            n if n as usize != CAN_MTU => return Err(IoErrorKind::InvalidData.into()),
+            _ => (),
+        }
+
+        let mut timestamp = None;
+        let mut dropped = None;
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+
+        while !cmsg.is_null() {
+            let hdr = unsafe { &*cmsg };
+            let data = unsafe { libc::CMSG_DATA(cmsg) };
+            match (hdr.cmsg_level, hdr.cmsg_type) {
+                (libc::SOL_SOCKET, libc::SCM_TIMESTAMPING) => {
+                    // struct scm_timestamping: [software, (deprecated), hardware]
+                    let tss: [libc::timespec; 3] = unsafe { ptr::read_unaligned(data.cast()) };
+                    timestamp = [tss[2], tss[0]]
+                        .into_iter()
+                        .find(|ts| ts.tv_sec != 0 || ts.tv_nsec != 0)
+                        .map(system_time_from_timespec);
+                }
+                // The software-only timestamps don't replace one from
+                // SO_TIMESTAMPING, if both are enabled.
+                (libc::SOL_SOCKET, libc::SCM_TIMESTAMPNS) => {
+                    let ts: libc::timespec = unsafe { ptr::read_unaligned(data.cast()) };
+                    timestamp = timestamp.or(Some(system_time_from_timespec(ts)));
+                }
+                (libc::SOL_SOCKET, libc::SCM_TIMESTAMP) => {
+                    let tv: libc::timeval = unsafe { ptr::read_unaligned(data.cast()) };
+                    let ts = Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000);
+                    timestamp = timestamp.or(Some(SystemTime::UNIX_EPOCH + ts));
+                }
+                (libc::SOL_SOCKET, libc::SO_RXQ_OVFL) => {
+                    dropped = Some(unsafe { ptr::read_unaligned(data.cast()) });
+                }
+                _ => (),
+            }
+            cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+        }
+
+        Ok(CanMsg {
+            frame: frame.into(),
+            origin: FrameOrigin::from_msg_flags(msg.msg_flags),
+            timestamp,
+            ifindex: addr.can_ifindex as u32,
+            dropped,
+        })
+    }
+
+    /// Reads a frame from the socket, along with the time that it was
+    /// received.
+    ///
+    /// RX timestamps must be enabled on the socket, such as with
+    /// [`SocketOptions::set_timestamp`]. The timestamp is read from the
+    /// same message as the frame, so it always belongs to the frame. It is
+    /// the hardware timestamp if there is one, otherwise the software
+    /// timestamp; see [`CanMsg::timestamp`] for the clock that each is
+    /// from. If the kernel reported no timestamp with the frame, this
+    /// returns an `InvalidData` error.
+    pub fn read_frame_with_timestamp(&self) -> IoResult<(CanFrame, SystemTime)> {
+        let msg = self.read_msg()?;
+        match msg.timestamp {
+            Some(ts) => Ok((msg.frame, ts)),
+            None => Err(IoErrorKind::InvalidData.into()),
+        }
+    }
+
+    /// Writes a frame, then blocks until it has been sent on the bus.
+    ///
+    /// The kernel doesn't report when the transmit queue of an interface
+    /// has drained, but it does echo a frame back to the socket that sent
+    /// it once the frame is transmitted. This writes the frame and waits
+    /// for that echo. Since the queue is FIFO, getting the echo implies that
+    /// all the frames written to the socket before it have also been sent.
+    /// This can be used with the last frame sent before shutting down or
+    /// reconfiguring the interface, to make sure nothing is still queued.
+    ///
+    /// This requires `loopback` and `recv_own_msgs` to be enabled on the
+    /// socket, otherwise the echo never arrives and the call times out. The
+    /// assumption that the echo means the frame left the interface holds
+    /// for drivers that echo on TX completion, which is most of them, but
+    /// some drivers echo the frame as soon as it is queued.
+    ///
+    /// Any other frames received while waiting are read and discarded. If
+    /// the echo doesn't arrive within the timeout, this returns a `TimedOut`
+    /// error.
+    pub fn write_frame_and_drain(&self, frame: &CanFrame, timeout: Duration) -> IoResult<()> {
+        use crate::{EmbeddedFrame, Frame};
+        use nix::{
+            errno::Errno,
+            poll::{ppoll, PollFd, PollFlags},
+            sys::time::TimeSpec,
+        };
+
+        // A deadline too far in the future is the same as none at all.
+        let deadline = Instant::now().checked_add(timeout);
+        self.write_frame_insist(frame)?;
+
+        loop {
+            let remaining = deadline
+                .map(|deadline| TimeSpec::from(deadline.saturating_duration_since(Instant::now())));
+            let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+
+            match ppoll(&mut [pollfd], remaining, None) {
+                Ok(0) => return Err(IoErrorKind::TimedOut.into()),
+                Ok(_) => match self.read_frame_with_origin() {
+                    Ok((echo, FrameOrigin::Local))
+                        if echo.id_word() == frame.id_word() && echo.data() == frame.data() =>
+                    {
+                        return Ok(())
+                    }
+                    Ok(_) => (),
+                    Err(err) if err.should_retry() => (),
+                    Err(err) => return Err(err),
+                },
+                Err(Errno::EINTR) => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Writes a frame only if its data has changed from the last one
+    /// written with the same ID.
+    ///
+    /// This checks the frame against the [`ChangeFilter`], and if it
+    /// differs, writes it and records it in the filter. It returns `true`
+    /// if the frame was written, and `false` if it was skipped as
+    /// redundant. If the write fails, the frame is not recorded, so the
+    /// next attempt with the same data will still be sent.
+    ///
+    /// Remote (RTR) frames are always sent, since they are requests for
+    /// data rather than a value.
+    pub fn write_frame_if_changed(
+        &self,
+        frame: &CanFrame,
+        filter: &mut ChangeFilter,
+    ) -> IoResult<bool> {
+        if !filter.is_changed(frame) {
+            return Ok(false);
+        }
+        self.write_frame_insist(frame)?;
+        filter.record(frame);
+        Ok(true)
+    }
+
+    /// Reads a batch of frames, with a single system call.
+    ///
+    /// Up to `max` frames are read with `recvmmsg()` and appended to `buf`,
+    /// returning the number of frames that were read. This is meant for
+    /// capturing busy buses, where a system call per frame can dominate
+    /// the CPU load.
+    ///
+    /// In blocking mode, this waits for the first frame, subject to any
+    /// read timeout, then returns it along with any others that are already
+    /// waiting, without blocking for more. In nonblocking mode, it returns
+    /// whatever is available, or a `WouldBlock` error if nothing is.
+    ///
+    /// If a message in the batch isn't a whole frame, the frames before it
+    /// are kept and counted, and it and the rest of the batch are dropped.
+    /// It's only an `InvalidData` error if the first message is bad.
+    ///
+    /// If `max` is zero, this returns immediately without reading. See
+    /// [`write_burst`](Self::write_burst) for the transmit side.
+    pub fn read_frames(&self, buf: &mut Vec<CanFrame>, max: usize) -> IoResult<usize> {
+        if max == 0 {
+            return Ok(0);
+        }
+
+        let mut frames = vec![can_frame_default(); max];
+
+        let mut iovs: Vec<_> = frames
+            .iter_mut()
+            .map(|frame| libc::iovec {
+                iov_base: (frame as *mut libc::can_frame).cast(),
+                iov_len: CAN_MTU,
+            })
+            .collect();
+
+        let mut msgs: Vec<_> = iovs
+            .iter_mut()
+            .map(|iov| {
+                let mut msg: libc::mmsghdr = unsafe { mem::zeroed() };
+                msg.msg_hdr.msg_iov = iov;
+                msg.msg_hdr.msg_iovlen = 1;
+                msg
+            })
+            .collect();
+
+        let n = loop {
+            let n = unsafe {
+                libc::recvmmsg(
+                    self.as_raw_fd(),
+                    msgs.as_mut_ptr(),
+                    msgs.len() as _,
+                    libc::MSG_WAITFORONE as _,
+                    ptr::null_mut(),
+                )
+            };
+            if n >= 0 {
+                break n as usize;
+            }
+            let err = IoError::last_os_error();
+            if err.kind() != IoErrorKind::Interrupted {
+                return Err(err);
+            }
+        };
+
+        for (i, (msg, frame)) in msgs.iter().zip(frames).take(n).enumerate() {
+            if msg.msg_len as usize != CAN_MTU {
+                return match i {
+                    0 => Err(IoErrorKind::InvalidData.into()),
+                    _ => Ok(i),
+                };
+            }
+            buf.push(frame.into());
+        }
+        Ok(n)
+    }
+
+    /// Gets an iterator over the frames received by the socket.
+    ///
+    /// Each call to `next()` reads a frame with
+    /// [`read_frame`](Socket::read_frame), so on a blocking socket the
+    /// iterator blocks until a frame arrives. On a nonblocking socket, or
+    /// when a read timeout expires, it yields a `WouldBlock` error, which
+    /// a caller can use to break out of a loop.
+    ///
+    /// The iterator ends after yielding an error that means that the socket
+    /// can no longer be used, such as when the interface was removed.
+    ///
+    /// ```no_run
+    /// use socketcan::{CanSocket, EmbeddedFrame, Socket};
+    ///
+    /// let sock = CanSocket::open("vcan0").unwrap();
+    /// for frame in sock.frames().filter_map(Result::ok).take(10) {
+    ///     println!("{:?}", frame.data());
+    /// }
+    /// ```
+    pub fn frames(&self) -> FrameIter<'_> {
+        FrameIter {
+            sock: self,
+            done: false,
+        }
+    }
+
+    /// Writes a sequence of frames as a burst, with a single system call.
+    ///
+    /// The frames are passed to the kernel together with `sendmmsg()`, and
+    /// queued for transmission in order. This saves a system call per
+    /// frame, but doesn't make the burst atomic.
+    ///
+    /// This returns the number of frames that were sent. If the transmit
+    /// queue fills part way through, the burst is cut short, and the count
+    /// is less than the number of frames. An error is only returned if the
+    /// first frame could not be sent.
+    ///
+    /// The kernel sends each frame in turn, does not hold the socket
+    /// locked for the whole burst, and the thread may be preempted between
+    /// frames. So frames written concurrently on the
+    /// same socket by other threads, or on other sockets bound to the same
+    /// interface, may still be interleaved with the burst. Applications that
+    /// write from several threads should serialize them, such as with a
+    /// mutex, to keep bursts contiguous. And of course, other nodes on the
+    /// bus can win arbitration between any two of the frames.
+    pub fn write_burst(&self, frames: &[CanFrame]) -> IoResult<usize> {
+        if frames.is_empty() {
+            return Ok(0);
+        }
+
+        let mut iovs: Vec<_> = frames
+            .iter()
+            .map(|frame| libc::iovec {
+                iov_base: frame.as_ptr() as *mut c_void,
+                iov_len: frame.size(),
+            })
+            .collect();
+
+        let mut msgs: Vec<_> = iovs
+            .iter_mut()
+            .map(|iov| {
+                let mut msg: libc::mmsghdr = unsafe { mem::zeroed() };
+                msg.msg_hdr.msg_iov = iov;
+                msg.msg_hdr.msg_iovlen = 1;
+                msg
+            })
+            .collect();
+
+        loop {
+            let n =
+                unsafe { libc::sendmmsg(self.as_raw_fd(), msgs.as_mut_ptr(), msgs.len() as _, 0) };
+            if n >= 0 {
+                return Ok(n as usize);
+            }
+            let err = IoError::last_os_error();
+            if err.kind() != IoErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    /// Writes a frame to be sent at a specific time.
+    ///
+    /// This attaches the time to the frame as an `SCM_TXTIME` control
+    /// message. Transmit times must first be enabled on the socket with
+    /// [`SocketOptions::set_txtime`], and the interface needs a qdisc that
+    /// holds each frame until its time, such as `etf`. Without one, the
+    /// time is ignored and the frame is sent right away.
+    ///
+    /// The time is converted from the system realtime clock to the clock
+    /// selected for the socket, using the current offset between them.
+    ///
+    /// If transmit times aren't enabled on the socket, or the kernel doesn't
+    /// support them for CAN, the kernel rejects the frame with a bare
+    /// `EINVAL`. That is returned as an `InvalidInput` error saying so.
+    /// Frames that the qdisc drops, such as for a time that already
+    /// passed, are only reported on the error queue, if
+    /// `SOF_TXTIME_REPORT_ERRORS` was set.
+    pub fn write_frame_at(&self, frame: &CanFrame, when: SystemTime) -> IoResult<()> {
+        let mut txtime: libc::sock_txtime = unsafe { mem::zeroed() };
+        let mut len = mem::size_of_val(&txtime) as socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                self.as_raw_fd(),
+                libc::SOL_SOCKET,
+                SO_TXTIME,
+                (&mut txtime as *mut libc::sock_txtime).cast(),
+                &mut len,
+            )
+        };
+        if ret != 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        // Shift the time by the current offset from the realtime clock
+        let when = when
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as i128;
+        let now_rt = TimestampClock::clock_now(libc::CLOCK_REALTIME).as_nanos() as i128;
+        let now = TimestampClock::clock_now(txtime.clockid).as_nanos() as i128;
+        let txtime = (when + now - now_rt).max(0) as u64;
+
+        let mut iov = libc::iovec {
+            iov_base: frame.as_ptr() as *mut c_void,
+            iov_len: frame.size(),
+        };
+
+        // Suitably aligned space for the transmit time control message
+        let mut cbuf = [0u64; 4];
+
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = cbuf.as_mut_ptr().cast();
+        msg.msg_controllen = unsafe { libc::CMSG_SPACE(mem::size_of::<u64>() as _) } as _;
+
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = SCM_TXTIME;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<u64>() as _) as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast(), txtime);
+        }
+
+        let n = unsafe { libc::sendmsg(self.as_raw_fd(), &msg, 0) };
+        if n < 0 {
+            let err = IoError::last_os_error();
+            return Err(match err.raw_os_error() {
+                Some(libc::EINVAL) => IoError::new(
+                    IoErrorKind::InvalidInput,
+                    "transmit time rejected; SO_TXTIME is not enabled on the socket",
+                ),
+                _ => err,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads a transmit timestamp from the socket's error queue.
+    ///
+    /// When TX timestamps are enabled with
+    /// [`SocketOptions::set_timestamping`], the kernel echoes each frame
+    /// sent on the socket back on its error queue, along with the time that
+    /// it was transmitted. This reads the next one, returning the frame
+    /// that was sent and its timestamp. A hardware timestamp is returned if
+    /// one is available, otherwise the software timestamp.
+    ///
+    /// Reads from the error queue never block. If there is nothing in the
+    /// queue, this returns a `WouldBlock` error. An application can wait for
+    /// a timestamp by polling the socket for `POLLERR`.
+    pub fn read_tx_timestamp(&self) -> IoResult<(CanFrame, SystemTime)> {
+        let mut frame = can_frame_default();
+        let mut iov = libc::iovec {
+            iov_base: (&mut frame as *mut libc::can_frame).cast(),
+            iov_len: CAN_MTU,
+        };
+
+        // Enough, suitably aligned, space for the timestamping and
+        // extended error control messages.
+        let mut cbuf = [0u64; 32];
+
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = cbuf.as_mut_ptr().cast();
+        msg.msg_controllen = mem::size_of_val(&cbuf) as _;
+
+        let n = unsafe { libc::recvmsg(self.as_raw_fd(), &mut msg, libc::MSG_ERRQUEUE) };
+        if n < 0 {
+            return Err(IoError::last_os_error());
+        }
+        if n as usize != CAN_MTU {
+            // Probably the timestamp without the frame (OPT_TSONLY)
+            return Err(IoErrorKind::InvalidData.into());
+        }
+
+        let mut ts = None;
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+
+        while !cmsg.is_null() {
+            let hdr = unsafe { &*cmsg };
+            if hdr.cmsg_level == libc::SOL_SOCKET && hdr.cmsg_type == libc::SCM_TIMESTAMPING {
+                // struct scm_timestamping: [software, (deprecated), hardware]
+                let tss: [libc::timespec; 3] =
+                    unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast()) };
+                ts = [tss[2], tss[0]]
+                    .into_iter()
+                    .find(|ts| ts.tv_sec != 0 || ts.tv_nsec != 0);
+            }
+            cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+        }
+
+        match ts {
+            Some(ts) => Ok((frame.into(), system_time_from_timespec(ts))),
+            None => Err(IoErrorKind::InvalidData.into()),
+        }
+    }
 }
 
 impl Socket for CanSocket {
@@ -498,8 +1700,7 @@ impl Socket for CanSocket {
 
     /// Opens the socket by interface index.
     fn open_addr(addr: &CanAddr) -> IoResult<Self> {
-        let sock = raw_open_socket(addr)?;
-        Ok(Self(sock))
+        raw_open_socket(addr).map(Self::from_socket)
     }
 
     /// Gets a shared reference to the underlying socket object
@@ -525,6 +1726,19 @@ impl Socket for CanSocket {
         let frame = self.read_raw_frame()?;
         Ok(frame.into())
     }
+
+    /// Queries the MTU of the interface, and updates the cached value.
+    fn mtu(&self) -> IoResult<usize> {
+        let mtu = raw_mtu(&self.0)?;
+        self.1.store(mtu, Ordering::Relaxed);
+        Ok(mtu)
+    }
+
+    /// Checks the frame size against the MTU cached when the socket was
+    /// opened.
+    fn can_send<F: AsPtr>(&self, frame: &F) -> bool {
+        fits_mtu(&self.1, frame)
+    }
 }
 
 impl SocketOptions for CanSocket {}
@@ -538,7 +1752,7 @@ impl AsRawFd for CanSocket {
 
 impl From<OwnedFd> for CanSocket {
     fn from(fd: OwnedFd) -> Self {
-        Self(socket2::Socket::from(fd))
+        Self::from_socket(socket2::Socket::from(fd))
     }
 }
 
@@ -570,6 +1784,32 @@ impl Write for CanSocket {
     }
 }
 
+// ===== FrameIter =====
+
+/// An iterator over the frames received by a [`CanSocket`].
+///
+/// This is created by [`CanSocket::frames`].
+#[derive(Debug)]
+pub struct FrameIter<'a> {
+    sock: &'a CanSocket,
+    done: bool,
+}
+
+impl Iterator for FrameIter<'_> {
+    type Item = IoResult<CanFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.sock.read_frame();
+        if let Err(err) = &res {
+            self.done = is_terminal_io_error(err);
+        }
+        Some(res)
+    }
+}
+
 // ===== CanFdSocket =====
 
 /// A socket for CAN FD devices.
@@ -578,9 +1818,56 @@ impl Write for CanSocket {
 /// or CAN Flexible Data (FD) frames with up to 64-bytes of data.
 #[allow(missing_copy_implementations)]
 #[derive(Debug)]
-pub struct CanFdSocket(socket2::Socket);
+pub struct CanFdSocket(
+    socket2::Socket,
+    // Whether to send short FD frames as classic frames
+    AtomicBool,
+    // The MTU of the interface, or zero if unknown
+    AtomicUsize,
+);
 
 impl CanFdSocket {
+    // Creates the socket object from an FD-enabled OS socket, caching the
+    // MTU if it's bound.
+    fn from_socket(sock: socket2::Socket) -> Self {
+        let mtu = raw_mtu(&sock).unwrap_or(0);
+        Self(sock, AtomicBool::new(false), AtomicUsize::new(mtu))
+    }
+
+    /// Sets whether FD frames with a classic-sized payload are sent as
+    /// classic CAN 2.0 frames.
+    ///
+    /// The kernel decides how to send a frame by the number of bytes
+    /// written to the socket:
+    ///
+    /// - When disabled (the default), each frame is written at its own
+    ///   size. A classic frame is written as a 16-byte `can_frame`
+    ///   (`CAN_MTU`), and goes on the bus as a classic frame. An FD frame
+    ///   is written as a 72-byte `canfd_frame` (`CANFD_MTU`), and goes on
+    ///   the bus as an FD frame, even if it has 8 or fewer data bytes and
+    ///   the bit rate switch (BRS) is off.
+    /// - When enabled, an FD frame with 8 or fewer data bytes is converted
+    ///   to a classic frame, and written as a 16-byte `can_frame`, so it
+    ///   goes on the bus as a true classic frame. Its FD flags (BRS, ESI)
+    ///   are dropped. FD frames with more than 8 bytes can't be sent as
+    ///   classic frames, and are still written as 72-byte FD frames.
+    ///
+    /// This is mainly useful for interoperability testing with nodes that
+    /// don't handle FD frames well. It only affects frames written with
+    /// [`Socket::write_frame`] (and the methods built on it), and not what
+    /// is received.
+    pub fn set_force_classic_dlc(&self, enabled: bool) {
+        self.1.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Determines if FD frames with a classic-sized payload are sent as
+    /// classic CAN 2.0 frames.
+    ///
+    /// See [`set_force_classic_dlc`](CanFdSocket::set_force_classic_dlc).
+    pub fn force_classic_dlc(&self) -> bool {
+        self.1.load(Ordering::Relaxed)
+    }
+
     // Enable or disable FD mode on a socket.
     fn set_fd_mode(sock: socket2::Socket, enable: bool) -> IoResult<socket2::Socket> {
         let enable = enable as c_int;
@@ -609,17 +1896,31 @@ impl CanFdSocket {
         let mut fdframe = canfd_frame_default();
 
         match self.as_raw_socket().read(as_bytes_mut(&mut fdframe))? {
+            // A zero-length read means the socket was shut down
+            0 => Err(IoErrorKind::UnexpectedEof.into()),
             // If we only get 'can_frame' number of bytes, then the return is,
-            // by definition, a can_frame, so we just copy the bytes into the
-            // proper type.
-            CAN_MTU => {
-                let mut frame = can_frame_default();
-                as_bytes_mut(&mut frame)[..CAN_MTU].copy_from_slice(&as_bytes(&fdframe)[..CAN_MTU]);
-                Ok(frame.into())
-            }
-            CANFD_MTU => Ok(fdframe.into()),
-            _ => Err(IoError::last_os_error()),
+            // by definition, a can_frame, which is sorted out when decoding.
+            n => CanRawFrame::from_bytes(&as_bytes(&fdframe)[..n])
+                .map_err(|_| IoErrorKind::InvalidData.into()),
+        }
+    }
+
+    /// Reads the next frame from the socket as raw bytes, without any
+    /// decoding.
+    ///
+    /// This copies the kernel's `struct canfd_frame` or `struct can_frame`
+    /// into the caller's buffer, and returns the number of bytes read. The
+    /// count tells which type of frame was received: `CANFD_MTU` (72) for
+    /// an FD frame, or `CAN_MTU` (16) for a classic CAN 2.0 frame.
+    ///
+    /// The buffer must be at least `CANFD_MTU` (72) bytes long, so that
+    /// it can hold any frame, or this returns an `InvalidInput` error
+    /// without reading from the socket.
+    pub fn read_raw_bytes(&self, buf: &mut [u8]) -> IoResult<usize> {
+        if buf.len() < CANFD_MTU {
+            return Err(IoErrorKind::InvalidInput.into());
         }
+        self.as_raw_socket().read(buf)
     }
 }
 
@@ -631,7 +1932,7 @@ impl Socket for CanFdSocket {
     fn open_addr(addr: &CanAddr) -> IoResult<Self> {
         raw_open_socket(addr)
             .and_then(|sock| Self::set_fd_mode(sock, true))
-            .map(Self)
+            .map(Self::from_socket)
     }
 
     /// Gets a shared reference to the underlying socket object
@@ -645,29 +1946,39 @@ impl Socket for CanFdSocket {
     }
 
     /// Writes any type of CAN frame to the socket.
+    ///
+    /// See [`CanFdSocket::set_force_classic_dlc`] for how the size of the
+    /// write determines the type of frame sent on the bus.
     fn write_frame<F>(&self, frame: &F) -> IoResult<()>
     where
         F: Into<Self::FrameType> + AsPtr,
     {
+        if self.force_classic_dlc() {
+            if let Ok(CanRawFrame::Fd(fdframe)) = CanRawFrame::from_bytes(frame.as_bytes()) {
+                if let Ok(frame) = CanFrame::try_from(CanFdFrame::from(fdframe)) {
+                    return self.as_raw_socket().write_all(frame.as_bytes());
+                }
+            }
+        }
         self.as_raw_socket().write_all(frame.as_bytes())
     }
 
     /// Reads either type of CAN frame from the socket.
     fn read_frame(&self) -> IoResult<CanAnyFrame> {
-        let mut fdframe = canfd_frame_default();
+        self.read_raw_frame().map(CanAnyFrame::from)
+    }
 
-        match self.as_raw_socket().read(as_bytes_mut(&mut fdframe))? {
-            // If we only get 'can_frame' number of bytes, then the return is,
-            // by definition, a can_frame, so we just copy the bytes into the
-            // proper type.
-            CAN_MTU => {
-                let mut frame = can_frame_default();
-                as_bytes_mut(&mut frame)[..CAN_MTU].copy_from_slice(&as_bytes(&fdframe)[..CAN_MTU]);
-                Ok(CanFrame::from(frame).into())
-            }
-            CANFD_MTU => Ok(CanFdFrame::from(fdframe).into()),
-            _ => Err(IoError::last_os_error()),
-        }
+    /// Queries the MTU of the interface, and updates the cached value.
+    fn mtu(&self) -> IoResult<usize> {
+        let mtu = raw_mtu(&self.0)?;
+        self.2.store(mtu, Ordering::Relaxed);
+        Ok(mtu)
+    }
+
+    /// Checks the frame size against the MTU cached when the socket was
+    /// opened.
+    fn can_send<F: AsPtr>(&self, frame: &F) -> bool {
+        fits_mtu(&self.2, frame)
     }
 }
 
@@ -682,7 +1993,7 @@ impl AsRawFd for CanFdSocket {
 
 impl From<OwnedFd> for CanFdSocket {
     fn from(fd: OwnedFd) -> CanFdSocket {
-        Self(socket2::Socket::from(fd))
+        Self::from_socket(socket2::Socket::from(fd))
     }
 }
 
@@ -698,6 +2009,175 @@ impl AsFd for CanFdSocket {
     }
 }
 
+// ===== CanSocketBuilder =====
+
+/// A builder to configure and open a CAN socket.
+///
+/// When a socket is opened and then configured, there is a window between
+/// binding the socket to the interface and applying the filters in which
+/// the socket receives every frame on the bus under the default filter.
+/// On a busy bus, these stray frames can flood the receive queue before
+/// the application is ready for them.
+///
+/// The builder avoids this by creating the socket, applying all of the
+/// requested options, and only then binding it to the interface. So the
+/// socket never receives a frame under an unintended filter.
+///
+/// ```no_run
+/// use socketcan::{CanFilter, CanSocketBuilder};
+///
+/// let sock = CanSocketBuilder::new()
+///     .filters(&[CanFilter::new(0x100, 0x7FF)])
+///     .open("vcan0")
+///     .unwrap();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CanSocketBuilder {
+    filters: Option<Vec<CanFilter>>,
+    error_filter: Option<u32>,
+    loopback: Option<bool>,
+    recv_own_msgs: Option<bool>,
+    join_filters: Option<bool>,
+    nonblocking: bool,
+}
+
+impl CanSocketBuilder {
+    /// Creates a new builder with the default socket options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the CAN ID filters for the socket.
+    ///
+    /// See [`SocketOptions::set_filters`]
+    pub fn filters<F>(mut self, filters: &[F]) -> Self
+    where
+        F: Into<CanFilter> + Copy,
+    {
+        self.filters = Some(filters.iter().map(|f| (*f).into()).collect());
+        self
+    }
+
+    /// Sets the socket to receive no data frames.
+    ///
+    /// See [`SocketOptions::set_filter_drop_all`]
+    pub fn filter_drop_all(mut self) -> Self {
+        self.filters = Some(Vec::new());
+        self
+    }
+
+    /// Sets the error mask for the socket.
+    ///
+    /// See [`SocketOptions::set_error_filter`]
+    pub fn error_filter(mut self, mask: u32) -> Self {
+        self.error_filter = Some(mask);
+        self
+    }
+
+    /// Sets the error mask for the socket to receive the classes of the
+    /// specified errors.
+    ///
+    /// See [`CanErrorFilter::for_errors`]
+    pub fn error_filter_for(self, errors: &[CanError]) -> Self {
+        self.error_filter(CanErrorFilter::for_errors(errors).bits())
+    }
+
+    /// Enable or disable loopback.
+    ///
+    /// Loopback is on by default, and sends the frames written by the
+    /// socket back to the _other_ sockets on the same interface on this
+    /// host. It doesn't send them back to this socket; that also needs
+    /// [`recv_own_msgs`](Self::recv_own_msgs).
+    ///
+    /// See [`SocketOptions::set_loopback`]
+    pub fn loopback(mut self, enabled: bool) -> Self {
+        self.loopback = Some(enabled);
+        self
+    }
+
+    /// Enable or disable receiving of own frames.
+    ///
+    /// This is off by default, so a socket doesn't see the frames that it
+    /// writes, even though other sockets on the host do. When enabled,
+    /// and loopback is also enabled, each frame written by the socket is
+    /// also received by it. Like all the builder options, it is applied
+    /// before the socket is bound, so it is in effect for the first frame.
+    ///
+    /// See [`SocketOptions::set_recv_own_msgs`]
+    pub fn recv_own_msgs(mut self, enabled: bool) -> Self {
+        self.recv_own_msgs = Some(enabled);
+        self
+    }
+
+    /// Enable or disable join filters.
+    ///
+    /// See [`SocketOptions::set_join_filters`]
+    pub fn join_filters(mut self, enabled: bool) -> Self {
+        self.join_filters = Some(enabled);
+        self
+    }
+
+    /// Whether to open the socket in non-blocking mode.
+    pub fn nonblocking(mut self, nonblocking: bool) -> Self {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    /// Applies the options to the socket, in order, then binds it.
+    fn configure_and_bind<S>(&self, sock: &S, addr: &CanAddr) -> IoResult<()>
+    where
+        S: Socket + SocketOptions,
+    {
+        if let Some(filters) = &self.filters {
+            sock.set_filters(filters)?;
+        }
+        if let Some(mask) = self.error_filter {
+            sock.set_error_filter(mask)?;
+        }
+        if let Some(enabled) = self.loopback {
+            sock.set_loopback(enabled)?;
+        }
+        if let Some(enabled) = self.recv_own_msgs {
+            sock.set_recv_own_msgs(enabled)?;
+        }
+        if let Some(enabled) = self.join_filters {
+            sock.set_join_filters(enabled)?;
+        }
+        if self.nonblocking {
+            sock.set_nonblocking(true)?;
+        }
+        sock.as_raw_socket().bind(&SockAddr::from(*addr))
+    }
+
+    /// Opens a classic CAN 2.0 socket on the named interface.
+    pub fn open(&self, ifname: &str) -> IoResult<CanSocket> {
+        let addr = CanAddr::from_iface(ifname)?;
+        self.open_addr(&addr)
+    }
+
+    /// Opens a classic CAN 2.0 socket by address.
+    pub fn open_addr(&self, addr: &CanAddr) -> IoResult<CanSocket> {
+        let sock = CanSocket::from_socket(raw_socket()?);
+        self.configure_and_bind(&sock, addr)?;
+        sock.mtu().ok();
+        Ok(sock)
+    }
+
+    /// Opens a CAN FD socket on the named interface.
+    pub fn open_fd(&self, ifname: &str) -> IoResult<CanFdSocket> {
+        let addr = CanAddr::from_iface(ifname)?;
+        self.open_fd_addr(&addr)
+    }
+
+    /// Opens a CAN FD socket by address.
+    pub fn open_fd_addr(&self, addr: &CanAddr) -> IoResult<CanFdSocket> {
+        let sock = CanFdSocket::from_socket(CanFdSocket::set_fd_mode(raw_socket()?, true)?);
+        self.configure_and_bind(&sock, addr)?;
+        sock.mtu().ok();
+        Ok(sock)
+    }
+}
+
 // ===== CanFilter =====
 
 /// The CAN filter defines which ID's can be accepted on a socket.
@@ -743,3 +2223,281 @@ impl AsRef<libc::can_filter> for CanFilter {
         &self.0
     }
 }
+
+// ===== FilterSet =====
+
+/// A builder for a set of CAN ID filters.
+///
+/// This collects accepting and rejecting (inverted) filters, which can
+/// then all be installed on a socket in a single call to
+/// [`SocketOptions::set_filters`].
+///
+/// The filters are built from typed IDs, so a standard ID always fits in
+/// 11 bits and an extended one in 29. Each filter also matches on the
+/// extended frame flag, so that a filter for a standard ID doesn't match
+/// an extended ID that happens to have the same low bits, and vice versa.
+///
+/// ```no_run
+/// use socketcan::{CanSocket, FilterSet, Socket, SocketOptions, StandardId};
+///
+/// let filters = FilterSet::new()
+///     .accept(StandardId::new(0x100).unwrap(), 0x700)
+///     .accept_exact(StandardId::new(0x7DF).unwrap());
+///
+/// let sock = CanSocket::open("vcan0").unwrap();
+/// sock.set_filters(&filters).unwrap();
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FilterSet(Vec<CanFilter>);
+
+impl FilterSet {
+    /// Creates a new, empty, set of filters.
+    ///
+    /// Note that installing an empty set on a socket disables the reception
+    /// of all frames.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a filter that accepts frames where the bits of the ID that are
+    /// set in the mask match the ID.
+    ///
+    /// Mask bits beyond the size of the ID are ignored.
+    pub fn accept(mut self, id: impl Into<Id>, mask: u32) -> Self {
+        self.0.push(Self::filter(id.into(), mask, false));
+        self
+    }
+
+    /// Adds a filter that accepts all frames except those where the bits of
+    /// the ID that are set in the mask match the ID.
+    ///
+    /// Note that the kernel accepts a frame if it matches _any_ of the
+    /// filters, so a rejecting filter is usually only useful on its own,
+    /// or when the filters are joined with
+    /// [`SocketOptions::set_join_filters`].
+    pub fn reject(mut self, id: impl Into<Id>, mask: u32) -> Self {
+        self.0.push(Self::filter(id.into(), mask, true));
+        self
+    }
+
+    /// Adds a filter that accepts frames with exactly this ID.
+    pub fn accept_exact(self, id: impl Into<Id>) -> Self {
+        self.accept(id, CAN_EFF_MASK)
+    }
+
+    /// Adds a filter that accepts frames matching a raw ID and mask.
+    ///
+    /// An ID up to 0x7FF is taken as a standard ID, and anything larger
+    /// as an extended one. This fails with
+    /// [`ConstructionError::IDTooLarge`] if the ID doesn't fit in 29 bits.
+    pub fn accept_raw(self, id: u32, mask: u32) -> std::result::Result<Self, ConstructionError> {
+        let id = id_from_raw(id).ok_or(ConstructionError::IDTooLarge)?;
+        Ok(self.accept(id, mask))
+    }
+
+    /// Adds an existing filter to the set, as is.
+    pub fn push(&mut self, filter: CanFilter) {
+        self.0.push(filter);
+    }
+
+    /// Gets the filters as a slice.
+    pub fn as_slice(&self) -> &[CanFilter] {
+        &self.0
+    }
+
+    /// Creates a filter for the ID, which also matches the EFF flag.
+    fn filter(id: Id, mask: u32, inverted: bool) -> CanFilter {
+        let id_mask = match id {
+            Id::Standard(_) => CAN_SFF_MASK,
+            Id::Extended(_) => CAN_EFF_MASK,
+        };
+        let mask = (mask & id_mask) | CAN_EFF_FLAG;
+        let id = id_to_canid_t(id);
+
+        if inverted {
+            CanFilter::new_inverted(id, mask)
+        } else {
+            CanFilter::new(id, mask)
+        }
+    }
+}
+
+impl ops::Deref for FilterSet {
+    type Target = [CanFilter];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<FilterSet> for Vec<CanFilter> {
+    fn from(filters: FilterSet) -> Self {
+        filters.0
+    }
+}
+
+impl FromIterator<CanFilter> for FilterSet {
+    fn from_iter<I: IntoIterator<Item = CanFilter>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_origin() {
+        use libc::{MSG_CONFIRM, MSG_DONTROUTE};
+
+        assert_eq!(FrameOrigin::Remote, FrameOrigin::from_msg_flags(0));
+        assert_eq!(
+            FrameOrigin::Host,
+            FrameOrigin::from_msg_flags(MSG_DONTROUTE)
+        );
+        assert_eq!(
+            FrameOrigin::Local,
+            FrameOrigin::from_msg_flags(MSG_DONTROUTE | MSG_CONFIRM)
+        );
+    }
+
+    #[test]
+    fn test_timestamp_clock() {
+        assert_eq!(TimestampClock::Monotonic, TimestampClock::default());
+
+        let now = SystemTime::now();
+        let since_epoch = now.duration_since(SystemTime::UNIX_EPOCH).unwrap();
+        assert_eq!(since_epoch, TimestampClock::Realtime.convert(now));
+
+        // A timestamp from a moment ago is a moment ago on any clock
+        let ts = now - Duration::from_secs(1);
+        let mono = TimestampClock::Monotonic.convert(ts);
+        let mono_now = TimestampClock::clock_now(libc::CLOCK_MONOTONIC);
+        let diff = mono_now - mono;
+        assert!(diff >= Duration::from_secs(1) && diff < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_filter_set() {
+        use crate::{ExtendedId, StandardId};
+
+        let sid = StandardId::new(0x123).unwrap();
+        let eid = ExtendedId::new(0x12345).unwrap();
+
+        let filters = FilterSet::new()
+            .accept(sid, 0xFFFF_FF00)
+            .accept_exact(eid)
+            .reject(sid, 0x7FF);
+
+        assert_eq!(3, filters.len());
+        assert_eq!(CanFilter::new(0x123, 0x700 | CAN_EFF_FLAG), filters[0]);
+        assert_eq!(
+            CanFilter::new(0x12345 | CAN_EFF_FLAG, CAN_EFF_MASK | CAN_EFF_FLAG),
+            filters[1]
+        );
+        assert_eq!(
+            CanFilter::new_inverted(0x123, 0x7FF | CAN_EFF_FLAG),
+            filters[2]
+        );
+
+        let filters = FilterSet::new().accept_raw(0x100, 0x7FF).unwrap();
+        assert_eq!(CanFilter::new(0x100, 0x7FF | CAN_EFF_FLAG), filters[0]);
+
+        let filters = FilterSet::new().accept_raw(0x1000, 0xFFFF).unwrap();
+        assert_eq!(
+            CanFilter::new(0x1000 | CAN_EFF_FLAG, 0xFFFF | CAN_EFF_FLAG),
+            filters[0]
+        );
+
+        assert!(matches!(
+            FilterSet::new().accept_raw(0x2000_0000, 0),
+            Err(ConstructionError::IDTooLarge)
+        ));
+        assert!(FilterSet::new().is_empty());
+    }
+
+    #[test]
+    fn test_frame_iter_ends() {
+        let (a, _b) =
+            socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::DGRAM, None).unwrap();
+        a.shutdown(std::net::Shutdown::Read).unwrap();
+
+        let sock = CanSocket::from_socket(a);
+        let mut frames = sock.frames();
+        assert!(frames.next().unwrap().is_err());
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn test_can_send_unknown_mtu() {
+        let (a, _b) =
+            socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::DGRAM, None).unwrap();
+        let sock = CanSocket::from_socket(a);
+        assert!(sock.mtu().is_err());
+        assert!(sock.can_send(&CanFrame::from(can_frame_default())));
+    }
+
+    #[test]
+    fn test_read_timeout_max() {
+        let (a, b) =
+            socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::DGRAM, None).unwrap();
+        let sock = CanSocket::from_socket(a);
+        sock.set_read_timeout(Duration::from_millis(1)).unwrap();
+
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            b.send(as_bytes(&can_frame_default())).unwrap();
+        });
+
+        // The socket's own read timeout isn't involved
+        assert!(sock.read_frame_timeout(Duration::MAX).is_ok());
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_software_timestamp() {
+        let (a, b) =
+            socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::DGRAM, None).unwrap();
+        let sock = CanSocket::from_socket(a);
+        sock.set_timestamp(TimestampMode::Software).unwrap();
+
+        let before = SystemTime::now() - Duration::from_millis(1);
+        b.send(as_bytes(&can_frame_default())).unwrap();
+
+        let msg = sock.read_msg().unwrap();
+        assert!(msg.timestamp.unwrap() >= before);
+    }
+
+    #[test]
+    fn test_read_frames_short() {
+        let (a, b) =
+            socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::DGRAM, None).unwrap();
+        let sock = CanSocket::from_socket(a);
+        let frame = can_frame_default();
+
+        b.send(as_bytes(&frame)).unwrap();
+        b.send(as_bytes(&frame)).unwrap();
+        b.send(&[0; 8]).unwrap();
+
+        let mut frames = Vec::new();
+        assert_eq!(2, sock.read_frames(&mut frames, 8).unwrap());
+        assert_eq!(2, frames.len());
+
+        b.send(&[0; 8]).unwrap();
+        assert!(sock.read_frames(&mut frames, 8).is_err());
+        assert_eq!(2, frames.len());
+    }
+
+    #[test]
+    fn test_abi_info() {
+        let info = can_abi_info();
+        assert_eq!(CAN_MTU, info.can_frame_size);
+        assert_eq!(CANFD_MTU, info.canfd_frame_size);
+
+        if let Some(mtu) = info.kernel_max_mtu {
+            assert!([CAN_MTU, CANFD_MTU, CANXL_MTU].contains(&mtu));
+        }
+    }
+}
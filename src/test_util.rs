@@ -0,0 +1,404 @@
+// socketcan/src/test_util.rs
+//
+// Helpers for testing applications that use the SocketCAN library.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Helpers for testing applications that use the SocketCAN library.
+//!
+//! These are meant for use in the unit and integration tests of crates
+//! that are built on top of this one, to cut down on the boilerplate of
+//! creating and comparing frames, and setting up virtual CAN interfaces.
+//! There's also a [`FaultInjector`] to corrupt frames, to exercise the
+//! error handling of an application.
+//!
+//! This module is only available with the "test_util" build feature.
+//! The functions panic on failure, as is expected in a test.
+
+use crate::{CanFrame, ExtendedId, Frame, Id, StandardId};
+use std::fmt::{self, Write};
+
+/// Creates a classic CAN 2.0 data frame from a raw ID and data.
+///
+/// The ID is treated as in [`Frame::from_raw_id`]: values <= 0x7FF are
+/// standard IDs and anything larger is an extended ID.
+///
+/// # Panics
+///
+/// If the ID is larger than a 29-bit extended ID, or there are more than
+/// 8 bytes of data.
+#[track_caller]
+pub fn make_data_frame(id: u32, data: &[u8]) -> CanFrame {
+    match CanFrame::from_raw_id(id, data) {
+        Some(frame) => frame,
+        None => panic!(
+            "invalid data frame: ID {:X} with {} bytes of data",
+            id,
+            data.len()
+        ),
+    }
+}
+
+/// Determines if two frames have the same ID word and data.
+///
+/// The ID word includes the EFF/RTR/ERR flags, so a standard and extended
+/// frame with the same raw ID are not equal.
+pub fn frame_eq<F1, F2>(a: &F1, b: &F2) -> bool
+where
+    F1: Frame,
+    F2: Frame,
+{
+    a.id_word() == b.id_word() && a.dlc() == b.dlc() && a.data() == b.data()
+}
+
+// Describes the differences between two frames, if any.
+fn frame_diff<F1, F2>(expected: &F1, got: &F2) -> Option<String>
+where
+    F1: Frame,
+    F2: Frame,
+{
+    let mut diff = String::new();
+
+    if expected.id_word() != got.id_word() {
+        let _ = writeln!(
+            diff,
+            "  id word: expected {:08X} ({:?}), got {:08X} ({:?})",
+            expected.id_word(),
+            expected.id_flags(),
+            got.id_word(),
+            got.id_flags()
+        );
+    }
+    if expected.dlc() != got.dlc() {
+        let _ = writeln!(
+            diff,
+            "  dlc: expected {}, got {}",
+            expected.dlc(),
+            got.dlc()
+        );
+    }
+    if expected.data() != got.data() {
+        let _ = writeln!(
+            diff,
+            "  data: expected {:02X?}, got {:02X?}",
+            expected.data(),
+            got.data()
+        );
+    }
+
+    if diff.is_empty() {
+        None
+    } else {
+        Some(diff)
+    }
+}
+
+/// Asserts that two frames have the same ID word and data.
+///
+/// On failure, this panics with a message showing both frames and which
+/// of their fields differ.
+#[track_caller]
+pub fn assert_frame_eq<F1, F2>(expected: &F1, got: &F2)
+where
+    F1: Frame + fmt::UpperHex,
+    F2: Frame + fmt::UpperHex,
+{
+    if let Some(diff) = frame_diff(expected, got) {
+        panic!(
+            "frames are not equal\n  expected: {:X}\n       got: {:X}\n{}",
+            expected, got, diff
+        );
+    }
+}
+
+/// Asserts that two sequences of frames are equal, in the same order.
+///
+/// On failure, this panics with a message showing the index of the first
+/// frame that differs, or the difference in the number of frames.
+#[track_caller]
+pub fn assert_frames_eq<F1, F2>(expected: &[F1], got: &[F2])
+where
+    F1: Frame + fmt::UpperHex,
+    F2: Frame + fmt::UpperHex,
+{
+    for (i, (exp, frame)) in expected.iter().zip(got).enumerate() {
+        if let Some(diff) = frame_diff(exp, frame) {
+            panic!(
+                "frames differ at index {}\n  expected: {:X}\n       got: {:X}\n{}",
+                i, exp, frame, diff
+            );
+        }
+    }
+    if expected.len() != got.len() {
+        panic!("expected {} frames, got {}", expected.len(), got.len());
+    }
+}
+
+// ===== VcanGuard =====
+
+/// RAII guard for a temporary virtual CAN interface.
+///
+/// This creates a vcan interface and brings it up, and then deletes the
+/// interface when it is dropped, even if the test fails.
+///
+/// PRIVILEGED: This requires root privilege.
+#[cfg(feature = "netlink")]
+#[derive(Debug)]
+pub struct VcanGuard {
+    name: String,
+    iface: Option<crate::CanInterface>,
+}
+
+#[cfg(feature = "netlink")]
+impl VcanGuard {
+    /// Gets the name of the interface.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the interface for configuration.
+    pub fn interface(&self) -> &crate::CanInterface {
+        // The interface is only taken on drop
+        self.iface.as_ref().unwrap()
+    }
+}
+
+#[cfg(feature = "netlink")]
+impl Drop for VcanGuard {
+    fn drop(&mut self) {
+        if let Some(iface) = self.iface.take() {
+            let _ = iface.delete();
+        }
+    }
+}
+
+/// Creates a temporary virtual CAN interface with the given name.
+///
+/// The interface is created, brought up, and deleted when the returned
+/// guard is dropped. Note that interface names are limited to 15
+/// characters on Linux.
+///
+/// PRIVILEGED: This requires root privilege.
+///
+/// # Panics
+///
+/// If the interface can not be created or brought up.
+#[cfg(feature = "netlink")]
+#[track_caller]
+pub fn vcan_guard(name: &str) -> VcanGuard {
+    let iface = match crate::CanInterface::create_vcan(name, None) {
+        Ok(iface) => iface,
+        Err(err) => panic!("unable to create vcan interface '{}': {}", name, err),
+    };
+    let guard = VcanGuard {
+        name: name.to_string(),
+        iface: Some(iface),
+    };
+    if let Err(err) = guard.interface().bring_up() {
+        panic!("unable to bring up vcan interface '{}': {}", name, err);
+    }
+    guard
+}
+
+// ===== FaultInjector =====
+
+/// Corrupts frames by flipping bits, for testing how an application
+/// handles bad data.
+///
+/// Each bit of the data is flipped with the configured probability, and
+/// optionally the bits of the ID as well. Specific data bits can also be
+/// set to always be flipped.
+///
+/// The random numbers come from a small, seeded generator, so the same
+/// seed and settings applied to the same sequence of frames always produce
+/// the same corruption. A failing test can then be reproduced by reusing
+/// its seed.
+///
+/// ```
+/// use socketcan::test_util::{make_data_frame, FaultInjector};
+///
+/// let mut inj = FaultInjector::new(42).flip_probability(0.01);
+/// let frame = inj.mutate(&make_data_frame(0x123, &[0; 8]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FaultInjector {
+    state: u64,
+    probability: f64,
+    flip_id: bool,
+    bits: Vec<usize>,
+}
+
+impl FaultInjector {
+    /// Creates an injector with the random number generator seeded from
+    /// the value.
+    ///
+    /// By default, no bits are flipped.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            probability: 0.0,
+            flip_id: false,
+            bits: Vec::new(),
+        }
+    }
+
+    /// Sets the probability, from 0.0 to 1.0, of each bit being flipped.
+    pub fn flip_probability(mut self, probability: f64) -> Self {
+        self.probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets whether the bits of the ID can also be flipped.
+    ///
+    /// The ID bits are flipped randomly with the same probability as the
+    /// data bits, within the width of the ID (11 or 29 bits).
+    pub fn flip_id(mut self, on: bool) -> Self {
+        self.flip_id = on;
+        self
+    }
+
+    /// Sets specific data bits that are always flipped.
+    ///
+    /// Bit 0 is the least significant bit of the first data byte. Bits past
+    /// the end of the data of a frame are ignored.
+    pub fn flip_bits(mut self, bits: &[usize]) -> Self {
+        self.bits = bits.to_vec();
+        self
+    }
+
+    /// Returns a copy of the frame with bits flipped.
+    pub fn mutate<F: Frame + Clone>(&mut self, frame: &F) -> F {
+        let mut frame = frame.clone();
+
+        let mut data = frame.data().to_vec();
+        for i in 0..8 * data.len() {
+            if self.chance() || self.bits.contains(&i) {
+                data[i / 8] ^= 1 << (i % 8);
+            }
+        }
+        // The length is unchanged, so this can't fail
+        let _ = frame.set_data(&data);
+
+        if self.flip_id {
+            let mut id = frame.raw_id();
+            let nbits = if frame.is_extended() { 29 } else { 11 };
+            for i in 0..nbits {
+                if self.chance() {
+                    id ^= 1 << i;
+                }
+            }
+            // Keep the same type of ID, whatever its new value
+            let id: Option<Id> = if frame.is_extended() {
+                ExtendedId::new(id).map(Id::from)
+            } else {
+                StandardId::new(id as u16).map(Id::from)
+            };
+            if let Some(id) = id {
+                frame.set_id(id);
+            }
+        }
+        frame
+    }
+
+    // Determines if a bit should be flipped.
+    fn chance(&mut self) -> bool {
+        self.probability > 0.0 && self.next_f64() < self.probability
+    }
+
+    // Gets the next random number in the range [0, 1), from a splitmix64
+    // generator.
+    fn next_f64(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CanFdFrame, CanRemoteFrame, EmbeddedFrame};
+
+    #[test]
+    fn test_frame_eq() {
+        let frame = make_data_frame(0x123, &[1, 2, 3]);
+        assert_frame_eq(&frame, &make_data_frame(0x123, &[1, 2, 3]));
+
+        // Different types, same contents
+        let fdframe = CanFdFrame::from_raw_id(0x123, &[1, 2, 3]).unwrap();
+        assert!(frame_eq(&frame, &fdframe));
+
+        assert!(!frame_eq(&frame, &make_data_frame(0x124, &[1, 2, 3])));
+        assert!(!frame_eq(&frame, &make_data_frame(0x123, &[1, 2])));
+
+        let id = StandardId::new(0x123).unwrap();
+        let remote = CanRemoteFrame::new_remote(id, 3).unwrap();
+        assert!(!frame_eq(&frame, &remote));
+    }
+
+    #[test]
+    #[should_panic(expected = "data: expected [01, 02, 03], got [01, 02, 04]")]
+    fn test_assert_frame_eq_fails() {
+        assert_frame_eq(
+            &make_data_frame(0x123, &[1, 2, 3]),
+            &make_data_frame(0x123, &[1, 2, 4]),
+        );
+    }
+
+    #[test]
+    fn test_frames_eq() {
+        let frames = [make_data_frame(0x1, &[1]), make_data_frame(0x2, &[2])];
+        assert_frames_eq(&frames, &frames);
+    }
+
+    #[test]
+    #[should_panic(expected = "frames differ at index 0")]
+    fn test_frames_out_of_order() {
+        let frames = [make_data_frame(0x1, &[1]), make_data_frame(0x2, &[2])];
+        let reversed = [frames[1], frames[0]];
+        assert_frames_eq(&frames, &reversed);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid data frame")]
+    fn test_make_data_frame_fails() {
+        make_data_frame(0x123, &[0; 9]);
+    }
+
+    #[test]
+    fn test_fault_injector() {
+        let frame = make_data_frame(0x123, &[0; 8]);
+
+        // No flipping by default
+        let mut inj = FaultInjector::new(1);
+        assert_frame_eq(&frame, &inj.mutate(&frame));
+
+        let mut inj = FaultInjector::new(1).flip_bits(&[0, 9, 100]);
+        assert_frame_eq(
+            &make_data_frame(0x123, &[0x01, 0x02, 0, 0, 0, 0, 0, 0]),
+            &inj.mutate(&frame),
+        );
+
+        // The same seed gives the same results
+        let mut a = FaultInjector::new(7).flip_probability(0.2).flip_id(true);
+        let mut b = FaultInjector::new(7).flip_probability(0.2).flip_id(true);
+        for _ in 0..10 {
+            assert_frame_eq(&a.mutate(&frame), &b.mutate(&frame));
+        }
+
+        let mutated = a.mutate(&frame);
+        assert!(!frame_eq(&frame, &mutated));
+        assert!(!mutated.is_extended());
+        assert_eq!(8, mutated.dlc());
+    }
+}
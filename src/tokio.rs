@@ -27,7 +27,8 @@
 //! }
 //! ```
 use crate::{
-    CanAddr, CanAnyFrame, CanFdFrame, CanFrame, Error, IoResult, Result, Socket, SocketOptions,
+    CanAddr, CanAnyFrame, CanFdFrame, CanFrame, CanMsg, Error, IoResult, Result, Socket,
+    SocketOptions,
 };
 use futures::{prelude::*, ready, task::Context};
 use mio::{event, unix::SourceFd, Interest, Registry, Token};
@@ -144,6 +145,25 @@ impl<T: Socket + From<OwnedFd>> AsyncCanSocket<T> {
     }
 }
 
+impl<T: Socket> AsyncCanSocket<T> {
+    /// Reads the next frame from the socket asynchronously.
+    ///
+    /// This waits until the socket is readable, and then reads the frame.
+    /// If the read would block, such as when another task got the frame
+    /// first, the readiness is cleared and it goes back to waiting. This
+    /// is an alternative to using the socket as a `Stream`, when only a
+    /// single frame is needed at a time.
+    pub async fn read_frame(&self) -> Result<T::FrameType> {
+        loop {
+            let mut ready_guard = self.0.readable().await?;
+            match ready_guard.try_io(|inner| inner.get_ref().get_ref().read_frame()) {
+                Ok(result) => return result.map_err(|e| e.into()),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
 impl<T: Socket> SocketOptions for AsyncCanSocket<T> {}
 
 impl<T: Socket> AsRawFd for AsyncCanSocket<T> {
@@ -166,6 +186,32 @@ impl CanSocket {
             frame,
         })
     }
+
+    /// Reads a frame from the socket asynchronously, along with all the
+    /// metadata that the kernel reports with it.
+    ///
+    /// This is the same as the blocking [`crate::CanSocket::read_msg`], and
+    /// the timestamp and dropped count are enabled on the socket in the
+    /// same way.
+    pub async fn read_msg(&self) -> IoResult<CanMsg> {
+        loop {
+            let mut ready_guard = self.0.readable().await?;
+            match ready_guard.try_io(|inner| inner.get_ref().get_ref().read_msg()) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Converts the socket into a stream of received frames.
+    ///
+    /// The socket itself implements `Stream`, so this is mainly a
+    /// convenience to make the intent clear and to hide the `Sink` half.
+    /// Frames are only read from the socket as the stream is polled, and
+    /// dropping the stream closes the socket and stops receiving.
+    pub fn into_stream(self) -> impl Stream<Item = Result<CanFrame>> {
+        self
+    }
 }
 
 impl Stream for CanSocket {
@@ -220,6 +266,13 @@ impl CanFdSocket {
             frame,
         })
     }
+
+    /// Converts the socket into a stream of received frames.
+    ///
+    /// See [`CanSocket::into_stream`]
+    pub fn into_stream(self) -> impl Stream<Item = Result<CanAnyFrame>> {
+        self
+    }
 }
 
 /// A Future representing the eventual writing of a CanFdFrame to the socket.
@@ -402,6 +455,35 @@ mod tests {
         Ok(())
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn test_into_stream() -> Result<()> {
+        let socket1 = CanSocket::open("vcan0").unwrap();
+        let socket2 = CanSocket::open("vcan0").unwrap();
+
+        let stream = socket2
+            .into_stream()
+            .filter_map(|res| future::ready(res.ok()))
+            .filter(|frame| future::ready(frame.raw_id() == 0x02))
+            .take(1)
+            .collect::<Vec<_>>();
+
+        let send_frames = async {
+            write_frame(&socket1).await?;
+            let frame = CanFrame::from_raw_id(0x02, &[0u8]).unwrap();
+            socket1.write_frame(frame)?.await?;
+            Ok::<(), Error>(())
+        };
+
+        let (frames, frame_send_r) = futures::future::join(stream, send_frames).await;
+        frame_send_r?;
+
+        assert_eq!(1, frames.len());
+        assert_eq!(0x02, frames[0].raw_id());
+
+        Ok(())
+    }
+
     #[serial]
     #[tokio::test]
     async fn test_sink_stream_fd() -> Result<()> {
@@ -441,4 +523,18 @@ mod tests {
 
         Ok(())
     }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_read_frame() -> Result<()> {
+        let socket1 = CanSocket::open("vcan0").unwrap();
+        let socket2 = CanSocket::open("vcan0").unwrap();
+
+        let frame = CanFrame::from_raw_id(0x05, &[1, 2]).unwrap();
+        let (sent, recvd) = future::join(socket1.write_frame(frame)?, socket2.read_frame()).await;
+        sent?;
+        assert_eq!(0x05, recvd?.raw_id());
+
+        Ok(())
+    }
 }
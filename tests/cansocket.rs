@@ -11,12 +11,17 @@
 
 #[cfg(feature = "vcan_tests")]
 use socketcan::{
-    frame::{ERR_MASK_ALL, ERR_MASK_NONE},
-    CanFrame, CanSocket, EmbeddedFrame, ShouldRetry, Socket, SocketOptions, StandardId,
+    frame::{FdFlags, ERR_MASK_ALL, ERR_MASK_NONE},
+    CanAnyFrame, CanDataFrame, CanErrorFilter, CanFdFrame, CanFdSocket, CanFilter, CanFrame,
+    CanSocket, CanSocketBuilder, EmbeddedFrame, Frame, ShouldRetry, Socket, SocketOptions,
+    StandardId,
 };
 
 #[cfg(feature = "vcan_tests")]
-use std::time;
+use std::{
+    os::unix::io::{FromRawFd, IntoRawFd, OwnedFd},
+    time,
+};
 
 // The virtual CAN interface to use for tests.
 #[cfg(feature = "vcan_tests")]
@@ -46,6 +51,9 @@ fn vcan_set_error_mask() {
     let sock = CanSocket::open(VCAN).unwrap();
     sock.set_error_mask(ERR_MASK_ALL).unwrap();
     sock.set_error_mask(ERR_MASK_NONE).unwrap();
+
+    let filter = CanErrorFilter::none().bus_off().no_ack();
+    sock.set_can_error_filter(filter).unwrap();
 }
 
 #[test]
@@ -62,6 +70,31 @@ fn vcan_enable_own_loopback() {
     sock.read_frame().unwrap();
 }
 
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_builder_no_stray_frames() {
+    let writer = CanSocket::open(VCAN).unwrap();
+
+    // The filter is in place before the socket is bound to the interface,
+    // so nothing but the filtered ID should ever reach the queue.
+    let reader = CanSocketBuilder::new()
+        .filters(&[CanFilter::new(0x100, 0x7FF)])
+        .open(VCAN)
+        .unwrap();
+    reader
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    for id in [0x123, 0x456, 0x100] {
+        let frame = CanFrame::from_raw_id(id, &[1, 2, 3]).unwrap();
+        writer.write_frame(&frame).unwrap();
+    }
+
+    let frame = reader.read_frame().unwrap();
+    assert_eq!(0x100, frame.raw_id());
+    assert!(reader.read_frame().should_retry());
+}
+
 // #[test]
 // fn vcan_set_down() {
 //     let can_if = CanInterface::open(VCAN).unwrap();
@@ -74,10 +107,754 @@ fn vcan_test_nonblocking() {
     let sock = CanSocket::open(VCAN).unwrap();
     // Filter out _any_ traffic
     sock.set_filter_drop_all().unwrap();
+    assert!(!sock.nonblocking().unwrap());
     sock.set_nonblocking(true).unwrap();
+    assert!(sock.nonblocking().unwrap());
 
     // no timeout set, but should return immediately
     assert!(sock.read_frame().should_retry());
+
+    // the mode is inherited by a socket made from the raw descriptor
+    let sock = CanSocket::from(unsafe { OwnedFd::from_raw_fd(sock.into_raw_fd()) });
+    assert!(sock.nonblocking().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_nonblocking_would_block() {
+    use std::io::ErrorKind;
+
+    let sock = CanSocket::open(VCAN).unwrap();
+    sock.set_filter_drop_all().unwrap();
+    sock.set_nonblocking(true).unwrap();
+    assert_eq!(ErrorKind::WouldBlock, sock.read_frame().unwrap_err().kind());
+
+    let sock = CanFdSocket::open(VCAN).unwrap();
+    sock.set_filter_drop_all().unwrap();
+    sock.set_nonblocking(true).unwrap();
+    assert_eq!(ErrorKind::WouldBlock, sock.read_frame().unwrap_err().kind());
+
+    // Back in blocking mode, a read with a timeout still times out
+    sock.set_nonblocking(false).unwrap();
+    sock.set_read_timeout(time::Duration::from_millis(10))
+        .unwrap();
+    assert!(sock.read_frame().should_retry());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_fd_mixed_send() {
+    let fdsock = CanFdSocket::open(VCAN).unwrap();
+    let sock = CanFdSocket::open(VCAN).unwrap();
+    sock.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+
+    // A classic frame sent on an FD socket is received as a classic frame,
+    // which it could only be if it was written as a 16-byte can_frame.
+    let frame = CanDataFrame::new(id, &[1, 2, 3]).unwrap();
+    fdsock.write_frame(&frame).unwrap();
+    match sock.read_frame().unwrap() {
+        CanAnyFrame::Normal(rx) => assert_eq!(frame.data(), rx.data()),
+        rx => panic!("Expected a classic frame, got: {:X}", rx),
+    }
+
+    let frame = CanFdFrame::new(id, &[1, 2, 3]).unwrap();
+    fdsock.write_frame(&frame).unwrap();
+    match sock.read_frame().unwrap() {
+        CanAnyFrame::Fd(rx) => assert_eq!(frame.data(), rx.data()),
+        rx => panic!("Expected an FD frame, got: {:X}", rx),
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_deadline() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    // Filter out _any_ traffic
+    sock.set_filter_drop_all().unwrap();
+
+    let start = time::Instant::now();
+    let deadline = start + time::Duration::from_millis(100);
+    assert!(sock.read_frame_deadline(deadline).unwrap().is_none());
+    assert!(time::Instant::now() >= deadline);
+
+    // A deadline in the past returns immediately
+    assert!(sock.read_frame_deadline(start).unwrap().is_none());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_frame_origin() {
+    use socketcan::FrameOrigin;
+
+    let sock = CanSocket::open(VCAN).unwrap();
+    let other = CanSocket::open(VCAN).unwrap();
+    sock.set_recv_own_msgs(true).unwrap();
+    sock.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let frame = CanFrame::from_raw_id(0x123, &[1, 2, 3]).unwrap();
+
+    sock.write_frame(&frame).unwrap();
+    let (_, origin) = sock.read_frame_with_origin().unwrap();
+    assert_eq!(FrameOrigin::Local, origin);
+
+    other.write_frame(&frame).unwrap();
+    let (_, origin) = sock.read_frame_with_origin().unwrap();
+    assert_eq!(FrameOrigin::Host, origin);
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_bundle() {
+    use socketcan::CanBundle;
+
+    let bundle = CanBundle::<CanSocket>::open(&[VCAN, VCAN]).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let frame = CanFrame::from_raw_id(0x123, &[1, 2, 3]).unwrap();
+
+    assert_eq!(0, bundle.write_frame_round_robin(&frame).unwrap());
+    assert_eq!(1, bundle.write_frame_round_robin(&frame).unwrap());
+    assert_eq!(0, bundle.write_frame_round_robin(&frame).unwrap());
+    for _ in 0..3 {
+        assert_eq!(0x123, rx.read_frame().unwrap().raw_id());
+    }
+
+    bundle.write_frame_all(&frame).unwrap();
+    for _ in 0..2 {
+        assert_eq!(0x123, rx.read_frame().unwrap().raw_id());
+    }
+
+    rx.write_frame(&frame).unwrap();
+    let (idx, rx_frame) = bundle
+        .read_frame_any_timeout(time::Duration::from_millis(100))
+        .unwrap();
+    assert!(idx < 2);
+    assert_eq!(0x123, rx_frame.raw_id());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_get_filters() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    assert_eq!(vec![CanFilter::new(0, 0)], sock.filters().unwrap());
+
+    let filters = [
+        CanFilter::new(0x123, 0x7FF),
+        CanFilter::new_inverted(0x100, 0x700),
+        CanFilter::new(0x18FF_0000 | libc::CAN_EFF_FLAG, 0x1FFF_0000),
+    ];
+    sock.set_filters(&filters).unwrap();
+    assert_eq!(filters.to_vec(), sock.filters().unwrap());
+
+    // More than fit in the initial buffer
+    let filters: Vec<_> = (0..100).map(|id| CanFilter::new(id, 0x7FF)).collect();
+    sock.set_filters(&filters).unwrap();
+    assert_eq!(filters, sock.filters().unwrap());
+
+    sock.set_filter_drop_all().unwrap();
+    assert!(sock.filters().unwrap().is_empty());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_read_data_frame() {
+    let tx = CanSocket::open(VCAN).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_error_filter_accept_all().unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let remote = CanFrame::new_remote(StandardId::new(0x100).unwrap(), 2).unwrap();
+    let data = CanFrame::from_raw_id(0x123, &[1, 2, 3]).unwrap();
+
+    tx.write_frame(&remote).unwrap();
+    tx.write_frame(&data).unwrap();
+
+    let frame = rx.read_data_frame().unwrap();
+    assert_eq!(0x123, frame.raw_id());
+    assert_eq!(&[1, 2, 3], frame.data());
+
+    assert!(rx.read_data_frame().unwrap_err().should_retry());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_write_and_drain() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    let timeout = time::Duration::from_millis(100);
+
+    let frame = CanFrame::from_raw_id(0x123, &[1, 2, 3]).unwrap();
+
+    // Without our own messages, the echo never comes back
+    let err = sock.write_frame_and_drain(&frame, timeout).unwrap_err();
+    assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+
+    sock.set_recv_own_msgs(true).unwrap();
+    for _ in 0..4 {
+        sock.write_frame(&frame).unwrap();
+    }
+    sock.write_frame_and_drain(&frame, timeout).unwrap();
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_frame_id_type() {
+    let tx = CanSocket::open(VCAN).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let sff = CanFrame::from_raw_id(0x123, &[1]).unwrap();
+    let eff = CanFrame::from_raw_id(0x12345, &[2]).unwrap();
+
+    tx.write_frame(&sff).unwrap();
+    tx.write_frame(&eff).unwrap();
+    assert_eq!(0x123, rx.read_standard_frame().unwrap().raw_id());
+    match rx.read_standard_frame() {
+        Err(socketcan::Error::UnexpectedFrameType(frame)) => assert_eq!(0x12345, frame.raw_id()),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+
+    tx.write_frame(&sff).unwrap();
+    assert!(rx.read_extended_frame().is_err());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_read_frame_with() {
+    let tx = CanSocket::open(VCAN).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let frame = CanFrame::from_raw_id(0x123, &[1, 2, 3]).unwrap();
+    tx.write_frame(&frame).unwrap();
+
+    let sum = rx
+        .read_frame_with(|frame| frame.data().iter().map(|&b| b as u32).sum::<u32>())
+        .unwrap();
+    assert_eq!(6, sum);
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_schedule() {
+    use socketcan::Schedule;
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let ms = time::Duration::from_millis;
+    let sched = Schedule::new()
+        .with(ms(20), CanFrame::from_raw_id(0x2, &[]).unwrap())
+        .with(ms(0), CanFrame::from_raw_id(0x1, &[]).unwrap());
+
+    let start = time::Instant::now();
+    sched.run(&tx, start).unwrap();
+    assert!(start.elapsed() >= ms(20));
+
+    assert_eq!(0x1, rx.read_frame().unwrap().raw_id());
+    assert_eq!(0x2, rx.read_frame().unwrap().raw_id());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_write_if_changed() {
+    use socketcan::monitor::ChangeFilter;
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let mut filter = ChangeFilter::new();
+    let frame1 = CanFrame::from_raw_id(0x123, &[1]).unwrap();
+    let frame2 = CanFrame::from_raw_id(0x123, &[2]).unwrap();
+
+    assert!(tx.write_frame_if_changed(&frame1, &mut filter).unwrap());
+    assert!(!tx.write_frame_if_changed(&frame1, &mut filter).unwrap());
+    assert!(tx.write_frame_if_changed(&frame2, &mut filter).unwrap());
+
+    assert_eq!(&[1], rx.read_frame().unwrap().data());
+    assert_eq!(&[2], rx.read_frame().unwrap().data());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_read_msg() {
+    use socketcan::{FrameOrigin, TimestampClock};
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+    rx.set_timestamp_clock(TimestampClock::Realtime).unwrap();
+    rx.set_rxq_overflow(true).unwrap();
+
+    let frame = CanFrame::from_raw_id(0x123, &[1, 2, 3]).unwrap();
+    tx.write_frame(&frame).unwrap();
+
+    let msg = rx.read_msg().unwrap();
+    assert_eq!(frame.id_word(), msg.frame.id_word());
+    assert_eq!(FrameOrigin::Host, msg.origin);
+    assert!(msg.timestamp.is_some());
+    assert!(msg.ifindex > 0);
+    assert_eq!(Some(0), msg.dropped);
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_timestamp_mode() {
+    use socketcan::TimestampMode;
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_filters(&[CanFilter::new(0x124, 0x7FF)]).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let frame = CanFrame::from_raw_id(0x124, &[1]).unwrap();
+
+    for mode in [TimestampMode::Software, TimestampMode::Hardware] {
+        rx.set_timestamp(mode).unwrap();
+        let before = time::SystemTime::now();
+        tx.write_frame(&frame).unwrap();
+
+        // vcan has no hardware clock, so both are software timestamps
+        let (_, ts) = rx.read_frame_with_timestamp().unwrap();
+        assert!(ts >= before - time::Duration::from_millis(1));
+    }
+
+    rx.set_timestamp(TimestampMode::None).unwrap();
+    tx.write_frame(&frame).unwrap();
+    assert!(rx.read_msg().unwrap().timestamp.is_none());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_write_burst() {
+    let tx = CanSocket::open(VCAN).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let frames: Vec<_> = (1..=4)
+        .map(|id| CanFrame::from_raw_id(id, &[id as u8]).unwrap())
+        .collect();
+
+    assert_eq!(0, tx.write_burst(&[]).unwrap());
+    assert_eq!(4, tx.write_burst(&frames).unwrap());
+
+    for id in 1..=4 {
+        assert_eq!(id, rx.read_frame().unwrap().raw_id());
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_fd_force_classic_dlc() {
+    let tx = CanFdSocket::open(VCAN).unwrap();
+    let rx = CanFdSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let short = CanFdFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+    let long = CanFdFrame::new(StandardId::new(0x123).unwrap(), &[0; 12]).unwrap();
+
+    tx.write_frame(&short).unwrap();
+    assert!(matches!(rx.read_frame().unwrap(), CanAnyFrame::Fd(_)));
+
+    tx.set_force_classic_dlc(true);
+    assert!(tx.force_classic_dlc());
+
+    tx.write_frame(&short).unwrap();
+    match rx.read_frame().unwrap() {
+        CanAnyFrame::Normal(frame) => assert_eq!(&[1, 2, 3], frame.data()),
+        frame => panic!("Expected a classic frame, got {:?}", frame),
+    }
+
+    tx.write_frame(&long).unwrap();
+    assert!(matches!(rx.read_frame().unwrap(), CanAnyFrame::Fd(_)));
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_read_raw_bytes() {
+    let tx = CanFdSocket::open(VCAN).unwrap();
+    let rx = CanFdSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let mut small = [0u8; 16];
+    assert!(rx.read_raw_bytes(&mut small).is_err());
+
+    let mut buf = [0u8; 72];
+    let frame = CanFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+    tx.write_frame(&frame).unwrap();
+    assert_eq!(16, rx.read_raw_bytes(&mut buf).unwrap());
+    assert_eq!(0x123, u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]));
+    assert_eq!(3, buf[4]);
+    assert_eq!(&[1, 2, 3], &buf[8..11]);
+
+    let frame = CanFdFrame::new(StandardId::new(0x123).unwrap(), &[0; 12]).unwrap();
+    tx.write_frame(&frame).unwrap();
+    assert_eq!(72, rx.read_raw_bytes(&mut buf).unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_builder_recv_own_msgs() {
+    let timeout = time::Duration::from_millis(100);
+
+    let sender = CanSocketBuilder::new()
+        .loopback(true)
+        .recv_own_msgs(true)
+        .open(VCAN)
+        .unwrap();
+    let quiet = CanSocketBuilder::new().loopback(true).open(VCAN).unwrap();
+    let listener = CanSocket::open(VCAN).unwrap();
+
+    for sock in [&sender, &quiet, &listener] {
+        sock.set_read_timeout(timeout).unwrap();
+    }
+
+    // The sender receives its own frame, along with the other socket.
+    let frame = CanFrame::new(StandardId::new(0x111).unwrap(), &[1]).unwrap();
+    sender.write_frame(&frame).unwrap();
+    assert_eq!(0x111, sender.read_frame().unwrap().raw_id());
+    assert_eq!(0x111, listener.read_frame().unwrap().raw_id());
+    assert_eq!(0x111, quiet.read_frame().unwrap().raw_id());
+
+    // By default, a socket doesn't see its own frames.
+    let frame = CanFrame::new(StandardId::new(0x222).unwrap(), &[2]).unwrap();
+    quiet.write_frame(&frame).unwrap();
+    assert_eq!(0x222, listener.read_frame().unwrap().raw_id());
+    assert_eq!(0x222, sender.read_frame().unwrap().raw_id());
+    assert!(quiet.read_frame().is_err());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_bcm_send_periodic() {
+    use socketcan::CanBcmSocket;
+
+    let id = StandardId::new(0x123).unwrap();
+    let sock = CanSocket::open(VCAN).unwrap();
+    sock.set_filters(&[CanFilter::new(0x123, 0x7FF)]).unwrap();
+    sock.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let bcm = CanBcmSocket::open(VCAN).unwrap();
+    let frame = CanDataFrame::new(StandardId::new(0x456).unwrap(), &[1, 2, 3]).unwrap();
+    bcm.send_periodic(id, &frame, time::Duration::from_millis(10))
+        .unwrap();
+
+    // The task sends the frame with its own ID, again and again
+    for _ in 0..3 {
+        let rx = sock.read_frame().unwrap();
+        assert_eq!(0x123, rx.raw_id());
+        assert_eq!(&[1, 2, 3], rx.data());
+    }
+
+    bcm.remove_periodic(id).unwrap();
+    bcm.filter_rx_changes(id, &[0xFF]).unwrap();
+    bcm.remove_rx_filter(id).unwrap();
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_read_frame_with_timestamp() {
+    use socketcan::TimestampClock;
+
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_filters(&[CanFilter::new(0x321, 0x7FF)]).unwrap();
+    rx.set_timestamp_clock(TimestampClock::Realtime).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    let frame = CanFrame::new(StandardId::new(0x321).unwrap(), &[1]).unwrap();
+
+    let before = time::SystemTime::now();
+    tx.write_frame(&frame).unwrap();
+    let (rx_frame, ts) = rx.read_frame_with_timestamp().unwrap();
+
+    assert_eq!(frame.data(), rx_frame.data());
+    assert!(ts >= before - time::Duration::from_millis(1));
+    assert!(ts <= time::SystemTime::now());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_filter_set() {
+    use socketcan::FilterSet;
+
+    let filters = FilterSet::new()
+        .accept_exact(StandardId::new(0x100).unwrap())
+        .accept(StandardId::new(0x200).unwrap(), 0x700);
+
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_filters(&filters).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    for id in [0x300, 0x101, 0x100, 0x2AB] {
+        let frame = CanFrame::new(StandardId::new(id).unwrap(), &[]).unwrap();
+        tx.write_frame(&frame).unwrap();
+    }
+
+    assert_eq!(0x100, rx.read_frame().unwrap().raw_id());
+    assert_eq!(0x2AB, rx.read_frame().unwrap().raw_id());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_join_filters() {
+    use socketcan::FilterSet;
+
+    let filters = FilterSet::new()
+        .accept(StandardId::new(0x100).unwrap(), 0x700)
+        .reject(StandardId::new(0x180).unwrap(), 0x7F0);
+
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_filters(&filters).unwrap();
+    rx.set_join_filters(true).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    for id in [0x181, 0x200, 0x1F0] {
+        let frame = CanFrame::new(StandardId::new(id).unwrap(), &[]).unwrap();
+        tx.write_frame(&frame).unwrap();
+    }
+
+    assert_eq!(0x1F0, rx.read_frame().unwrap().raw_id());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_disable_loopback() {
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    tx.set_loopback(false).unwrap();
+    tx.set_recv_own_msgs(true).unwrap();
+
+    let frame = CanFrame::new(StandardId::new(0x123).unwrap(), &[1]).unwrap();
+    tx.write_frame(&frame).unwrap();
+
+    // With loopback off, no local socket sees the frame, even the sender
+    assert!(rx.read_frame().should_retry());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_read_frame_timeout() {
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_filters(&[CanFilter::new(0x456, 0x7FF)]).unwrap();
+
+    let err = rx.read_frame_timeout(time::Duration::ZERO).unwrap_err();
+    assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+    assert!(matches!(
+        socketcan::Error::from(err),
+        socketcan::Error::TimedOut
+    ));
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    let frame = CanFrame::new(StandardId::new(0x456).unwrap(), &[1]).unwrap();
+    tx.write_frame(&frame).unwrap();
+
+    let rx_frame = rx
+        .read_frame_timeout(time::Duration::from_millis(100))
+        .unwrap();
+    assert_eq!(frame.data(), rx_frame.data());
+    assert_eq!(None, rx.read_timeout().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_read_frames() {
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_filters(&[CanFilter::new(0x567, 0x7FF)]).unwrap();
+    rx.set_nonblocking(true).unwrap();
+
+    let mut frames = Vec::new();
+    assert!(rx.read_frames(&mut frames, 8).should_retry());
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    let burst: Vec<_> = (0u8..3)
+        .map(|i| CanFrame::new(StandardId::new(0x567).unwrap(), &[i]).unwrap())
+        .collect();
+    assert_eq!(3, tx.write_burst(&burst).unwrap());
+
+    assert_eq!(2, rx.read_frames(&mut frames, 2).unwrap());
+    assert_eq!(1, rx.read_frames(&mut frames, 8).unwrap());
+
+    let data: Vec<_> = frames.iter().map(|frame| frame.data()[0]).collect();
+    assert_eq!(vec![0, 1, 2], data);
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_frames_iter() {
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_filters(&[CanFilter::new(0x678, 0x7FF)]).unwrap();
+    rx.set_nonblocking(true).unwrap();
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    for i in 0u8..3 {
+        let frame = CanFrame::new(StandardId::new(0x678).unwrap(), &[i]).unwrap();
+        tx.write_frame(&frame).unwrap();
+    }
+
+    let data: Vec<_> = rx
+        .frames()
+        .map_while(Result::ok)
+        .map(|frame| frame.data()[0])
+        .collect();
+    assert_eq!(vec![0, 1, 2], data);
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_fd_flags() {
+    let tx = CanFdSocket::open(VCAN).unwrap();
+    let rx = CanFdSocket::open(VCAN).unwrap();
+    rx.set_filters(&[CanFilter::new(0x234, 0x7FF)]).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    for flags in [FdFlags::empty(), FdFlags::BRS, FdFlags::ESI, FdFlags::all()] {
+        let frame =
+            CanFdFrame::with_flags(StandardId::new(0x234).unwrap(), &[1, 2, 3], flags).unwrap();
+        tx.write_frame(&frame).unwrap();
+
+        match rx.read_frame().unwrap() {
+            CanAnyFrame::Fd(frame) => {
+                assert_eq!(flags, frame.flags());
+                assert_eq!(flags.contains(FdFlags::BRS), frame.is_brs());
+                assert_eq!(flags.contains(FdFlags::ESI), frame.is_esi());
+            }
+            frame => panic!("Expected an FD frame, got {:?}", frame),
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_tx_priority() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    sock.set_tx_priority(6).unwrap();
+
+    let frame = CanFrame::new(StandardId::new(0x345).unwrap(), &[1]).unwrap();
+    sock.write_frame(&frame).unwrap();
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_write_frame_at() {
+    let tx = CanSocket::open(VCAN).unwrap();
+    let rx = CanSocket::open(VCAN).unwrap();
+    rx.set_filters(&[CanFilter::new(0x456, 0x7FF)]).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let frame = CanFrame::new(StandardId::new(0x456).unwrap(), &[1, 2]).unwrap();
+    let now = time::SystemTime::now();
+
+    let err = tx.write_frame_at(&frame, now).unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+
+    // Without an etf qdisc on the interface, the frame goes out right away
+    tx.set_txtime(libc::CLOCK_MONOTONIC, 0).unwrap();
+    tx.write_frame_at(&frame, now).unwrap();
+    assert_eq!(&[1, 2], rx.read_frame().unwrap().data());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_open_all() {
+    let rx = CanSocket::open_all().unwrap();
+    rx.set_filters(&[CanFilter::new(0x567, 0x7FF)]).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let tx = CanSocket::open(VCAN).unwrap();
+    let frame = CanFrame::new(StandardId::new(0x567).unwrap(), &[1]).unwrap();
+    tx.write_frame(&frame).unwrap();
+
+    let msg = rx.read_msg().unwrap();
+    assert_eq!(&[1], msg.frame.data());
+    assert_ne!(0, msg.ifindex);
+
+    assert!(rx.write_frame(&frame).is_err());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_isotp_transfer() {
+    use socketcan::CanIsoTpSocket;
+
+    let req = StandardId::new(0x7E0).unwrap();
+    let resp = StandardId::new(0x7E8).unwrap();
+
+    let tx = CanIsoTpSocket::bind(VCAN, req, resp).unwrap();
+    let rx = CanIsoTpSocket::bind(VCAN, resp, req).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(500))
+        .unwrap();
+
+    // Too long for a single frame, so it's segmented and reassembled
+    let data: Vec<u8> = (0..100).collect();
+    tx.write(&data).unwrap();
+    assert_eq!(data, rx.read().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_j1939_transfer() {
+    use socketcan::j1939::{J1939Socket, J1939_NO_NAME, J1939_NO_PGN};
+
+    const PGN: u32 = 0x0EF00;
+
+    let tx = J1939Socket::open(VCAN, J1939_NO_NAME, J1939_NO_PGN, 0x80).unwrap();
+    let rx = J1939Socket::open(VCAN, J1939_NO_NAME, J1939_NO_PGN, 0x30).unwrap();
+    rx.set_read_timeout(time::Duration::from_millis(500))
+        .unwrap();
+
+    // Sent by the kernel with the transport protocol
+    let data: Vec<u8> = (0..20).collect();
+    tx.send_to(PGN, 0x30, &data).unwrap();
+
+    let (src, pgn, rx_data) = rx.recv_from().unwrap();
+    assert_eq!(0x80, src.addr);
+    assert_eq!(PGN, pgn);
+    assert_eq!(data, rx_data);
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_mtu() {
+    let sock = CanFdSocket::open(VCAN).unwrap();
+    let mtu = sock.mtu().unwrap();
+    assert!(mtu == socketcan::socket::CAN_MTU || mtu == socketcan::socket::CANFD_MTU);
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanAnyFrame::from(CanDataFrame::new(id, &[1, 2, 3]).unwrap());
+    assert!(sock.can_send(&frame));
+
+    let frame = CanAnyFrame::from(CanFdFrame::new(id, &[1, 2, 3]).unwrap());
+    assert_eq!(mtu == socketcan::socket::CANFD_MTU, sock.can_send(&frame));
 }
 
 /*